@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use rosarioc::OptimizationLevel;
+
+pub const USAGE: &str = "\
+Usage: rosario <command> [options] <file.ros> [-- <program arguments>]
+
+Commands:
+    build         Compiles the program into an executable.
+    run           Compiles the program and runs it.
+    check         Parses and checks the program without generating any code.
+    emit-c        Prints the generated C code.
+    emit-ast      Prints the parsed syntax tree.
+    emit-tokens   Prints the tokens of the file.
+    audit         Lists the trusted packages and the unsafe code in each of them.
+    fmt           Formats the file, or every file of a folder, in place.
+
+Options:
+    -o, --output <path>     Where to write the executable or the emitted output.
+    --cc <compiler>         C compiler to use, a name on PATH or a path.
+    -O0, -O1, -O2, -O3, -Os Optimization level of the C compiler (default: -O0).
+    --color <when>          `auto`, `always` or `never` (default: auto).
+    --check                 With `fmt`, lists the files that aren't formatted instead.
+    -h, --help              Prints this message.
+
+Exit codes:
+    0   Success.
+    1   The program has errors or the output couldn't be written, or with
+        `fmt --check`, isn't formatted.
+    2   Invalid command line.
+    3   The C compiler is missing or failed.";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    #[default]
+    Help,
+    Build,
+    Run,
+    Check,
+    EmitC,
+    EmitAst,
+    EmitTokens,
+    Audit,
+    Format,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Arguments {
+    pub command: Command,
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub compiler: Option<String>,
+    pub optimization: OptimizationLevel,
+    pub color: Color,
+    /// Whether `fmt` only checks the files are formatted.
+    pub check: bool,
+    pub program_arguments: Vec<String>,
+}
+
+impl Arguments {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut result = Self::default();
+        let mut input = None;
+
+        let mut args = args.iter();
+
+        result.command = match args.next().map(String::as_str) {
+            None | Some("-h") | Some("--help") | Some("help") => return Ok(result),
+            Some("build") => Command::Build,
+            Some("run") => Command::Run,
+            Some("check") => Command::Check,
+            Some("emit-c") => Command::EmitC,
+            Some("emit-ast") => Command::EmitAst,
+            Some("emit-tokens") => Command::EmitTokens,
+            Some("audit") => Command::Audit,
+            Some("fmt") => Command::Format,
+            Some(command) => return Err(format!("unknown command `{}`", command)),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("`{}` expects a value", name))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    result.command = Command::Help;
+                    return Ok(result);
+                }
+                "-o" | "--output" => result.output = Some(PathBuf::from(value(arg)?)),
+                "--cc" => result.compiler = Some(value(arg)?),
+                "--color" => {
+                    result.color = match value(arg)?.as_str() {
+                        "auto" => Color::Auto,
+                        "always" => Color::Always,
+                        "never" => Color::Never,
+                        color => return Err(format!("unknown color mode `{}`", color)),
+                    }
+                }
+                "--check" => result.check = true,
+                "-O0" => result.optimization = OptimizationLevel::None,
+                "-O1" => result.optimization = OptimizationLevel::Less,
+                "-O2" => result.optimization = OptimizationLevel::Default,
+                "-O3" => result.optimization = OptimizationLevel::Aggressive,
+                "-Os" => result.optimization = OptimizationLevel::Size,
+                "--" => {
+                    result.program_arguments = args.cloned().collect();
+                    break;
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+                _ if input.is_some() => return Err(format!("unexpected argument `{}`", arg)),
+                _ => input = Some(PathBuf::from(arg)),
+            }
+        }
+
+        match input {
+            Some(input) => result.input = input,
+            None => return Err("missing input file".to_string()),
+        }
+
+        Ok(result)
+    }
+}
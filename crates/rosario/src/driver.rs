@@ -0,0 +1,481 @@
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    process,
+};
+
+use rosarioc::{BuildOptions, CCompiler, CDiagnostic, CResult, CSeverity};
+use rosarioparser::{
+    analysis::{
+        check_initialization, check_moves, check_mutability, check_ownership, check_trust,
+        monomorphize, resolve, trust::trusted_code, typecheck,
+    },
+    diagnostic::{Diagnostic, Severity},
+    formatter::format_file,
+    lexer::Lexer,
+    loader::Loader,
+    parser::ast::Ast,
+};
+
+use crate::{
+    cli::{Arguments, Color, Command},
+    lower::lower,
+};
+
+pub const SUCCESS: u8 = 0;
+pub const ERRORS: u8 = 1;
+pub const C_COMPILER_ERROR: u8 = 3;
+
+pub struct Driver {
+    pub arguments: Arguments,
+    pub color: bool,
+}
+
+impl Driver {
+    pub fn new(arguments: Arguments) -> Self {
+        let color = match arguments.color {
+            Color::Always => true,
+            Color::Never => false,
+            Color::Auto => {
+                std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+        };
+
+        Self { arguments, color }
+    }
+
+    pub fn run(&self) -> u8 {
+        let result = match self.arguments.command {
+            Command::Help => Ok(()),
+            Command::EmitTokens => self.emit_tokens(),
+            Command::EmitAst => self
+                .parse()
+                .and_then(|ast| self.write(&format!("{:#?}\n", ast))),
+            Command::Check => self.check().map(|_| ()),
+            Command::EmitC => self.emit_c(),
+            Command::Build => self.build(&self.output_path()).map(|_| ()),
+            Command::Run => self.run_program(),
+            Command::Audit => self.audit(),
+            Command::Format => self.format(),
+        };
+
+        match result {
+            Ok(()) => SUCCESS,
+            Err(code) => code,
+        }
+    }
+
+    fn lexer(&self) -> Result<Lexer, u8> {
+        let path = self.arguments.input.display().to_string();
+
+        if !self.arguments.input.is_file() {
+            self.report(&[Diagnostic::error(format!("can't read `{}`", path))]);
+            return Err(ERRORS);
+        }
+
+        Ok(Lexer::from_file(&path, None))
+    }
+
+    fn emit_tokens(&self) -> Result<(), u8> {
+        let mut lexer = self.lexer()?;
+
+        lexer.start();
+
+        if !lexer.diagnostics.is_empty() {
+            self.report(&lexer.diagnostics);
+            return Err(ERRORS);
+        }
+
+        let mut result = String::new();
+
+        for token in &lexer.contents {
+            let (line, column) = token.begin_location;
+            result.push_str(&format!("{}:{}\t{:?}\n", line + 1, column, token.ty));
+        }
+
+        self.write(&result)
+    }
+
+    /// Parses the input and every package it declares.
+    fn parse(&self) -> Result<Ast, u8> {
+        let path = self.lexer()?.file_path;
+
+        let mut loader = Loader::default();
+        loader.load(&path);
+
+        if !loader.diagnostics.is_empty() {
+            self.report(&loader.diagnostics);
+            return Err(ERRORS);
+        }
+
+        Ok(loader.parser.ast)
+    }
+
+    /// Parses and checks the input, returning the `Ast` ready to be lowered.
+    pub fn check(&self) -> Result<Ast, u8> {
+        let mut ast = self.parse()?;
+
+        let mut diagnostics = resolve(&mut ast);
+        diagnostics.extend(check_trust(&ast));
+        self.report(&diagnostics);
+
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(ERRORS);
+        }
+
+        let ast = monomorphize(&ast).map_err(|diagnostics| {
+            self.report(&diagnostics);
+            ERRORS
+        })?;
+
+        let mut diagnostics = typecheck(&ast);
+        diagnostics.extend(check_mutability(&ast));
+        diagnostics.extend(check_initialization(&ast));
+        diagnostics.extend(check_ownership(&ast));
+        diagnostics.extend(check_moves(&ast));
+
+        self.report(&diagnostics);
+
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(ERRORS);
+        }
+
+        Ok(ast)
+    }
+
+    /// Lists every trusted package of the build with the unsafe code it contains.
+    fn audit(&self) -> Result<(), u8> {
+        let ast = self.parse()?;
+        let trusted = trusted_code(&ast);
+
+        let mut result = String::new();
+
+        if trusted.is_empty() {
+            result.push_str("no trusted packages\n");
+        }
+
+        for (module, code) in trusted {
+            result.push_str(&format!(
+                "trusted package `{}`: {} unsafe use{}\n",
+                module,
+                code.len(),
+                if code.len() == 1 { "" } else { "s" }
+            ));
+
+            for code in code {
+                result.push_str(&format!("    {}: {}\n", code.location, code.kind));
+            }
+        }
+
+        self.write(&result)
+    }
+
+    /// Formats the input file, or every `.ros` file of the input folder, in place. With
+    /// `--check` it only reports the files that aren't formatted.
+    fn format(&self) -> Result<(), u8> {
+        let input = &self.arguments.input;
+
+        let mut paths = vec![];
+        if input.is_dir() {
+            ros_files(input, &mut paths);
+            paths.sort();
+        } else if input.is_file() {
+            paths.push(input.clone());
+        } else {
+            let message = format!("can't read `{}`", input.display());
+            self.report(&[Diagnostic::error(message)]);
+            return Err(ERRORS);
+        }
+
+        let mut failed = false;
+
+        for path in paths {
+            let name = path.display().to_string();
+
+            let formatted = match format_file(&name) {
+                Ok(formatted) => formatted,
+                Err(diagnostics) => {
+                    self.report(&diagnostics);
+                    failed = true;
+                    continue;
+                }
+            };
+
+            let source = std::fs::read_to_string(&path).unwrap_or_default();
+
+            if formatted == source {
+                continue;
+            }
+
+            if self.arguments.check {
+                self.report(&[Diagnostic::error(format!("`{}` isn't formatted", name))
+                    .with_note("`rosario fmt` formats it".to_string())]);
+                failed = true;
+            } else if let Err(error) = std::fs::write(&path, formatted) {
+                self.report(&[Diagnostic::error(format!(
+                    "couldn't write `{}`: {}",
+                    name, error
+                ))]);
+                failed = true;
+            }
+        }
+
+        if failed {
+            return Err(ERRORS);
+        }
+
+        Ok(())
+    }
+
+    fn stem(&self) -> String {
+        self.arguments
+            .input
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "main".to_string())
+    }
+
+    /// Checks the input and lowers it to C, checking it has a `Main` to start from when
+    /// it's built into a `program`.
+    fn lower(&self, program: bool) -> Result<CResult, u8> {
+        let ast = self.check()?;
+
+        if program {
+            self.check_entry_point(&ast)?;
+        }
+
+        let result = lower(&ast, &format!("{}.c", self.stem())).map_err(|diagnostics| {
+            self.report(&diagnostics);
+            ERRORS
+        })?;
+
+        Ok(result)
+    }
+
+    /// Reports a program without `Main`, which the C linker would only report as a missing
+    /// `main`.
+    fn check_entry_point(&self, ast: &Ast) -> Result<(), u8> {
+        let main = ast
+            .definitions
+            .keys()
+            .filter(|signature| signature.name == "Main")
+            .collect::<Vec<_>>();
+
+        if main.iter().any(|signature| signature.args.is_empty()) {
+            return Ok(());
+        }
+
+        let diagnostic = match main.is_empty() {
+            true => Diagnostic::error(format!(
+                "`{}` has no `Main` procedure",
+                self.arguments.input.display()
+            ))
+            .with_note("programs start with `procedure Main() is`".to_string()),
+            false => Diagnostic::error("`Main` can't take arguments".to_string())
+                .with_note("programs start with `procedure Main() is`".to_string()),
+        };
+
+        self.report(&[diagnostic]);
+
+        Err(ERRORS)
+    }
+
+    fn emit_c(&self) -> Result<(), u8> {
+        let result = self.lower(false)?;
+
+        let mut files = result.files.iter().collect::<Vec<_>>();
+        files.sort_by_key(|(id, _)| **id);
+
+        let output = files
+            .iter()
+            .map(|(_, file)| file.emit())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        self.write(&output)
+    }
+
+    fn output_path(&self) -> PathBuf {
+        match &self.arguments.output {
+            Some(output) => output.clone(),
+            None => PathBuf::from(format!("{}{}", self.stem(), std::env::consts::EXE_SUFFIX)),
+        }
+    }
+
+    fn compiler(&self) -> Result<CCompiler, u8> {
+        let compiler = match &self.arguments.compiler {
+            Some(name) => CCompiler::from_name(name),
+            None => CCompiler::find(),
+        };
+
+        compiler.ok_or_else(|| {
+            let message = match &self.arguments.compiler {
+                Some(name) => format!("the C compiler `{}` wasn't found", name),
+                None => "no C compiler (cc, gcc, clang or tcc) was found on PATH".to_string(),
+            };
+
+            self.report(&[Diagnostic::error(message)]);
+            C_COMPILER_ERROR
+        })
+    }
+
+    /// Builds the executable `output`. The C files are written to a temporary folder,
+    /// removed once they're compiled.
+    fn build(&self, output: &Path) -> Result<PathBuf, u8> {
+        let mut result = self.lower(true)?;
+        result.compiler = self.compiler()?;
+
+        let options = BuildOptions {
+            build_directory: std::env::temp_dir().join(format!(
+                "rosario-build-{}-{}",
+                process::id(),
+                self.stem()
+            )),
+            output: output.to_path_buf(),
+            optimization: self.arguments.optimization,
+            ..Default::default()
+        };
+
+        let outcome = result.build(&options);
+        let _ = std::fs::remove_dir_all(&options.build_directory);
+
+        let outcome = outcome.map_err(|error| {
+            self.report(&[Diagnostic::error(format!(
+                "couldn't run the C compiler: {}",
+                error
+            ))]);
+            C_COMPILER_ERROR
+        })?;
+
+        for diagnostic in &outcome.diagnostics {
+            self.report_c(diagnostic);
+        }
+
+        match outcome.executable {
+            Some(executable) => Ok(executable),
+            None => {
+                if outcome.diagnostics.is_empty() {
+                    eprint!("{}", outcome.output);
+                }
+
+                Err(C_COMPILER_ERROR)
+            }
+        }
+    }
+
+    fn run_program(&self) -> Result<(), u8> {
+        let directory = std::env::temp_dir().join(format!("rosario-run-{}", process::id()));
+
+        if let Err(error) = std::fs::create_dir_all(&directory) {
+            self.report(&[Diagnostic::error(format!(
+                "couldn't create `{}`: {}",
+                directory.display(),
+                error
+            ))]);
+            return Err(ERRORS);
+        }
+
+        let result = self
+            .build(&directory.join(self.stem()))
+            .and_then(|executable| self.execute(&executable));
+
+        let _ = std::fs::remove_dir_all(&directory);
+
+        result
+    }
+
+    fn execute(&self, executable: &Path) -> Result<(), u8> {
+        let status = process::Command::new(executable)
+            .args(&self.arguments.program_arguments)
+            .status();
+
+        match status {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(status.code().unwrap_or(1).clamp(1, 255) as u8),
+            Err(error) => {
+                self.report(&[Diagnostic::error(format!(
+                    "couldn't run `{}`: {}",
+                    executable.display(),
+                    error
+                ))]);
+                Err(ERRORS)
+            }
+        }
+    }
+
+    fn write(&self, contents: &str) -> Result<(), u8> {
+        match &self.arguments.output {
+            Some(path) => std::fs::write(path, contents).map_err(|error| {
+                self.report(&[Diagnostic::error(format!(
+                    "couldn't write `{}`: {}",
+                    path.display(),
+                    error
+                ))]);
+                ERRORS
+            }),
+            None => {
+                print!("{}", contents);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn report(&self, diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            let severity = match diagnostic.severity {
+                Severity::Error => paint(self.color, "1;31", "error"),
+                Severity::Warning => paint(self.color, "1;33", "warning"),
+            };
+
+            match &diagnostic.location {
+                Some(location) => eprintln!("{}: {}: {}", location, severity, diagnostic.message),
+                None => eprintln!("{}: {}", severity, diagnostic.message),
+            }
+
+            for note in &diagnostic.notes {
+                eprintln!("  {}: {}", paint(self.color, "1;36", "note"), note);
+            }
+        }
+    }
+
+    fn report_c(&self, diagnostic: &CDiagnostic) {
+        let severity = match diagnostic.severity {
+            CSeverity::Error => paint(self.color, "1;31", "error"),
+            CSeverity::Warning => paint(self.color, "1;33", "warning"),
+            CSeverity::Note => paint(self.color, "1;36", "note"),
+        };
+
+        let location = match (diagnostic.line, diagnostic.column) {
+            (Some(line), Some(column)) => format!("{}:{}:{}", diagnostic.file, line, column),
+            (Some(line), None) => format!("{}:{}", diagnostic.file, line),
+            _ => diagnostic.file.clone(),
+        };
+
+        eprintln!("{}: {} (C): {}", location, severity, diagnostic.message);
+    }
+}
+
+/// Adds the `.ros` files of `folder` and its subfolders to `result`.
+fn ros_files(folder: &Path, result: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            ros_files(&path, result);
+        } else if path.extension().is_some_and(|extension| extension == "ros") {
+            result.push(path);
+        }
+    }
+}
+
+fn paint(color: bool, code: &str, text: &str) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
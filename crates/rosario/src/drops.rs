@@ -0,0 +1,146 @@
+use std::collections::BTreeSet;
+
+use rosarioparser::parser::ast::VariableId;
+
+/// Variables holding no value, `None` once no path reaches the current point.
+pub type Empty = Option<BTreeSet<VariableId>>;
+
+/// Owned variables implementing `Drop` in the scopes of the definition being lowered.
+///
+/// A variable holds no value once it's moved out, or while it's declared without an
+/// initializer and not assigned yet. Only variables holding a value are dropped.
+#[derive(Debug)]
+pub struct Drops {
+    /// Variables of each scope, from the outermost, in declaration order.
+    scopes: Vec<Vec<VariableId>>,
+    empty: Empty,
+}
+
+impl Default for Drops {
+    fn default() -> Self {
+        Self {
+            scopes: vec![],
+            empty: Some(BTreeSet::new()),
+        }
+    }
+}
+
+impl Drops {
+    pub fn enter(&mut self) {
+        self.scopes.push(vec![]);
+    }
+
+    /// Leaves the innermost scope, returning its variables to drop in reverse declaration order.
+    pub fn exit(&mut self) -> Vec<VariableId> {
+        let scope = self.scopes.pop().unwrap_or_default();
+        let dropped = self.holding(scope.iter().rev());
+
+        if let Some(empty) = &mut self.empty {
+            for variable in &scope {
+                empty.remove(variable);
+            }
+        }
+
+        dropped
+    }
+
+    pub fn declare(&mut self, variable: VariableId, assigned: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(variable);
+        }
+
+        if !assigned && let Some(empty) = &mut self.empty {
+            empty.insert(variable);
+        }
+    }
+
+    fn is_tracked(&self, variable: VariableId) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(&variable))
+    }
+
+    /// Moves the value out of `variable`, which isn't dropped until it's assigned again.
+    pub fn move_out(&mut self, variable: VariableId) {
+        if self.is_tracked(variable)
+            && let Some(empty) = &mut self.empty
+        {
+            empty.insert(variable);
+        }
+    }
+
+    /// Assigns `variable`, returning whether its previous value has to be dropped first.
+    pub fn assign(&mut self, variable: VariableId) -> bool {
+        if !self.is_tracked(variable) {
+            return false;
+        }
+
+        match &mut self.empty {
+            Some(empty) => !empty.remove(&variable),
+            None => false,
+        }
+    }
+
+    /// Returns from the definition, giving the variables of every scope to drop, innermost
+    /// first. Nothing reaches the point after it.
+    pub fn leave(&mut self) -> Vec<VariableId> {
+        let dropped = self.holding(
+            self.scopes
+                .iter()
+                .rev()
+                .flat_map(|scope| scope.iter().rev()),
+        );
+
+        self.empty = None;
+
+        dropped
+    }
+
+    fn holding<'a>(&self, variables: impl Iterator<Item = &'a VariableId>) -> Vec<VariableId> {
+        match &self.empty {
+            Some(empty) => variables
+                .filter(|variable| !empty.contains(variable))
+                .copied()
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn state(&self) -> Empty {
+        self.empty.clone()
+    }
+
+    pub fn restore(&mut self, state: Empty) {
+        self.empty = state;
+    }
+
+    /// Joins branches ending in the states `ends`, returning the variables each has to drop
+    /// at its end so they all leave the same variables without a value.
+    ///
+    /// A variable moved out in one branch only is dropped at the end of the others.
+    pub fn merge(&mut self, ends: &[Empty]) -> Vec<Vec<VariableId>> {
+        let joined = ends.iter().flatten().fold(None, |joined: Empty, end| {
+            Some(match joined {
+                Some(joined) => joined.union(end).copied().collect(),
+                None => end.clone(),
+            })
+        });
+
+        let dropped = ends
+            .iter()
+            .map(|end| match (end, &joined) {
+                (Some(end), Some(joined)) => self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .flat_map(|scope| scope.iter().rev())
+                    .filter(|variable| joined.contains(variable) && !end.contains(variable))
+                    .copied()
+                    .collect(),
+                _ => vec![],
+            })
+            .collect();
+
+        self.empty = joined;
+
+        dropped
+    }
+}
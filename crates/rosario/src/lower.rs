@@ -0,0 +1,2015 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::drops::Drops;
+use rosarioc::{
+    Builder, CDeclaration, CExpression, CFileId, CLine, CResult, CStatement, CStruct,
+    CStructElement, CType, CTypeId, Function, FunctionArgs, FunctionSignature, Include,
+};
+use rosarioparser::{
+    analysis::{
+        monomorphize, moves,
+        mutability::is_mutable,
+        typecheck::{self, Scaling, Type, fixed_bounds, range_bounds},
+    },
+    diagnostic::Diagnostic,
+    parser::{
+        ast::{
+            Associated, Ast, BinOpType, Conversion, DefinitionOwner, DefinitionSignature,
+            Expression, ExpressionId, For, Index, Loop, Match, MethodCall, Pattern, TypeBody,
+            TypeExpression, TypeSignature, UnOpType, Variable, VariableId, VariableType,
+        },
+        number::Exact,
+    },
+};
+
+/// Lowers a monomorphized `Ast` into a single C file named `path`.
+pub fn lower(ast: &Ast, path: &str) -> Result<CResult, Vec<Diagnostic>> {
+    let mut builder = Builder::new();
+    let file = builder.new_file(path);
+
+    let mut lowering = Lowering {
+        ast,
+        builder,
+        file,
+        types: BTreeMap::new(),
+        arrays: BTreeMap::new(),
+        array_literals: typecheck::array_literals(ast),
+        scalings: typecheck::scalings(ast),
+        helpers: BTreeSet::new(),
+        strings: BTreeMap::new(),
+        string_types: BTreeMap::new(),
+        drops: Drops::default(),
+        bindings: BTreeMap::new(),
+        drop_glue: BTreeSet::new(),
+        names: Names::new(ast),
+        diagnostics: vec![],
+    };
+
+    lowering.start();
+
+    if lowering.diagnostics.iter().any(Diagnostic::is_error) {
+        return Err(lowering.diagnostics);
+    }
+
+    Ok(lowering.builder.result)
+}
+
+/// C identifiers of the items of a monomorphized `Ast`.
+///
+/// Generic instances are named after their generic and the names of their arguments,
+/// like `main_Option_Int32`, and only get a numbered suffix when two of them, or one and
+/// an item written in Rosario, would otherwise share an identifier.
+#[derive(Default)]
+struct Names {
+    /// The suffixed name of each colliding instance, by its C owner and Rosario name.
+    suffixed: BTreeMap<(String, String), String>,
+}
+
+impl Names {
+    fn new(ast: &Ast) -> Self {
+        let mut names = Names::default();
+
+        let types = ast
+            .types
+            .keys()
+            .map(|ty| (ty.owner.replace("::", "_"), ty.name.clone()))
+            .collect::<Vec<_>>();
+        names.suffix(types);
+
+        let definitions = ast
+            .definitions
+            .keys()
+            .map(|definition| {
+                (
+                    names.owner_c_name(&definition.owner),
+                    definition.name.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        names.suffix(definitions);
+
+        names
+    }
+
+    /// Numbers the instances among `items` whose readable identifiers collide.
+    fn suffix(&mut self, items: Vec<(String, String)>) {
+        let mut identifiers = BTreeMap::<String, Vec<(String, String)>>::new();
+
+        for (owner, name) in items {
+            let identifier = join(&owner, &self.name_c_name(&owner, &name));
+            identifiers
+                .entry(identifier)
+                .or_default()
+                .push((owner, name));
+        }
+
+        let mut taken = identifiers.keys().cloned().collect::<BTreeSet<_>>();
+
+        for items in identifiers.into_values().filter(|items| items.len() > 1) {
+            let mut count = 0;
+
+            for (owner, name) in items.into_iter().filter(|(_, name)| name.contains('<')) {
+                let readable = instance_c_name(&name);
+
+                let suffixed = loop {
+                    count += 1;
+
+                    let suffixed = format!("{}_{}", readable, count);
+
+                    if taken.insert(join(&owner, &suffixed)) {
+                        break suffixed;
+                    }
+                };
+
+                self.suffixed.insert((owner, name), suffixed);
+            }
+        }
+    }
+
+    /// C identifier of an item declared in the Rosario path `owner`.
+    fn c_name(&self, owner: &str, name: &str) -> String {
+        let owner = owner.replace("::", "_");
+        let name = self.name_c_name(&owner, name);
+
+        join(&owner, &name)
+    }
+
+    /// C identifier of `name` itself, once prefixed by the C identifier `owner`.
+    fn name_c_name(&self, owner: &str, name: &str) -> String {
+        if let Some(suffixed) = self.suffixed.get(&(owner.to_string(), name.to_string())) {
+            return suffixed.clone();
+        }
+
+        // Operators are named after their symbol, which C identifiers can't hold.
+        match name {
+            "?" => "operator_question".to_string(),
+            "mutable ?" => "mutable_operator_question".to_string(),
+            _ => instance_c_name(name),
+        }
+    }
+
+    /// C identifier of a written type, e.g. `array_main_Digit_of_main_Byte`.
+    fn type_c_name(&self, ty: &TypeExpression) -> String {
+        match ty {
+            TypeExpression::Named(reference) => {
+                self.c_name(&reference.ty.owner, &reference.ty.name)
+            }
+            TypeExpression::Pointer(inner)
+            | TypeExpression::Reference(inner)
+            | TypeExpression::MutableReference(inner) => {
+                format!("{}_pointer", self.type_c_name(inner))
+            }
+            TypeExpression::Array { index, element } => format!(
+                "array_{}_of_{}",
+                self.type_c_name(index),
+                self.type_c_name(element)
+            ),
+        }
+    }
+
+    fn definition_c_name(&self, signature: &DefinitionSignature) -> String {
+        let owner = self.owner_c_name(&signature.owner);
+        let name = self.name_c_name(&owner, &signature.name);
+
+        join(&owner, &name)
+    }
+
+    fn owner_c_name(&self, owner: &DefinitionOwner) -> String {
+        match owner {
+            DefinitionOwner::Path(path) => path.replace("::", "_"),
+            DefinitionOwner::Type(ty) => self.c_name(&ty.owner, &ty.name),
+            DefinitionOwner::Unknown => String::new(),
+        }
+    }
+}
+
+fn join(owner: &str, name: &str) -> String {
+    match owner.is_empty() {
+        true => name.to_string(),
+        false => format!("{}_{}", owner, name),
+    }
+}
+
+/// C identifier of the name of a generic instance, like `Pair<main::A, main::B>`, which
+/// keeps the names of its arguments without their paths: `Pair_A_B`. Other names are kept.
+fn instance_c_name(name: &str) -> String {
+    let mut result = String::new();
+
+    for (index, part) in name.split(['<', ',']).enumerate() {
+        let part = part.trim().trim_end_matches('>');
+        let part = part.rsplit("::").next().unwrap_or(part);
+
+        if index > 0 {
+            result.push('_');
+        }
+
+        result.push_str(part);
+    }
+
+    result
+}
+
+/// Smallest C integer able to hold every value of `min..max`.
+fn integer_type(min: i128, max: i128) -> Option<CType> {
+    [8, 16, 32, 64].into_iter().find_map(|bits: u32| {
+        if min >= 0 && max < 1 << bits {
+            Some(CType::UnsignedInteger(bits as usize))
+        } else if min >= -(1 << (bits - 1)) && max < 1 << (bits - 1) {
+            Some(CType::Integer(bits as usize))
+        } else {
+            None
+        }
+    })
+}
+
+/// The delta `mantissa * 10^exponent` as the fraction `(p, q)`.
+fn delta_ratio(delta: &Exact) -> Option<(i128, i128)> {
+    let mantissa = i128::try_from(delta.mantissa.to_u128()?).ok()?;
+    let power = 10i128.checked_pow(u32::try_from(delta.exponent.unsigned_abs()).ok()?)?;
+
+    match delta.exponent >= 0 {
+        true => Some((mantissa.checked_mul(power)?, 1)),
+        false => Some((mantissa, power)),
+    }
+}
+
+fn conversion_helper_name(balanced: bool, signed: bool) -> String {
+    format!(
+        "rosario_{}_{}",
+        if balanced { "balance" } else { "check" },
+        if signed { "int64" } else { "uint64" }
+    )
+}
+
+/// C literal of `value`, which must fit in a 64 bit integer.
+fn integer_literal(value: i128) -> CExpression {
+    let literal = if value == i64::MIN as i128 {
+        "INT64_MIN".to_string()
+    } else if value > i64::MAX as i128 {
+        format!("{}ULL", value)
+    } else {
+        value.to_string()
+    };
+
+    CExpression::Literal(literal)
+}
+
+struct Lowering<'a> {
+    ast: &'a Ast,
+    builder: Builder,
+    file: CFileId,
+    types: BTreeMap<TypeSignature, CTypeId>,
+    /// Arrays are wrapped in a struct so they can be passed and returned by value.
+    arrays: BTreeMap<TypeExpression, CTypeId>,
+    /// The array type of each `[a, b, ...]` literal.
+    array_literals: BTreeMap<ExpressionId, TypeExpression>,
+    /// How constants and products of fixed-point values are scaled to their delta.
+    scalings: BTreeMap<ExpressionId, Scaling>,
+    /// Conversion helpers used by the program, as `(balanced, signed)`.
+    helpers: BTreeSet<(bool, bool)>,
+    /// Constant holding each string literal.
+    strings: BTreeMap<String, String>,
+    /// Length-prefixed struct of the string literals, by their length in bytes.
+    string_types: BTreeMap<usize, CTypeId>,
+    /// Variables to drop in the definition being lowered.
+    drops: Drops,
+    /// Type of each match binding of the definition being lowered that's dropped, bindings
+    /// are declared without one.
+    bindings: BTreeMap<VariableId, TypeSignature>,
+    /// Types dropped through a function dropping their own `Drop` and their fields.
+    drop_glue: BTreeSet<TypeSignature>,
+    names: Names,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Lowering<'_> {
+    fn start(&mut self) {
+        for header in self.ast.foreign_includes.values().flatten() {
+            self.include(header);
+        }
+
+        for signature in self.ast.types.keys() {
+            self.lower_type(signature);
+        }
+
+        for (signature, body) in &self.ast.definitions {
+            self.lower_definition(signature, *body);
+        }
+
+        if let Some(main) = self
+            .ast
+            .definitions
+            .keys()
+            .find(|signature| signature.name == "Main" && signature.args.is_empty())
+        {
+            self.lower_entry_point(main);
+        }
+
+        for (balanced, signed) in self.helpers.clone() {
+            self.lower_conversion_helper(balanced, signed);
+        }
+
+        // Dropping a field may need the glue of another type.
+        let mut lowered = BTreeSet::new();
+
+        while let Some(ty) = self.drop_glue.difference(&lowered).next().cloned() {
+            self.lower_drop_glue(&ty);
+            lowered.insert(ty);
+        }
+    }
+
+    fn include(&mut self, header: &str) {
+        let file = self.builder.file(self.file);
+
+        if !file.includes.iter().any(|include| include.0 == header) {
+            file.includes.push(Include(header.to_string()));
+        }
+    }
+
+    fn new_type(&mut self, ty: CType) -> CTypeId {
+        self.builder.new_type(self.file, ty)
+    }
+
+    fn lower_type(&mut self, signature: &TypeSignature) -> CTypeId {
+        if let Some(id) = self.types.get(signature) {
+            return *id;
+        }
+
+        let Some(ty) = self.ast.types.get(signature) else {
+            self.diagnostics.push(Diagnostic::error(format!(
+                "unknown type `{}`",
+                signature.name
+            )));
+
+            return CTypeId(0);
+        };
+
+        let name = self.names.c_name(&signature.owner, &signature.name);
+
+        let id = match &ty.ty {
+            TypeBody::Range(range) => {
+                let bounds = self
+                    .ast
+                    .evaluate(range.min)
+                    .zip(self.ast.evaluate(range.max));
+
+                let Some((min, max)) = bounds else {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "the bounds of `{}` must be constant",
+                            signature.name
+                        ))
+                        .at(self.ast.find_location(range.min)),
+                    );
+
+                    return CTypeId(0);
+                };
+
+                let Some(integer) = integer_type(min, max) else {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "`{}` doesn't fit in a 64 bit integer",
+                            signature.name
+                        ))
+                        .at(self.ast.find_location(range.min)),
+                    );
+
+                    return CTypeId(0);
+                };
+
+                let integer = self.new_type(integer);
+                self.new_type(CType::Typedef(name, integer))
+            }
+            TypeBody::Float(digits) => {
+                let bits = self.ast.evaluate(*digits).and_then(typecheck::float_bits);
+
+                let Some(bits) = bits else {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "`{}` must have a constant number of digits from 1 to 18",
+                            signature.name
+                        ))
+                        .at(self.ast.find_location(*digits)),
+                    );
+
+                    return CTypeId(0);
+                };
+
+                let float = self.new_type(CType::Float(bits));
+                self.new_type(CType::Typedef(name, float))
+            }
+            // Fixed-point values are stored as integer multiples of the delta.
+            TypeBody::Fixed(fixed) => {
+                let Some((_, min, max)) = fixed_bounds(self.ast, signature) else {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "the delta and bounds of `{}` must be constant numbers",
+                            signature.name
+                        ))
+                        .at(self.ast.find_location(fixed.delta))
+                        .with_note("the delta must be positive".to_string()),
+                    );
+
+                    return CTypeId(0);
+                };
+
+                let Some(integer) = integer_type(min, max) else {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "`{}` has too many steps for a 64 bit integer",
+                            signature.name
+                        ))
+                        .at(self.ast.find_location(fixed.delta)),
+                    );
+
+                    return CTypeId(0);
+                };
+
+                let integer = self.new_type(integer);
+                self.new_type(CType::Typedef(name, integer))
+            }
+            TypeBody::Enum(variants) => {
+                let kind = self.new_type(CType::UnsignedInteger(32));
+
+                let mut elements = vec![CStructElement {
+                    name: "kind".to_string(),
+                    ty: kind,
+                }];
+
+                for variant in variants {
+                    for (i, field) in variant.fields.iter().enumerate() {
+                        elements.push(CStructElement {
+                            name: format!("{}_{}", variant.name, i),
+                            ty: self.lower_type_expression(field),
+                        });
+                    }
+                }
+
+                self.new_type(CType::Struct(CStruct { name, elements }))
+            }
+            // Resolving replaces aliases with the types they name.
+            TypeBody::Alias(_) | TypeBody::Unknown => CTypeId(0),
+        };
+
+        self.types.insert(signature.clone(), id);
+
+        id
+    }
+
+    fn lower_type_expression(&mut self, ty: &TypeExpression) -> CTypeId {
+        match ty {
+            TypeExpression::Named(reference) => self.lower_type(&reference.ty),
+            TypeExpression::Pointer(inner)
+            | TypeExpression::Reference(inner)
+            | TypeExpression::MutableReference(inner) => {
+                let inner = self.lower_type_expression(inner);
+                self.new_type(CType::Pointer(inner))
+            }
+            TypeExpression::Array { element, .. } => {
+                if let Some(id) = self.arrays.get(ty) {
+                    return *id;
+                }
+
+                let Some(length) = typecheck::array_length(self.ast, ty) else {
+                    self.diagnostics.push(Diagnostic::error(format!(
+                        "the length of `{}` must be constant",
+                        ty
+                    )));
+
+                    return CTypeId(0);
+                };
+
+                let element = self.lower_type_expression(element);
+                let elements = self.new_type(CType::Array(element, length as usize));
+
+                let id = self.new_type(CType::Struct(CStruct {
+                    name: self.names.type_c_name(ty),
+                    elements: vec![CStructElement {
+                        name: "elements".to_string(),
+                        ty: elements,
+                    }],
+                }));
+
+                self.arrays.insert(ty.clone(), id);
+
+                id
+            }
+        }
+    }
+
+    fn line(&self, id: ExpressionId) -> Option<CLine> {
+        self.ast.find_location(id).map(|location| CLine {
+            file: location.file,
+            line: location.line,
+        })
+    }
+
+    fn lower_return_type(&mut self, signature: &DefinitionSignature) -> CTypeId {
+        match &signature.return_type {
+            Some(ty) if signature.return_variable_type.is_reference() => {
+                let ty = self.lower_type_expression(ty);
+                self.new_type(CType::Pointer(ty))
+            }
+            Some(ty) => self.lower_type_expression(ty),
+            None => CTypeId(0),
+        }
+    }
+
+    fn lower_definition(&mut self, signature: &DefinitionSignature, body: ExpressionId) {
+        let ty = self.lower_return_type(signature);
+
+        let line = self.line(body);
+
+        // Parameters taken by value are owned by the body, and dropped when it ends.
+        self.drops = Drops::default();
+        self.bindings.clear();
+        self.drops.enter();
+
+        for argument in &signature.args {
+            if self.is_dropped(&self.ast.variables[signature][argument]) {
+                self.drops.declare(*argument, true);
+            }
+        }
+
+        let mut body = match (&signature.return_type, self.ast.find_expression(body)) {
+            (Some(_), Some(Expression::Match(match_expression))) => {
+                self.lower_match(signature, body, match_expression, true)
+            }
+            (Some(_), Some(expression))
+                if !matches!(expression, Expression::Body(_) | Expression::If(_)) =>
+            {
+                self.lower_return(signature, body, Some(body))
+            }
+            _ => self.lower_statement(signature, body),
+        };
+
+        let dropped = self.drops.exit();
+        body.extend(self.lower_drops(signature, dropped));
+
+        let args = signature
+            .args
+            .iter()
+            .map(|argument| {
+                let variable = &self.ast.variables[signature][argument];
+
+                let ty = self.lower_type_expression(&variable.ty);
+
+                match variable.variable_type {
+                    VariableType::Reference | VariableType::MutableReference => FunctionArgs {
+                        name: variable.name.clone(),
+                        ty: self.new_type(CType::Pointer(ty)),
+                        constant: variable.variable_type == VariableType::Reference,
+                    },
+                    _ => FunctionArgs {
+                        name: variable.name.clone(),
+                        ty,
+                        constant: self.is_constant(variable),
+                    },
+                }
+            })
+            .collect();
+
+        let function = Function {
+            signature: FunctionSignature {
+                name: self.names.definition_c_name(signature),
+                ty,
+                constant: signature.return_variable_type == VariableType::Reference,
+                args,
+            },
+            line,
+            body,
+        };
+
+        self.builder.new_function(self.file, function);
+    }
+
+    fn lower_entry_point(&mut self, main: &DefinitionSignature) {
+        let int = self.new_type(CType::Integer(32));
+
+        let function = Function {
+            signature: FunctionSignature {
+                name: "main".to_string(),
+                ty: int,
+                constant: false,
+                args: vec![],
+            },
+            line: None,
+            body: vec![
+                CStatement::Expression(CExpression::Call(
+                    self.names.definition_c_name(main),
+                    vec![],
+                )),
+                CStatement::Return(Some(CExpression::Literal("0".to_string()))),
+            ],
+        };
+
+        self.builder.new_function(self.file, function);
+    }
+
+    fn lower_statement(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+    ) -> Vec<CStatement> {
+        let mut result = vec![];
+
+        match self.ast.find_expression(id) {
+            Some(Expression::Body(body)) => {
+                self.drops.enter();
+
+                for content in &body.contents {
+                    if let Some(line) = self.line(*content) {
+                        result.push(CStatement::Line(line));
+                    }
+
+                    let statements = self.lower_statement(signature, *content);
+
+                    match self.ast.find_expression(*content) {
+                        Some(Expression::Body(_)) => result.push(CStatement::Block(statements)),
+                        _ => result.extend(statements),
+                    }
+                }
+
+                let dropped = self.drops.exit();
+                result.extend(self.lower_drops(signature, dropped));
+            }
+            Some(Expression::NewVariable(id)) => {
+                let variable = &self.ast.variables[signature][id];
+
+                let declaration = match variable.variable_type.is_reference() {
+                    true => {
+                        let ty = self.lower_type_expression(&variable.ty);
+
+                        CDeclaration {
+                            name: variable.name.clone(),
+                            ty: self.new_type(CType::Pointer(ty)),
+                            constant: variable.variable_type == VariableType::Reference,
+                            value: variable
+                                .initializer
+                                .map(|initializer| self.lower_reference(signature, initializer)),
+                        }
+                    }
+                    false => CDeclaration {
+                        name: variable.name.clone(),
+                        ty: self.lower_type_expression(&variable.ty),
+                        constant: variable.initializer.is_some() && self.is_constant(variable),
+                        value: variable
+                            .initializer
+                            .map(|initializer| self.lower_moved(signature, initializer)),
+                    },
+                };
+
+                if self.is_dropped(variable) {
+                    self.drops.declare(*id, variable.initializer.is_some());
+                }
+
+                result.push(CStatement::Declaration(declaration));
+            }
+            Some(Expression::Assignment(assignment)) => {
+                let op = match &assignment.op {
+                    Some(op) => op.to_string(),
+                    None => "=".to_string(),
+                };
+
+                let place = self.lower_expression(signature, assignment.place);
+                let mut value = self.lower_moved(signature, assignment.value);
+
+                // The previous value is dropped once the new one is computed, as it may use it.
+                if let Some(Expression::Variable(variable)) =
+                    self.ast.find_expression(assignment.place)
+                    && assignment.op.is_none()
+                    && self.drops.assign(*variable)
+                {
+                    let name = format!("assign_{}", id.0);
+                    let ty =
+                        self.lower_type_expression(&self.ast.variables[signature][variable].ty);
+
+                    result.push(CStatement::Declaration(CDeclaration {
+                        name: name.clone(),
+                        ty,
+                        constant: false,
+                        value: Some(value),
+                    }));
+                    result.extend(self.lower_drops(signature, vec![*variable]));
+
+                    value = CExpression::Variable(name);
+                }
+
+                result.push(CStatement::Assignment(place, op, value));
+            }
+            Some(Expression::Return(value)) => {
+                result.extend(self.lower_return(signature, id, *value));
+            }
+            Some(Expression::If(if_expression)) => {
+                let conditions = if_expression
+                    .branches
+                    .iter()
+                    .map(|(condition, _)| self.lower_expression(signature, *condition))
+                    .collect::<Vec<_>>();
+
+                let start = self.drops.state();
+                let mut bodies = vec![];
+                let mut ends = vec![];
+
+                for (_, body) in &if_expression.branches {
+                    self.drops.restore(start.clone());
+                    bodies.push(self.lower_statement(signature, *body));
+                    ends.push(self.drops.state());
+                }
+
+                self.drops.restore(start);
+
+                let mut otherwise = match if_expression.otherwise {
+                    Some(otherwise) => self.lower_statement(signature, otherwise),
+                    None => vec![],
+                };
+
+                ends.push(self.drops.state());
+
+                let mut dropped = self.drops.merge(&ends);
+
+                if let Some(last) = dropped.pop() {
+                    otherwise.extend(self.lower_drops(signature, last));
+                }
+
+                for ((condition, mut body), dropped) in
+                    conditions.into_iter().zip(bodies).zip(dropped).rev()
+                {
+                    body.extend(self.lower_drops(signature, dropped));
+                    otherwise = vec![CStatement::If(condition, body, otherwise)];
+                }
+
+                result.extend(otherwise);
+            }
+            Some(Expression::Match(match_expression)) => {
+                result.extend(self.lower_match(signature, id, match_expression, false));
+            }
+            Some(Expression::Loop(loop_expression)) => {
+                result.extend(self.lower_loop(signature, loop_expression));
+            }
+            Some(Expression::For(for_expression)) => {
+                result.push(CStatement::Block(self.lower_for(
+                    signature,
+                    id,
+                    for_expression,
+                )));
+            }
+            _ => result.push(CStatement::Expression(self.lower_expression(signature, id))),
+        }
+
+        result
+    }
+
+    fn lower_expression(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+    ) -> CExpression {
+        if let Some(Scaling::Constant(count)) = self.scalings.get(&id) {
+            return integer_literal(*count);
+        }
+
+        if let Some(value) = self.ast.evaluate(id) {
+            return CExpression::Literal(value.to_string());
+        }
+
+        match self.ast.find_expression(id) {
+            Some(Expression::Number(number)) => {
+                let mut literal = number.value.to_string();
+
+                // `2.0` stays a `double` in C, so `1.0 / 2.0` isn't an integer division.
+                if number.real && !literal.contains(['.', 'e']) {
+                    literal.push_str(".0");
+                }
+
+                CExpression::Literal(literal)
+            }
+            Some(Expression::String(text)) => self.lower_string(text),
+            Some(Expression::UnaryOperation(un_op)) => {
+                let op = match un_op.op {
+                    UnOpType::Minus => "-",
+                    UnOpType::Plus => "+",
+                    UnOpType::Dereference => "*",
+                    UnOpType::Reference => "&",
+                };
+
+                CExpression::Unary(
+                    op.to_string(),
+                    Box::new(self.lower_expression(signature, un_op.operand)),
+                )
+            }
+            Some(Expression::BinaryOperation(bin_op)) => {
+                let op = match bin_op.op {
+                    BinOpType::Add | BinOpType::Sub | BinOpType::Mul | BinOpType::Div => {
+                        bin_op.op.to_string()
+                    }
+                    BinOpType::Mod => "%".to_string(),
+                    _ if bin_op.op.is_comparison() => bin_op.op.to_string(),
+                    _ => return self.unsupported(id, "this operator"),
+                };
+
+                let left = self.lower_expression(signature, bin_op.left);
+                let right = self.lower_expression(signature, bin_op.right);
+
+                match self.scalings.get(&id).cloned() {
+                    Some(scaling) => self.lower_scaled(id, &scaling, left, right),
+                    None => CExpression::Binary(Box::new(left), op, Box::new(right)),
+                }
+            }
+            Some(Expression::Conversion(conversion)) => {
+                self.lower_conversion(signature, id, conversion)
+            }
+            Some(Expression::Variable(variable)) => {
+                let variable = &self.ast.variables[signature][variable];
+                let name = CExpression::Variable(variable.name.clone());
+
+                match variable.variable_type.is_reference() {
+                    true => CExpression::Unary("*".to_string(), Box::new(name)),
+                    false => name,
+                }
+            }
+            Some(Expression::MethodCall(call)) => self.lower_method_call(signature, id, call),
+            Some(Expression::Associated(associated)) => {
+                self.lower_associated(signature, id, associated)
+            }
+            Some(Expression::Cast(cast)) => {
+                let ty = self.lower_type_expression(&cast.ty);
+                let value = self.lower_expression(signature, cast.value);
+
+                CExpression::Cast(self.builder.file(self.file).type_name(ty), Box::new(value))
+            }
+            Some(Expression::SizeOf(ty)) => {
+                let ty = self.lower_type_expression(ty);
+
+                CExpression::Call(
+                    "sizeof".to_string(),
+                    vec![CExpression::Literal(
+                        self.builder.file(self.file).type_name(ty),
+                    )],
+                )
+            }
+            Some(Expression::Field(field)) => CExpression::Member(
+                Box::new(self.lower_expression(signature, field.base)),
+                field.name.clone(),
+            ),
+            Some(Expression::Index(index)) => self.lower_index(signature, index),
+            Some(Expression::Array(elements)) => {
+                let Some(ty) = self.array_literals.get(&id).cloned() else {
+                    return self.unsupported(id, "an array literal without a known type");
+                };
+
+                let ty = self.lower_type_expression(&ty);
+                let elements = elements
+                    .iter()
+                    .map(|element| self.lower_moved(signature, *element))
+                    .collect();
+
+                CExpression::Compound(
+                    self.builder.file(self.file).type_name(ty),
+                    vec![CExpression::Initializer(elements)],
+                )
+            }
+            Some(Expression::Call(call)) => {
+                let Some(definition) = self.ast.find_definition(call) else {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("unknown definition `{}`", call.name))
+                            .at(self.ast.find_location(id)),
+                    );
+
+                    return CExpression::Literal("0".to_string());
+                };
+
+                let name = self.names.definition_c_name(definition);
+                let args = definition
+                    .args
+                    .iter()
+                    .zip(&call.args)
+                    .map(|(parameter, arg)| {
+                        self.lower_argument(signature, definition, *parameter, *arg)
+                    })
+                    .collect();
+
+                Self::dereference_result(definition, CExpression::Call(name, args))
+            }
+            Some(Expression::Foreign(call)) => {
+                let Some(declaration) = self.ast.foreign.get(&call.name) else {
+                    return self.unsupported(id, "an undeclared C function");
+                };
+
+                let args = declaration
+                    .args
+                    .iter()
+                    .zip(&call.args)
+                    .map(|(parameter, arg)| {
+                        self.lower_argument(signature, declaration, *parameter, *arg)
+                    })
+                    .collect();
+
+                Self::dereference_result(declaration, CExpression::Call(call.name.clone(), args))
+            }
+            _ => self.unsupported(id, "this expression"),
+        }
+    }
+
+    /// Branches on the `kind` of the matched enum, declaring the bindings of each arm.
+    ///
+    /// When `tail` is set the match is the value of its definition, so each arm returns.
+    fn lower_match(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+        match_expression: &Match,
+        tail: bool,
+    ) -> Vec<CStatement> {
+        let ty = match typecheck::infer(self.ast, signature, match_expression.value) {
+            Type::Named(ty) => ty,
+            _ => return vec![],
+        };
+
+        let Some(TypeBody::Enum(variants)) = self.ast.types.get(&ty).map(|ty| &ty.ty) else {
+            return vec![];
+        };
+
+        let mut result = vec![];
+        let borrowed = monomorphize::borrowed(self.ast, signature, match_expression.value);
+
+        // An owned value gives the fields an arm takes to its bindings, the rest of it is
+        // dropped when the arm starts, as nothing can read it anymore.
+        let (owner, temporary) = match self.ast.find_expression(match_expression.value) {
+            _ if borrowed.is_some() => (None, false),
+            Some(Expression::Variable(variable)) => (Some(*variable), false),
+            Some(
+                Expression::Call(_)
+                | Expression::Foreign(_)
+                | Expression::MethodCall(_)
+                | Expression::Associated(_),
+            ) => (None, true),
+            _ => (None, false),
+        };
+
+        // The matched value is read by every arm, so it's only computed once, and a
+        // borrowed one is pointed to so that the bindings point into it rather than a copy.
+        let value = match self.ast.find_expression(match_expression.value) {
+            Some(Expression::Variable(_)) => {
+                self.lower_expression(signature, match_expression.value)
+            }
+            _ => {
+                let name = format!("match_{}", id.0);
+                let variable = CExpression::Variable(name.clone());
+                let c_type = self.lower_type(&ty);
+
+                match borrowed {
+                    Some(borrowed) => {
+                        result.push(CStatement::Declaration(CDeclaration {
+                            name,
+                            ty: self.new_type(CType::Pointer(c_type)),
+                            constant: borrowed == VariableType::Reference,
+                            value: Some(self.lower_reference(signature, match_expression.value)),
+                        }));
+
+                        CExpression::Unary("*".to_string(), Box::new(variable))
+                    }
+                    None => {
+                        result.push(CStatement::Declaration(CDeclaration {
+                            name,
+                            ty: c_type,
+                            constant: !(temporary && self.ast.needs_drop(&ty)),
+                            value: Some(self.lower_expression(signature, match_expression.value)),
+                        }));
+
+                        variable
+                    }
+                }
+            }
+        };
+
+        let reference = |value: CExpression| CExpression::Unary("&".to_string(), Box::new(value));
+
+        let start = self.drops.state();
+        let mut arms = vec![];
+        let mut ends = vec![];
+
+        for arm in &match_expression.arms {
+            let mut body = vec![];
+
+            let Pattern::Variant { name, bindings } = &arm.pattern else {
+                self.drops.restore(start.clone());
+
+                if temporary {
+                    body.extend(self.lower_drop(&ty, reference(value.clone())));
+                }
+
+                body.extend(self.lower_arm(signature, arm.body, tail));
+                arms.push((None, body));
+                ends.push(self.drops.state());
+                continue;
+            };
+
+            let Some(kind) = variants.iter().position(|variant| variant.name == *name) else {
+                continue;
+            };
+
+            self.drops.restore(start.clone());
+            self.drops.enter();
+
+            let takes =
+                (owner.is_some() || temporary) && moves::takes(self.ast, signature, &ty, arm);
+
+            if takes && let Some(owner) = owner {
+                self.drops.move_out(owner);
+            }
+
+            let mut left = vec![];
+
+            for (position, binding) in bindings.iter().enumerate() {
+                let field = &variants[kind].fields[position];
+                let member =
+                    CExpression::Member(Box::new(value.clone()), format!("{}_{}", name, position));
+
+                let dropped = field
+                    .signature()
+                    .filter(|field| takes && self.ast.needs_drop(field));
+
+                let Some(binding) = binding else {
+                    if let Some(field) = dropped {
+                        left.extend(self.lower_drop(field, reference(member)));
+                    }
+
+                    continue;
+                };
+
+                let variable = &self.ast.variables[signature][binding];
+
+                let declaration = match variable.variable_type.is_reference() {
+                    true => {
+                        let ty = self.lower_type_expression(field);
+                        let ty = self.new_type(CType::Pointer(ty));
+                        let mut value = reference(member);
+
+                        // C can't write a pointer to a `const` raw pointer, and `const` would
+                        // apply to what the raw pointer points to, so it's cast away instead.
+                        let raw = matches!(field, TypeExpression::Pointer(_));
+
+                        if raw && variable.variable_type == VariableType::Reference {
+                            let name = self.builder.file(self.file).type_name(ty);
+                            value = CExpression::Cast(name, Box::new(value));
+                        }
+
+                        CDeclaration {
+                            name: variable.name.clone(),
+                            ty,
+                            constant: variable.variable_type == VariableType::Reference && !raw,
+                            value: Some(value),
+                        }
+                    }
+                    false => CDeclaration {
+                        name: variable.name.clone(),
+                        ty: self.lower_type_expression(field),
+                        // `const` on a raw pointer would also forbid writing through it, and
+                        // dropped bindings are changed by their `Drop`.
+                        constant: !matches!(field, TypeExpression::Pointer(_)) && dropped.is_none(),
+                        value: Some(member),
+                    },
+                };
+
+                if let Some(field) = dropped {
+                    self.bindings.insert(*binding, field.clone());
+                    self.drops.declare(*binding, true);
+                }
+
+                body.push(CStatement::Declaration(declaration));
+            }
+
+            if temporary && !takes {
+                left.extend(self.lower_drop(&ty, reference(value.clone())));
+            }
+
+            body.extend(left);
+            body.extend(self.lower_arm(signature, arm.body, tail));
+
+            let dropped = self.drops.exit();
+            body.extend(self.lower_drops(signature, dropped));
+            ends.push(self.drops.state());
+
+            arms.push((Some(kind), body));
+        }
+
+        let dropped = self.drops.merge(&ends);
+        let mut otherwise = vec![];
+
+        for (i, ((kind, mut body), dropped)) in arms.into_iter().zip(dropped).rev().enumerate() {
+            body.extend(self.lower_drops(signature, dropped));
+
+            // Matches are exhaustive, so the last arm doesn't need to test the kind.
+            let Some(kind) = kind.filter(|_| i != 0) else {
+                otherwise = body;
+                continue;
+            };
+
+            let is_kind = CExpression::Binary(
+                Box::new(CExpression::Member(
+                    Box::new(value.clone()),
+                    "kind".to_string(),
+                )),
+                "==".to_string(),
+                Box::new(CExpression::Literal(kind.to_string())),
+            );
+
+            otherwise = vec![CStatement::If(is_kind, body, otherwise)];
+        }
+
+        result.extend(otherwise);
+
+        result
+    }
+
+    /// `while condition loop ... end loop` runs while the condition holds, `loop` forever.
+    fn lower_loop(
+        &mut self,
+        signature: &DefinitionSignature,
+        loop_expression: &Loop,
+    ) -> Vec<CStatement> {
+        let condition = match loop_expression.condition {
+            Some(condition) => self.lower_expression(signature, condition),
+            None => CExpression::Literal("1".to_string()),
+        };
+
+        let body = self.lower_loop_body(signature, loop_expression.body, vec![], vec![]);
+
+        // Without a condition, only a `return` leaves the loop.
+        if loop_expression.condition.is_none() {
+            self.drops.restore(None);
+        }
+
+        vec![CStatement::While(condition, body)]
+    }
+
+    /// Lowers a body running any number of times between `before` and `after`.
+    ///
+    /// Each iteration has to end with the same variables holding a value as it started
+    /// with, so the ones it assigns are dropped at its end.
+    fn lower_loop_body(
+        &mut self,
+        signature: &DefinitionSignature,
+        body: ExpressionId,
+        before: Vec<CStatement>,
+        after: Vec<CStatement>,
+    ) -> Vec<CStatement> {
+        let start = self.drops.state();
+
+        let mut result = before;
+        result.extend(self.lower_statement(signature, body));
+
+        let end = self.drops.state();
+        let dropped = self.drops.merge(&[start, end]);
+
+        if let Some(dropped) = dropped.into_iter().nth(1) {
+            result.extend(self.lower_drops(signature, dropped));
+        }
+
+        result.extend(after);
+        result
+    }
+
+    /// Iterates an array in order, or any other container through its `Length` and `Get`.
+    ///
+    /// The container is borrowed for the whole loop, each element is a reference into it.
+    fn lower_for(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+        for_expression: &For,
+    ) -> Vec<CStatement> {
+        let container_type = typecheck::infer(self.ast, signature, for_expression.container);
+        let element = &self.ast.variables[signature][&for_expression.element].name;
+
+        let container = format!("for_{}", id.0);
+        let index = format!("for_{}_index", id.0);
+        let variable = |name: &str| CExpression::Variable(name.to_string());
+
+        let mut result = vec![];
+
+        let (index_type, length, value, element_type) = match &container_type {
+            Type::Array(ty @ TypeExpression::Array { element, .. }) => {
+                let Some(length) = typecheck::array_length(self.ast, ty) else {
+                    return vec![CStatement::Expression(
+                        self.unsupported(id, "this `for` loop"),
+                    )];
+                };
+
+                let value = CExpression::Unary(
+                    "&".to_string(),
+                    Box::new(CExpression::Index(
+                        Box::new(CExpression::Member(
+                            Box::new(CExpression::Unary(
+                                "*".to_string(),
+                                Box::new(variable(&container)),
+                            )),
+                            "elements".to_string(),
+                        )),
+                        Box::new(variable(&index)),
+                    )),
+                );
+
+                (
+                    self.new_type(CType::UnsignedInteger(64)),
+                    integer_literal(length),
+                    value,
+                    self.lower_type_expression(element),
+                )
+            }
+            Type::Named(ty) => {
+                let methods = self
+                    .ast
+                    .find_method(ty, "Length")
+                    .zip(self.ast.find_method(ty, "Get"));
+
+                let Some((length, get)) = methods else {
+                    return vec![CStatement::Expression(
+                        self.unsupported(id, "this `for` loop"),
+                    )];
+                };
+
+                let receiver = |lowering: &Self, method: &DefinitionSignature| {
+                    let by_reference = lowering.ast.variables[method][&method.args[0]]
+                        .variable_type
+                        .is_reference();
+
+                    match by_reference {
+                        true => variable(&container),
+                        false => {
+                            CExpression::Unary("*".to_string(), Box::new(variable(&container)))
+                        }
+                    }
+                };
+
+                let index_type =
+                    self.lower_type_expression(&self.ast.variables[get][&get.args[1]].ty);
+                let element_type = match &get.return_type {
+                    Some(element) => self.lower_type_expression(element),
+                    None => CTypeId(0),
+                };
+
+                (
+                    index_type,
+                    CExpression::Call(
+                        self.names.definition_c_name(length),
+                        vec![receiver(self, length)],
+                    ),
+                    CExpression::Call(
+                        self.names.definition_c_name(get),
+                        vec![receiver(self, get), variable(&index)],
+                    ),
+                    element_type,
+                )
+            }
+            _ => {
+                return vec![CStatement::Expression(
+                    self.unsupported(id, "this `for` loop"),
+                )];
+            }
+        };
+
+        let container_type = match &container_type {
+            Type::Array(ty) => self.lower_type_expression(ty),
+            Type::Named(ty) => self.lower_type(ty),
+            _ => CTypeId(0),
+        };
+
+        result.push(CStatement::Declaration(CDeclaration {
+            name: container.clone(),
+            ty: self.new_type(CType::Pointer(container_type)),
+            constant: true,
+            value: Some(self.lower_reference(signature, for_expression.container)),
+        }));
+
+        result.push(CStatement::Declaration(CDeclaration {
+            name: index.clone(),
+            ty: index_type,
+            constant: false,
+            value: Some(CExpression::Literal("0".to_string())),
+        }));
+
+        let condition = CExpression::Binary(
+            Box::new(variable(&index)),
+            "<".to_string(),
+            Box::new(length),
+        );
+
+        let declaration = CStatement::Declaration(CDeclaration {
+            name: element.clone(),
+            ty: self.new_type(CType::Pointer(element_type)),
+            constant: true,
+            value: Some(value),
+        });
+
+        let next = CStatement::Assignment(
+            variable(&index),
+            "=".to_string(),
+            CExpression::Binary(
+                Box::new(variable(&index)),
+                "+".to_string(),
+                Box::new(CExpression::Literal("1".to_string())),
+            ),
+        );
+
+        let body = self.lower_loop_body(
+            signature,
+            for_expression.body,
+            vec![declaration],
+            vec![next],
+        );
+
+        result.push(CStatement::While(condition, body));
+        result
+    }
+
+    fn lower_arm(
+        &mut self,
+        signature: &DefinitionSignature,
+        body: ExpressionId,
+        tail: bool,
+    ) -> Vec<CStatement> {
+        match tail {
+            true => self.lower_return(signature, body, Some(body)),
+            false => self.lower_statement(signature, body),
+        }
+    }
+
+    /// Returns `value` once the variables still holding a value are dropped.
+    ///
+    /// When something is dropped the value is computed first, as it may read them.
+    fn lower_return(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+        value: Option<ExpressionId>,
+    ) -> Vec<CStatement> {
+        let mut value = value.map(|value| self.lower_result(signature, value));
+
+        let dropped = self.drops.leave();
+
+        if dropped.is_empty() {
+            return vec![CStatement::Return(value)];
+        }
+
+        let mut result = vec![];
+
+        if let Some(returned) = value {
+            let name = format!("result_{}", id.0);
+
+            result.push(CStatement::Declaration(CDeclaration {
+                name: name.clone(),
+                ty: self.lower_return_type(signature),
+                constant: signature.return_variable_type == VariableType::Reference,
+                value: Some(returned),
+            }));
+
+            value = Some(CExpression::Variable(name));
+        }
+
+        result.extend(self.lower_drops(signature, dropped));
+        result.push(CStatement::Return(value));
+
+        result
+    }
+
+    /// Whether `variable` can be declared `const` in C. Dropped values are changed by their
+    /// `Drop`, and `const` in front of a pointer would apply to what it points to instead.
+    fn is_constant(&self, variable: &Variable) -> bool {
+        !is_mutable(variable)
+            && !self.is_dropped(variable)
+            && !matches!(variable.ty, TypeExpression::Pointer(_))
+    }
+
+    /// Whether `variable` owns a value that needs dropping, which it drops when it goes.
+    fn is_dropped(&self, variable: &Variable) -> bool {
+        !variable.variable_type.is_reference()
+            && variable
+                .ty
+                .signature()
+                .is_some_and(|ty| self.ast.needs_drop(ty))
+    }
+
+    /// Drops each of the variables, in order.
+    fn lower_drops(
+        &mut self,
+        signature: &DefinitionSignature,
+        variables: Vec<VariableId>,
+    ) -> Vec<CStatement> {
+        let mut result = vec![];
+
+        for id in variables {
+            let variable = &self.ast.variables[signature][&id];
+            let pointer = CExpression::Unary(
+                "&".to_string(),
+                Box::new(CExpression::Variable(variable.name.clone())),
+            );
+
+            let ty = match variable.ty.is_inferred() {
+                true => self.bindings.get(&id),
+                false => variable.ty.signature(),
+            };
+
+            if let Some(ty) = ty.cloned() {
+                result.extend(self.lower_drop(&ty, pointer));
+            }
+        }
+
+        result
+    }
+
+    /// Drops the `ty` that `pointer` points to, nothing when it doesn't need dropping.
+    ///
+    /// Types with fields to drop go through their drop glue, which calls their own `Drop`
+    /// first, the others only have their own `Drop` to call.
+    fn lower_drop(&mut self, ty: &TypeSignature, pointer: CExpression) -> Option<CStatement> {
+        if !self.ast.needs_drop(ty) {
+            return None;
+        }
+
+        let name = match self.fields_to_drop(ty).is_empty() {
+            true => self.names.definition_c_name(self.ast.find_drop(ty)?),
+            false => {
+                self.drop_glue.insert(ty.clone());
+                format!("drop_{}", self.names.c_name(&ty.owner, &ty.name))
+            }
+        };
+
+        Some(CStatement::Expression(CExpression::Call(
+            name,
+            vec![pointer],
+        )))
+    }
+
+    /// Fields of the variants of `ty` that need dropping, as `(kind, variant, position, type)`.
+    fn fields_to_drop(&self, ty: &TypeSignature) -> Vec<(usize, String, usize, TypeSignature)> {
+        let Some(TypeBody::Enum(variants)) = self.ast.types.get(ty).map(|ty| &ty.ty) else {
+            return vec![];
+        };
+
+        variants
+            .iter()
+            .enumerate()
+            .flat_map(|(kind, variant)| {
+                variant
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(position, field)| Some((position, field.signature()?)))
+                    .filter(|(_, field)| self.ast.needs_drop(field))
+                    .map(move |(position, field)| {
+                        (kind, variant.name.clone(), position, field.clone())
+                    })
+            })
+            .collect()
+    }
+
+    /// `void drop_T(T *value)`, calling the own `Drop` of `ty` and then dropping the fields
+    /// of the variant the value holds.
+    fn lower_drop_glue(&mut self, ty: &TypeSignature) {
+        let value = || {
+            CExpression::Unary(
+                "*".to_string(),
+                Box::new(CExpression::Variable("value".to_string())),
+            )
+        };
+        let mut body = vec![];
+
+        if let Some(drop) = self.ast.find_drop(ty) {
+            body.push(CStatement::Expression(CExpression::Call(
+                self.names.definition_c_name(drop),
+                vec![CExpression::Variable("value".to_string())],
+            )));
+        }
+
+        let mut variants: Vec<(usize, Vec<CStatement>)> = vec![];
+
+        for (kind, variant, position, field) in self.fields_to_drop(ty) {
+            let member =
+                CExpression::Member(Box::new(value()), format!("{}_{}", variant, position));
+            let dropped = self.lower_drop(
+                &field,
+                CExpression::Unary("&".to_string(), Box::new(member)),
+            );
+
+            match variants.last_mut() {
+                Some((last, statements)) if *last == kind => statements.extend(dropped),
+                _ => variants.push((kind, dropped.into_iter().collect())),
+            }
+        }
+
+        for (kind, statements) in variants {
+            let is_kind = CExpression::Binary(
+                Box::new(CExpression::Member(Box::new(value()), "kind".to_string())),
+                "==".to_string(),
+                Box::new(CExpression::Literal(kind.to_string())),
+            );
+
+            body.push(CStatement::If(is_kind, statements, vec![]));
+        }
+
+        let c_type = self.lower_type(ty);
+
+        let function = Function {
+            signature: FunctionSignature {
+                name: format!("drop_{}", self.names.c_name(&ty.owner, &ty.name)),
+                ty: CTypeId(0),
+                constant: false,
+                args: vec![FunctionArgs {
+                    name: "value".to_string(),
+                    ty: self.new_type(CType::Pointer(c_type)),
+                    constant: false,
+                }],
+            },
+            line: None,
+            body,
+        };
+
+        self.builder.new_function(self.file, function);
+    }
+
+    /// Lowers a value that's moved, a variable holding it isn't dropped afterwards.
+    fn lower_moved(&mut self, signature: &DefinitionSignature, id: ExpressionId) -> CExpression {
+        let value = self.lower_expression(signature, id);
+
+        if let Some(Expression::Variable(variable)) = self.ast.find_expression(id) {
+            self.drops.move_out(*variable);
+        }
+
+        value
+    }
+
+    /// `Type::Variant(fields)` builds the variant, `Type::Name(args)` calls the definition.
+    fn lower_associated(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+        associated: &Associated,
+    ) -> CExpression {
+        let ty = &associated.ty.ty;
+
+        if let Some((kind, variant)) = self.ast.find_variant(ty, &associated.name) {
+            let c_type = self.lower_type(ty);
+
+            let mut fields = vec![CExpression::Designated(
+                "kind".to_string(),
+                Box::new(CExpression::Literal(kind.to_string())),
+            )];
+
+            for (i, arg) in associated.args.iter().enumerate() {
+                fields.push(CExpression::Designated(
+                    format!("{}_{}", variant.name, i),
+                    Box::new(self.lower_moved(signature, *arg)),
+                ));
+            }
+
+            return CExpression::Compound(self.builder.file(self.file).type_name(c_type), fields);
+        }
+
+        let Some(definition) = self.ast.find_method(ty, &associated.name) else {
+            return self.unsupported(id, "this associated definition");
+        };
+
+        let args = definition
+            .args
+            .iter()
+            .zip(&associated.args)
+            .map(|(parameter, arg)| self.lower_argument(signature, definition, *parameter, *arg))
+            .collect();
+
+        Self::dereference_result(
+            definition,
+            CExpression::Call(self.names.definition_c_name(definition), args),
+        )
+    }
+
+    /// A `StaticString` pointing at the bytes of the literal's constant.
+    fn lower_string(&mut self, text: &str) -> CExpression {
+        let constant = CExpression::Variable(self.string_constant(text));
+
+        let Some((ty, variant)) = self
+            .ast
+            .static_string()
+            .and_then(|ty| Some((ty, self.ast.find_variant(ty, "StaticString")?.1)))
+        else {
+            return CExpression::Literal("0".to_string());
+        };
+
+        let c_type = self.lower_type(ty);
+        let bytes = self.lower_type_expression(&variant.fields[0]);
+        let file = self.builder.file(self.file);
+
+        CExpression::Compound(
+            file.type_name(c_type),
+            vec![
+                CExpression::Designated(
+                    "kind".to_string(),
+                    Box::new(CExpression::Literal("0".to_string())),
+                ),
+                CExpression::Designated(
+                    "StaticString_0".to_string(),
+                    Box::new(CExpression::Cast(
+                        file.type_name(bytes),
+                        Box::new(CExpression::Member(
+                            Box::new(constant.clone()),
+                            "bytes".to_string(),
+                        )),
+                    )),
+                ),
+                CExpression::Designated(
+                    "StaticString_1".to_string(),
+                    Box::new(CExpression::Member(
+                        Box::new(constant),
+                        "length".to_string(),
+                    )),
+                ),
+            ],
+        )
+    }
+
+    /// Name of the constant holding `text`, its length followed by its bytes without a NUL.
+    fn string_constant(&mut self, text: &str) -> String {
+        if let Some(name) = self.strings.get(text) {
+            return name.clone();
+        }
+
+        let bytes = text.as_bytes();
+        let name = format!("rosario_string_{}", self.strings.len());
+
+        let ty = match self.string_types.get(&bytes.len()) {
+            Some(ty) => *ty,
+            None => {
+                let length = self.builder.new_type(self.file, CType::UnsignedInteger(32));
+                let byte = self.builder.new_type(self.file, CType::UnsignedInteger(8));
+                // C has no empty arrays, an empty literal keeps one unused byte.
+                let array = self
+                    .builder
+                    .new_type(self.file, CType::Array(byte, bytes.len().max(1)));
+
+                let ty = self.builder.new_type(
+                    self.file,
+                    CType::Struct(CStruct {
+                        name: format!("rosario_string_of_{}", bytes.len()),
+                        elements: vec![
+                            CStructElement {
+                                name: "length".to_string(),
+                                ty: length,
+                            },
+                            CStructElement {
+                                name: "bytes".to_string(),
+                                ty: array,
+                            },
+                        ],
+                    }),
+                );
+
+                self.string_types.insert(bytes.len(), ty);
+                ty
+            }
+        };
+
+        let mut elements = bytes
+            .iter()
+            .map(|byte| CExpression::Literal(byte.to_string()))
+            .collect::<Vec<_>>();
+
+        if elements.is_empty() {
+            elements.push(CExpression::Literal("0".to_string()));
+        }
+
+        self.builder.new_constant(
+            self.file,
+            CDeclaration {
+                name: name.clone(),
+                ty,
+                constant: true,
+                value: Some(CExpression::Initializer(vec![
+                    CExpression::Literal(bytes.len().to_string()),
+                    CExpression::Initializer(elements),
+                ])),
+            },
+        );
+
+        self.strings.insert(text.to_string(), name.clone());
+        name
+    }
+
+    /// Calls the method with its receiver first, by address when it takes `&self` or `mutable self`.
+    fn lower_method_call(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+        call: &MethodCall,
+    ) -> CExpression {
+        let method = match typecheck::infer(self.ast, signature, call.receiver) {
+            Type::Named(ty) => self.ast.find_method(&ty, &call.name),
+            Type::Array(ty) if call.name == "Length" => {
+                return match typecheck::array_length(self.ast, &ty) {
+                    Some(length) => integer_literal(length),
+                    None => self.unsupported(id, "this method call"),
+                };
+            }
+            _ => None,
+        };
+
+        // `Copy` types without a `Clone` of their own are cloned by copying them.
+        if method.is_none() && call.name == "Clone" && call.args.is_empty() {
+            return self.lower_expression(signature, call.receiver);
+        }
+
+        let Some(method) = method else {
+            return self.unsupported(id, "this method call");
+        };
+
+        let args = method
+            .args
+            .iter()
+            .zip([call.receiver].iter().chain(&call.args))
+            .map(|(parameter, arg)| self.lower_argument(signature, method, *parameter, *arg))
+            .collect();
+
+        Self::dereference_result(
+            method,
+            CExpression::Call(self.names.definition_c_name(method), args),
+        )
+    }
+
+    /// `base[index]` reads the C array from the range's minimum, which is known to be in bounds.
+    fn lower_index(&mut self, signature: &DefinitionSignature, index: &Index) -> CExpression {
+        let base = typecheck::infer(self.ast, signature, index.base);
+
+        // Raw pointers are indexed like C pointers, from zero and without bounds.
+        if let Type::Pointer(_) = base {
+            return CExpression::Index(
+                Box::new(self.lower_expression(signature, index.base)),
+                Box::new(self.lower_expression(signature, index.index)),
+            );
+        }
+
+        let min = match base {
+            Type::Array(TypeExpression::Array { index: range, .. }) => range
+                .signature()
+                .and_then(|range| range_bounds(self.ast, range))
+                .map_or(0, |(min, _)| min),
+            _ => 0,
+        };
+
+        let position = match self.ast.evaluate(index.index) {
+            Some(value) => integer_literal(value - min),
+            None if min == 0 => self.lower_expression(signature, index.index),
+            None => CExpression::Binary(
+                Box::new(self.lower_expression(signature, index.index)),
+                "-".to_string(),
+                Box::new(integer_literal(min)),
+            ),
+        };
+
+        CExpression::Index(
+            Box::new(CExpression::Member(
+                Box::new(self.lower_expression(signature, index.base)),
+                "elements".to_string(),
+            )),
+            Box::new(position),
+        )
+    }
+
+    /// Passes the argument by address when the parameter is a reference.
+    fn lower_argument(
+        &mut self,
+        signature: &DefinitionSignature,
+        definition: &DefinitionSignature,
+        parameter: VariableId,
+        arg: ExpressionId,
+    ) -> CExpression {
+        match self.ast.variables[definition][&parameter]
+            .variable_type
+            .is_reference()
+        {
+            true => self.lower_reference(signature, arg),
+            false => self.lower_moved(signature, arg),
+        }
+    }
+
+    /// The value returned by the definition, its address when it returns a reference.
+    fn lower_result(
+        &mut self,
+        signature: &DefinitionSignature,
+        value: ExpressionId,
+    ) -> CExpression {
+        match signature.return_variable_type.is_reference() {
+            true => self.lower_reference(signature, value),
+            false => self.lower_moved(signature, value),
+        }
+    }
+
+    /// Lowers an expression in a reference position to a pointer.
+    ///
+    /// Reference variables and calls returning references already are pointers, and are
+    /// only dereferenced when read as values, so that's undone here.
+    fn lower_reference(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+    ) -> CExpression {
+        if let Some(Expression::UnaryOperation(un_op)) = self.ast.find_expression(id)
+            && un_op.op == UnOpType::Reference
+        {
+            return self.lower_expression(signature, id);
+        }
+
+        match self.lower_expression(signature, id) {
+            CExpression::Unary(op, pointer) if op == "*" => *pointer,
+            value => CExpression::Unary("&".to_string(), Box::new(value)),
+        }
+    }
+
+    /// Calls to definitions returning references give a pointer, read through it.
+    fn dereference_result(definition: &DefinitionSignature, call: CExpression) -> CExpression {
+        match definition.return_variable_type.is_reference() {
+            true => CExpression::Unary("*".to_string(), Box::new(call)),
+            false => call,
+        }
+    }
+
+    /// Converts with a runtime check unless every value of the source fits in the target.
+    /// `left * right` or `left / right` of two fixed-point values, rescaled to the delta
+    /// `p / q` of their type as `left * right * p / q` or `left * q / (right * p)`.
+    fn lower_scaled(
+        &mut self,
+        id: ExpressionId,
+        scaling: &Scaling,
+        left: CExpression,
+        right: CExpression,
+    ) -> CExpression {
+        let (Scaling::Product(ty) | Scaling::Quotient(ty)) = scaling else {
+            return self.unsupported(id, "this constant");
+        };
+
+        let Some(ratio) = fixed_bounds(self.ast, ty).and_then(|(delta, ..)| delta_ratio(&delta))
+        else {
+            return self.unsupported(id, "this fixed-point operation");
+        };
+
+        let integer = self.new_type(CType::Integer(64));
+        let integer = self.builder.file(self.file).type_name(integer);
+
+        let widen = |value| CExpression::Cast(integer.clone(), Box::new(value));
+        let times = |value, factor: i128| match factor {
+            1 => value,
+            factor => CExpression::Binary(
+                Box::new(value),
+                "*".to_string(),
+                Box::new(integer_literal(factor)),
+            ),
+        };
+
+        let (numerator, denominator) = match scaling {
+            Scaling::Product(_) => (
+                times(
+                    CExpression::Binary(Box::new(widen(left)), "*".to_string(), Box::new(right)),
+                    ratio.0,
+                ),
+                integer_literal(ratio.1),
+            ),
+            _ => (times(widen(left), ratio.1), times(widen(right), ratio.0)),
+        };
+
+        CExpression::Binary(Box::new(numerator), "/".to_string(), Box::new(denominator))
+    }
+
+    fn lower_conversion(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+        conversion: &Conversion,
+    ) -> CExpression {
+        let value = self.lower_expression(signature, conversion.value);
+
+        if let Some(TypeBody::Float(_)) = self.ast.types.get(&conversion.ty).map(|ty| &ty.ty) {
+            let ty = self.lower_type(&conversion.ty);
+            let value =
+                CExpression::Cast(self.builder.file(self.file).type_name(ty), Box::new(value));
+
+            // Fixed-point values count deltas.
+            return match typecheck::infer(self.ast, signature, conversion.value) {
+                Type::Named(source) if let Some((delta, ..)) = fixed_bounds(self.ast, &source) => {
+                    CExpression::Binary(
+                        Box::new(value),
+                        "*".to_string(),
+                        Box::new(CExpression::Literal(delta.to_string())),
+                    )
+                }
+                _ => value,
+            };
+        }
+
+        let source = match typecheck::infer(self.ast, signature, conversion.value) {
+            Type::Literal(value) => Some((value, value)),
+            Type::Named(ty) => range_bounds(self.ast, &ty),
+            _ => None,
+        };
+
+        let Some((min, max)) = range_bounds(self.ast, &conversion.ty) else {
+            return self.unsupported(id, "this conversion");
+        };
+
+        let (source_min, source_max) = source.unwrap_or((i64::MIN as i128, u64::MAX as i128));
+
+        if min <= source_min && source_max <= max {
+            return value;
+        }
+
+        let signed = source_min < 0;
+        let (min, max) = match signed {
+            true => (min, max.min(i64::MAX as i128)),
+            false => (min.max(0), max),
+        };
+
+        self.helpers.insert((conversion.balanced, signed));
+
+        CExpression::Call(
+            conversion_helper_name(conversion.balanced, signed),
+            vec![value, integer_literal(min), integer_literal(max)],
+        )
+    }
+
+    fn lower_conversion_helper(&mut self, balanced: bool, signed: bool) {
+        let integer = match signed {
+            true => self.new_type(CType::Integer(64)),
+            false => self.new_type(CType::UnsignedInteger(64)),
+        };
+
+        let variable = |name: &str| Box::new(CExpression::Variable(name.to_string()));
+        let is_less = CExpression::Binary(variable("value"), "<".to_string(), variable("min"));
+        let is_more = CExpression::Binary(variable("value"), ">".to_string(), variable("max"));
+
+        let mut body = if balanced {
+            vec![
+                CStatement::If(
+                    is_less,
+                    vec![CStatement::Return(Some(*variable("min")))],
+                    vec![],
+                ),
+                CStatement::If(
+                    is_more,
+                    vec![CStatement::Return(Some(*variable("max")))],
+                    vec![],
+                ),
+            ]
+        } else {
+            self.include("stdio.h");
+            self.include("stdlib.h");
+
+            let message = "\"panic: the value doesn't fit in the converted type\\n\"";
+
+            vec![CStatement::If(
+                CExpression::Binary(Box::new(is_less), "||".to_string(), Box::new(is_more)),
+                vec![
+                    CStatement::Expression(CExpression::Call(
+                        "fputs".to_string(),
+                        vec![
+                            CExpression::Literal(message.to_string()),
+                            *variable("stderr"),
+                        ],
+                    )),
+                    CStatement::Expression(CExpression::Call("abort".to_string(), vec![])),
+                ],
+                vec![],
+            )]
+        };
+
+        body.push(CStatement::Return(Some(*variable("value"))));
+
+        let args = ["value", "min", "max"]
+            .into_iter()
+            .map(|name| FunctionArgs {
+                name: name.to_string(),
+                ty: integer,
+                constant: true,
+            })
+            .collect();
+
+        let function = Function {
+            signature: FunctionSignature {
+                name: conversion_helper_name(balanced, signed),
+                ty: integer,
+                constant: false,
+                args,
+            },
+            line: None,
+            body,
+        };
+
+        self.builder.new_function(self.file, function);
+    }
+
+    fn unsupported(&mut self, id: ExpressionId, what: &str) -> CExpression {
+        self.diagnostics.push(
+            Diagnostic::error(format!("{} can't be lowered to C yet", what))
+                .at(self.ast.find_location(id)),
+        );
+
+        CExpression::Literal("0".to_string())
+    }
+}
@@ -1 +1,403 @@
-fn main() {}
+use std::process::ExitCode;
+
+use crate::{
+    cli::{Arguments, Command, USAGE},
+    driver::Driver,
+};
+
+mod cli;
+mod driver;
+mod drops;
+mod lower;
+
+/// Exit code of an invalid command line.
+const USAGE_ERROR: u8 = 2;
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    let arguments = match Arguments::parse(&args) {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::from(USAGE_ERROR);
+        }
+    };
+
+    if arguments.command == Command::Help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    ExitCode::from(Driver::new(arguments).run())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::{ERRORS, SUCCESS};
+
+    fn arguments(args: &[&str]) -> Result<Arguments, String> {
+        Arguments::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn command_line() {
+        let run = arguments(&["run", "-O2", "--cc", "tcc", "main.ros", "--", "-v"]).unwrap();
+
+        assert_eq!(run.command, Command::Run);
+        assert_eq!(run.input.to_str(), Some("main.ros"));
+        assert_eq!(run.compiler.as_deref(), Some("tcc"));
+        assert_eq!(run.optimization, rosarioc::OptimizationLevel::Default);
+        assert_eq!(run.program_arguments, ["-v"]);
+
+        let audit = arguments(&["audit", "main.ros"]).unwrap();
+        assert_eq!(audit.command, Command::Audit);
+
+        let format = arguments(&["fmt", "--check", "core"]).unwrap();
+        assert_eq!(format.command, Command::Format);
+        assert!(format.check);
+
+        assert!(arguments(&["build"]).is_err());
+        assert!(arguments(&["launch", "main.ros"]).is_err());
+        assert!(arguments(&["check", "--color", "sometimes", "main.ros"]).is_err());
+    }
+
+    #[test]
+    fn build_and_run() {
+        let source = "../rosarioparser/tests/local_variables.ros";
+
+        let driver = Driver::new(arguments(&["check", "--color", "never", source]).unwrap());
+        assert_eq!(driver.run(), SUCCESS);
+
+        let driver = Driver::new(arguments(&["check", "missing.ros"]).unwrap());
+        assert_eq!(driver.run(), ERRORS);
+
+        let driver =
+            Driver::new(arguments(&["emit-c", "-o", "/nonexistent/folder/out.c", source]).unwrap());
+        assert_eq!(driver.run(), ERRORS);
+
+        let directory =
+            std::env::temp_dir().join(format!("rosario-build-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        // Reported before the C compiler is looked for.
+        let library = directory.join("library.ros");
+        std::fs::write(&library, "procedure Helper() is\nbegin\nend Helper;\n").unwrap();
+
+        let driver = Driver::new(
+            arguments(&["build", "--color", "never", library.to_str().unwrap()]).unwrap(),
+        );
+        assert_eq!(driver.run(), ERRORS);
+
+        if rosarioc::CCompiler::find().is_none() {
+            eprintln!("skipping `rosario build` and `rosario run`: no C compiler was found");
+            std::fs::remove_dir_all(&directory).unwrap();
+            return;
+        }
+
+        let output = directory.join("local_variables");
+        let driver =
+            Driver::new(arguments(&["build", "-o", output.to_str().unwrap(), source]).unwrap());
+        assert_eq!(driver.run(), SUCCESS);
+
+        // Only the executable is left.
+        let entries = std::fs::read_dir(&directory).unwrap().count();
+        assert_eq!(entries, 2);
+        assert!(output.is_file());
+
+        let driver = Driver::new(arguments(&["run", "--color", "never", source]).unwrap());
+        assert_eq!(driver.run(), SUCCESS);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn format() {
+        let driver = Driver::new(arguments(&["fmt", "--check", "../../core"]).unwrap());
+        assert_eq!(driver.run(), SUCCESS);
+
+        let directory = std::env::temp_dir().join(format!("rosario-fmt-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("main.ros");
+        std::fs::write(
+            &path,
+            "procedure Main() is\nbegin\nlet a: Integer=2;\nend Main;\n",
+        )
+        .unwrap();
+
+        let source = path.to_str().unwrap();
+
+        let driver =
+            Driver::new(arguments(&["fmt", "--check", "--color", "never", source]).unwrap());
+        assert_eq!(driver.run(), ERRORS);
+
+        let driver = Driver::new(arguments(&["fmt", source]).unwrap());
+        assert_eq!(driver.run(), SUCCESS);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "procedure Main() is\nbegin\n    let a: Integer = 2;\nend Main;\n"
+        );
+
+        let driver = Driver::new(arguments(&["fmt", "--check", source]).unwrap());
+        assert_eq!(driver.run(), SUCCESS);
+
+        // Files that don't parse are left as they are.
+        std::fs::write(&path, "procedure Main( is").unwrap();
+
+        let driver = Driver::new(arguments(&["fmt", "--color", "never", source]).unwrap());
+        assert_eq!(driver.run(), ERRORS);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "procedure Main( is"
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn drops() {
+        let source = "../rosarioparser/tests/drops.ros";
+
+        let driver = Driver::new(arguments(&["check", "--color", "never", source]).unwrap());
+        let result = lower::lower(&driver.check().unwrap(), "drops.c").unwrap();
+
+        let output = result
+            .files
+            .values()
+            .map(|file| file.emit())
+            .collect::<String>();
+
+        // Statements of the function defined by `signature`, without `#line` directives.
+        let body = |signature: &str| {
+            output
+                .lines()
+                .skip_while(|line| *line != format!("{} {{", signature))
+                .skip(1)
+                .take_while(|line| *line != "}")
+                .filter(|line| !line.starts_with('#'))
+                .map(str::trim)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            body("tests_drops_Handle tests_drops_Pick(tests_drops_Handle flag)"),
+            [
+                "tests_drops_Handle first = tests_drops_Make(1);",
+                "tests_drops_Handle second = tests_drops_Make(2);",
+                "if (flag > 50) {",
+                "tests_drops_Handle result_23 = first;",
+                "tests_drops_Handle_Drop((&second));",
+                "tests_drops_Handle_Drop((&flag));",
+                "return result_23;",
+                "} else if (flag > 25) {",
+                "tests_drops_Consume(second);",
+                "} else {",
+                "tests_drops_Handle_Drop((&second));",
+                "}",
+                "tests_drops_Handle result_34 = tests_drops_Make(3);",
+                "tests_drops_Handle_Drop((&first));",
+                "tests_drops_Handle_Drop((&flag));",
+                "return result_34;",
+            ]
+        );
+
+        assert_eq!(
+            body("void tests_drops_Main(void)"),
+            [
+                "tests_drops_Handle kept = tests_drops_Make(4);",
+                "tests_drops_Handle moved = tests_drops_Make(5);",
+                "tests_drops_Consume(moved);",
+                "tests_drops_Handle assign_47 = tests_drops_Make(6);",
+                "tests_drops_Handle_Drop((&kept));",
+                "kept = assign_47;",
+                "{",
+                "tests_drops_Handle inner = tests_drops_Pick(kept);",
+                "if (inner > 10) {",
+                "kept = tests_drops_Make(7);",
+                "tests_drops_Handle_Drop((&kept));",
+                "}",
+                "tests_drops_Handle_Drop((&inner));",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn foreign() {
+        let source = "../rosarioparser/tests/foreign/main.ros";
+
+        let driver = Driver::new(arguments(&["check", "--color", "never", source]).unwrap());
+        let result = lower::lower(&driver.check().unwrap(), "foreign.c").unwrap();
+
+        let file = result.files.values().next().unwrap();
+
+        let includes = file
+            .includes
+            .iter()
+            .map(|include| include.0.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(includes, ["stdlib.h", "string.h"]);
+
+        let output = file.emit();
+
+        assert!(output.contains("return malloc(size);"));
+        assert!(output.contains("return strlen(text);"));
+        assert!(output.contains("foreign_memory_Release(foreign_memory_Allocate(16));"));
+    }
+
+    #[test]
+    fn arrays() {
+        let source = "../rosarioparser/tests/arrays.ros";
+
+        let driver = Driver::new(arguments(&["check", "--color", "never", source]).unwrap());
+        let result = lower::lower(&driver.check().unwrap(), "arrays.c").unwrap();
+
+        let output = result.files.values().next().unwrap().emit();
+
+        assert!(output.contains(
+            "typedef struct {\n    tests_arrays_Count elements[7];\n} \
+             array_tests_arrays_Weekday_of_tests_arrays_Count;"
+        ));
+        assert!(output.contains("visits.elements[(day - 1)] = (visits.elements[(day - 1)] + 1);"));
+        assert!(output.contains("return ((*values).elements[0] + (*values).elements[6]);"));
+        assert!(output.contains("const tests_arrays_Count length = 10;"));
+    }
+
+    /// Builds the test program `name` with the sanitizers when the compiler has them, and
+    /// runs it. The programs abort on a wrong result, and the sanitizer fails them on leaks.
+    fn run_checked(name: &str) {
+        let Some(compiler) = rosarioc::CCompiler::find() else {
+            return;
+        };
+
+        let source = format!("../rosarioparser/tests/{}.ros", name);
+
+        let driver = Driver::new(arguments(&["check", "--color", "never", &source]).unwrap());
+        let mut result = lower::lower(&driver.check().unwrap(), &format!("{}.c", name)).unwrap();
+        result.compiler = compiler.clone();
+
+        let flags = match compiler.name.as_str() {
+            "gcc" | "clang" | "cc" => vec!["-fsanitize=address".to_string()],
+            _ => vec![],
+        };
+
+        let directory =
+            std::env::temp_dir().join(format!("rosario-{}-{}", name, std::process::id()));
+        let options = rosarioc::BuildOptions {
+            build_directory: directory.clone(),
+            output: directory.join(name),
+            flags,
+            ..Default::default()
+        };
+
+        let outcome = result.build(&options).unwrap();
+
+        assert!(outcome.success, "{}", outcome.output);
+
+        let status = std::process::Command::new(directory.join(name))
+            .status()
+            .unwrap();
+
+        assert!(status.success());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn groups() {
+        run_checked("groups");
+    }
+
+    #[test]
+    fn generic_methods() {
+        run_checked("generic_methods");
+    }
+
+    #[test]
+    fn aliases() {
+        run_checked("aliases");
+    }
+
+    #[test]
+    fn options() {
+        run_checked("options");
+    }
+
+    #[test]
+    fn drop_glue() {
+        run_checked("drop_glue");
+
+        let source = "../rosarioparser/tests/drop_glue.ros";
+
+        let driver = Driver::new(arguments(&["check", "--color", "never", source]).unwrap());
+        let result = lower::lower(&driver.check().unwrap(), "drop_glue.c").unwrap();
+
+        let output = result.files.values().next().unwrap().emit();
+
+        assert!(output.contains("drop_core_options_Option_String((&kept));"));
+        assert!(output.contains("drop_core_options_Option_Option_String((&nested));"));
+        assert!(!output.contains("(&counted)"));
+    }
+
+    #[test]
+    fn instance_names() {
+        run_checked("instance_names");
+
+        let source = "../rosarioparser/tests/instance_names.ros";
+
+        let driver = Driver::new(arguments(&["check", "--color", "never", source]).unwrap());
+        let result = lower::lower(&driver.check().unwrap(), "instance_names.c").unwrap();
+
+        let output = result.files.values().next().unwrap().emit();
+
+        assert!(output.contains("} core_options_Option_Int32;"));
+        assert!(output.contains("} tests_instance_names_Pair_A_B_C_1;"));
+        assert!(output.contains("} tests_instance_names_Pair_A_B_C_2;"));
+    }
+
+    #[test]
+    fn unicode() {
+        run_checked("unicode");
+    }
+
+    #[test]
+    fn strings() {
+        run_checked("strings");
+
+        let source = "../rosarioparser/tests/strings.ros";
+
+        let driver = Driver::new(arguments(&["check", "--color", "never", source]).unwrap());
+        let result = lower::lower(&driver.check().unwrap(), "strings.c").unwrap();
+
+        let output = result.files.values().next().unwrap().emit();
+
+        assert!(output.contains(
+            "static const rosario_string_of_7 rosario_string_0 = \
+             { 7, { 72, 101, 108, 108, 111, 44, 32 } };"
+        ));
+        assert!(
+            output.contains("static const rosario_string_of_0 rosario_string_2 = { 0, { 0 } };")
+        );
+        assert!(output.contains(".StaticString_1 = rosario_string_0.length }"));
+        assert!(output.contains("{ 5, { 9, 34, 92, 195, 169 } }"));
+    }
+
+    #[test]
+    fn reals() {
+        run_checked("reals");
+
+        let source = "../rosarioparser/tests/reals.ros";
+
+        let driver = Driver::new(arguments(&["check", "--color", "never", source]).unwrap());
+        let result = lower::lower(&driver.check().unwrap(), "reals.c").unwrap();
+
+        let output = result.files.values().next().unwrap().emit();
+
+        assert!(output.contains("typedef double core_basic_types_Float64;"));
+        assert!(output.contains("typedef int32_t tests_reals_Money;"));
+        assert!(output.contains("const tests_reals_Money price = 1999;"));
+    }
+}
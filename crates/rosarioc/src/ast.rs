@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 #[derive(Debug, Default, Clone)]
 pub struct CCompiler {
     pub name: String,
+    pub path: PathBuf,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -11,13 +12,15 @@ pub struct CResult {
     pub files: HashMap<CFileId, CFile>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CFileId(pub usize);
 
 #[derive(Debug, Default, Clone)]
 pub struct CFile {
     pub path: String,
     pub includes: Vec<Include>,
+    /// Declared `static` at the top of the file, after the types.
+    pub constants: Vec<CDeclaration>,
     pub functions: Vec<Function>,
     pub types: HashMap<CTypeId, CType>,
 }
@@ -26,14 +29,17 @@ pub struct CFile {
 pub enum CType {
     #[default]
     Void,
+    Bool,
     Integer(usize),
+    UnsignedInteger(usize),
     Float(usize),
     Pointer(CTypeId),
     Array(CTypeId, usize),
     Struct(CStruct),
+    Typedef(String, CTypeId),
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CTypeId(pub usize);
 
 #[derive(Debug, Default, Clone)]
@@ -51,12 +57,16 @@ pub struct CStructElement {
 #[derive(Debug, Default, Clone)]
 pub struct Function {
     pub signature: FunctionSignature,
+    pub line: Option<CLine>,
+    pub body: Vec<CStatement>,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct FunctionSignature {
     pub name: String,
-    pub ty: CType,
+    pub ty: CTypeId,
+    /// Prefixes the result with `const`, for pointers to values that can't be changed.
+    pub constant: bool,
     pub args: Vec<FunctionArgs>,
 }
 
@@ -64,7 +74,60 @@ pub struct FunctionSignature {
 pub struct FunctionArgs {
     pub name: String,
     pub ty: CTypeId,
+    pub constant: bool,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Include(pub String);
+
+/// Location in the original source, emitted as a `#line` directive so the C compiler
+/// reports its diagnostics against the Rosario file instead of the generated one.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CLine {
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum CStatement {
+    Line(CLine),
+    Declaration(CDeclaration),
+    Expression(CExpression),
+    /// `place op value;`, where `op` is `=` or a compound assignment like `+=`.
+    Assignment(CExpression, String, CExpression),
+    Return(Option<CExpression>),
+    /// `if (condition) { ... } else { ... }`, without the `else` when it's empty.
+    If(CExpression, Vec<CStatement>, Vec<CStatement>),
+    /// `while (condition) { ... }`.
+    While(CExpression, Vec<CStatement>),
+    Block(Vec<CStatement>),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CDeclaration {
+    pub name: String,
+    pub ty: CTypeId,
+    pub constant: bool,
+    pub value: Option<CExpression>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CExpression {
+    Literal(String),
+    Variable(String),
+    Unary(String, Box<CExpression>),
+    /// `value.name`.
+    Member(Box<CExpression>, String),
+    Binary(Box<CExpression>, String, Box<CExpression>),
+    Call(String, Vec<CExpression>),
+    /// `value[index]`.
+    Index(Box<CExpression>, Box<CExpression>),
+    /// `{ a, b, ... }`, only valid inside an initializer.
+    Initializer(Vec<CExpression>),
+    /// `(name){ a, b, ... }`, a compound literal of the named type.
+    Compound(String, Vec<CExpression>),
+    /// `.name = value`, only valid inside an initializer.
+    Designated(String, Box<CExpression>),
+    /// `((name)value)`, converting the value to the named type.
+    Cast(String, Box<CExpression>),
+}
@@ -1,5 +1,6 @@
-use crate::{CFileId, CResult, CTypeId};
+use crate::{CDeclaration, CFile, CFileId, CResult, CType, CTypeId, Function};
 
+#[derive(Default)]
 pub struct Builder {
     pub result: CResult,
 }
@@ -11,7 +12,39 @@ impl Builder {
         }
     }
 
-    /* pub fn new_file(&mut self, name: &str) -> CFileId {
-        self.result.
-    } */
+    /// Creates an empty file whose `CTypeId(0)` is `void`.
+    pub fn new_file(&mut self, path: &str) -> CFileId {
+        let id = CFileId(self.result.files.len());
+
+        let mut file = CFile {
+            path: path.to_string(),
+            ..Default::default()
+        };
+        file.types.insert(CTypeId(0), CType::Void);
+
+        self.result.files.insert(id, file);
+
+        id
+    }
+
+    pub fn file(&mut self, id: CFileId) -> &mut CFile {
+        self.result.files.get_mut(&id).unwrap()
+    }
+
+    pub fn new_type(&mut self, file: CFileId, ty: CType) -> CTypeId {
+        let file = self.file(file);
+        let id = CTypeId(file.types.len());
+
+        file.types.insert(id, ty);
+
+        id
+    }
+
+    pub fn new_function(&mut self, file: CFileId, function: Function) {
+        self.file(file).functions.push(function);
+    }
+
+    pub fn new_constant(&mut self, file: CFileId, constant: CDeclaration) {
+        self.file(file).constants.push(constant);
+    }
 }
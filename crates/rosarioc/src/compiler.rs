@@ -0,0 +1,257 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{CCompiler, CResult};
+
+/// Compilers probed by `CCompiler::find`, in order of preference.
+pub const KNOWN_COMPILERS: [&str; 4] = ["cc", "gcc", "clang", "tcc"];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OptimizationLevel {
+    #[default]
+    None,
+    Less,
+    Default,
+    Aggressive,
+    Size,
+}
+
+impl OptimizationLevel {
+    pub fn flag(&self) -> &'static str {
+        match self {
+            OptimizationLevel::None => "-O0",
+            OptimizationLevel::Less => "-O1",
+            OptimizationLevel::Default => "-O2",
+            OptimizationLevel::Aggressive => "-O3",
+            OptimizationLevel::Size => "-Os",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BuildOptions {
+    pub build_directory: PathBuf,
+    pub output: PathBuf,
+    pub optimization: OptimizationLevel,
+    pub debug_info: bool,
+    pub warnings: bool,
+    pub warnings_as_errors: bool,
+    pub include_directories: Vec<PathBuf>,
+    pub library_directories: Vec<PathBuf>,
+    pub libraries: Vec<String>,
+    pub flags: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CSeverity {
+    #[default]
+    Error,
+    Warning,
+    Note,
+}
+
+/// A diagnostic printed by the C compiler.
+///
+/// Generated files carry `#line` directives, so `file` and `line` point at the Rosario
+/// source whenever the offending C code was lowered from one.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CDiagnostic {
+    pub file: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub severity: CSeverity,
+    pub message: String,
+}
+
+impl CDiagnostic {
+    pub fn is_rosario_source(&self) -> bool {
+        self.file.ends_with(".ros")
+    }
+
+    /// Parses a `file:line:column: severity: message` line, as printed by gcc, clang and tcc.
+    pub fn parse(line: &str) -> Option<Self> {
+        let (severity, index, length) = [
+            (CSeverity::Error, ": fatal error: "),
+            (CSeverity::Error, ": error: "),
+            (CSeverity::Warning, ": warning: "),
+            (CSeverity::Note, ": note: "),
+        ]
+        .into_iter()
+        .find_map(|(severity, marker)| line.find(marker).map(|i| (severity, i, marker.len())))?;
+
+        let location = &line[..index];
+        let message = line[index + length..].to_string();
+
+        let (file, line, column) = match location.rsplit_once(':') {
+            Some((rest, last)) if last.parse::<usize>().is_ok() => match rest.rsplit_once(':') {
+                Some((file, line)) if line.parse::<usize>().is_ok() => {
+                    (file, line.parse().ok(), last.parse().ok())
+                }
+                _ => (rest, last.parse().ok(), None),
+            },
+            _ => (location, None, None),
+        };
+
+        Some(Self {
+            file: file.to_string(),
+            line,
+            column,
+            severity,
+            message,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BuildOutcome {
+    pub success: bool,
+    pub executable: Option<PathBuf>,
+    pub sources: Vec<PathBuf>,
+    pub command: Vec<String>,
+    pub diagnostics: Vec<CDiagnostic>,
+    pub output: String,
+}
+
+impl BuildOutcome {
+    pub fn errors(&self) -> impl Iterator<Item = &CDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == CSeverity::Error)
+    }
+}
+
+impl CResult {
+    pub fn build(&self, options: &BuildOptions) -> std::io::Result<BuildOutcome> {
+        self.compiler.build(self, options)
+    }
+}
+
+impl CCompiler {
+    /// Finds the first compiler of `$CC` and `KNOWN_COMPILERS` available on `PATH`.
+    pub fn find() -> Option<Self> {
+        if let Ok(name) = env::var("CC")
+            && let Some(compiler) = Self::from_name(&name)
+        {
+            return Some(compiler);
+        }
+
+        KNOWN_COMPILERS
+            .iter()
+            .find_map(|name| Self::from_name(name))
+    }
+
+    /// Looks `name` up on `PATH`, or uses it directly when it's already a path.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let candidate = Path::new(name);
+
+        if candidate.components().count() > 1 {
+            return is_executable(candidate).then(|| Self {
+                name: name.to_string(),
+                path: candidate.to_path_buf(),
+            });
+        }
+
+        let paths = env::var_os("PATH")?;
+
+        env::split_paths(&paths)
+            .map(|directory| directory.join(format!("{}{}", name, env::consts::EXE_SUFFIX)))
+            .find(|path| is_executable(path))
+            .map(|path| Self {
+                name: name.to_string(),
+                path,
+            })
+    }
+
+    pub fn arguments(&self, sources: &[PathBuf], options: &BuildOptions) -> Vec<String> {
+        let mut result = vec![options.optimization.flag().to_string()];
+
+        if options.debug_info {
+            result.push("-g".to_string());
+        }
+
+        if options.warnings {
+            result.push("-Wall".to_string());
+
+            if self.name != "tcc" {
+                result.push("-Wextra".to_string());
+            }
+        }
+
+        if options.warnings_as_errors {
+            result.push("-Werror".to_string());
+        }
+
+        for directory in &options.include_directories {
+            result.push(format!("-I{}", directory.display()));
+        }
+
+        for directory in &options.library_directories {
+            result.push(format!("-L{}", directory.display()));
+        }
+
+        result.extend(options.flags.iter().cloned());
+        result.extend(sources.iter().map(|source| source.display().to_string()));
+
+        result.push("-o".to_string());
+        result.push(options.output.display().to_string());
+
+        for library in &options.libraries {
+            result.push(format!("-l{}", library));
+        }
+
+        result
+    }
+
+    /// Writes every file of `result` into the build directory and compiles them together.
+    pub fn build(&self, result: &CResult, options: &BuildOptions) -> std::io::Result<BuildOutcome> {
+        std::fs::create_dir_all(&options.build_directory)?;
+
+        let mut files = result.files.iter().collect::<Vec<_>>();
+        files.sort_by_key(|(id, _)| **id);
+
+        let mut sources = vec![];
+
+        for (_, file) in files {
+            let path = options.build_directory.join(&file.path);
+
+            std::fs::write(&path, file.emit())?;
+
+            sources.push(path);
+        }
+
+        let arguments = self.arguments(&sources, options);
+        let output = Command::new(&self.path).args(&arguments).output()?;
+
+        let mut text = String::from_utf8_lossy(&output.stderr).to_string();
+        text.push_str(&String::from_utf8_lossy(&output.stdout));
+
+        let mut command = vec![self.path.display().to_string()];
+        command.extend(arguments);
+
+        Ok(BuildOutcome {
+            success: output.status.success(),
+            executable: output.status.success().then(|| options.output.clone()),
+            sources,
+            command,
+            diagnostics: text.lines().filter_map(CDiagnostic::parse).collect(),
+            output: text,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
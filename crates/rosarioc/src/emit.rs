@@ -0,0 +1,281 @@
+use std::fmt::Write;
+
+use crate::{CDeclaration, CExpression, CFile, CLine, CStatement, CType, CTypeId, Function};
+
+impl CFile {
+    /// Renders the file as C99 source code.
+    pub fn emit(&self) -> String {
+        let mut result = String::new();
+
+        writeln!(result, "#include <stdbool.h>").unwrap();
+        writeln!(result, "#include <stdint.h>").unwrap();
+
+        for include in &self.includes {
+            writeln!(result, "#include <{}>", include.0).unwrap();
+        }
+
+        let mut ids = self.types.keys().collect::<Vec<_>>();
+        ids.sort();
+
+        for id in ids {
+            match &self.types[id] {
+                CType::Typedef(name, ty) => {
+                    writeln!(result, "\ntypedef {};", self.declarator(*ty, name)).unwrap();
+                }
+                CType::Struct(structure) => {
+                    writeln!(result, "\ntypedef struct {{").unwrap();
+
+                    for element in &structure.elements {
+                        writeln!(
+                            result,
+                            "    {};",
+                            self.declarator(element.ty, &element.name)
+                        )
+                        .unwrap();
+                    }
+
+                    writeln!(result, "}} {};", structure.name).unwrap();
+                }
+                _ => {}
+            }
+        }
+
+        if !self.constants.is_empty() {
+            result.push('\n');
+        }
+
+        for constant in &self.constants {
+            writeln!(result, "static {};", self.declaration(constant)).unwrap();
+        }
+
+        if !self.functions.is_empty() {
+            result.push('\n');
+        }
+
+        for function in &self.functions {
+            writeln!(result, "{};", self.function_signature(function)).unwrap();
+        }
+
+        for function in &self.functions {
+            result.push('\n');
+
+            if let Some(line) = &function.line {
+                Self::emit_line(&mut result, line);
+            }
+
+            writeln!(result, "{} {{", self.function_signature(function)).unwrap();
+            self.emit_statements(&mut result, &function.body, 1);
+            writeln!(result, "}}").unwrap();
+
+            if function.line.is_some() {
+                // Hands the following lines back to the generated file.
+                let line = CLine {
+                    file: self.path.clone(),
+                    line: result.lines().count() + 2,
+                };
+
+                Self::emit_line(&mut result, &line);
+            }
+        }
+
+        result
+    }
+
+    pub fn type_name(&self, id: CTypeId) -> String {
+        match &self.types[&id] {
+            CType::Void => "void".to_string(),
+            CType::Bool => "bool".to_string(),
+            CType::Integer(size) => format!("int{}_t", size),
+            CType::UnsignedInteger(size) => format!("uint{}_t", size),
+            CType::Float(32) => "float".to_string(),
+            CType::Float(64) => "double".to_string(),
+            CType::Float(_) => "long double".to_string(),
+            CType::Pointer(ty) => format!("{}*", self.type_name(*ty)),
+            CType::Array(ty, _) => format!("{}*", self.type_name(*ty)),
+            CType::Struct(structure) => structure.name.clone(),
+            CType::Typedef(name, _) => name.clone(),
+        }
+    }
+
+    /// Declares `name` with type `id`, e.g. `int32_t name` or `uint8_t name[4]`.
+    pub fn declarator(&self, id: CTypeId, name: &str) -> String {
+        match &self.types[&id] {
+            CType::Array(ty, length) => format!("{}[{}]", self.declarator(*ty, name), length),
+            _ => format!("{} {}", self.type_name(id), name),
+        }
+    }
+
+    fn function_signature(&self, function: &Function) -> String {
+        let args = if function.signature.args.is_empty() {
+            "void".to_string()
+        } else {
+            function
+                .signature
+                .args
+                .iter()
+                .map(|arg| match arg.constant {
+                    true => format!("const {}", self.declarator(arg.ty, &arg.name)),
+                    false => self.declarator(arg.ty, &arg.name),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let result = self.declarator(function.signature.ty, &function.signature.name);
+
+        match function.signature.constant {
+            true => format!("const {}({})", result, args),
+            false => format!("{}({})", result, args),
+        }
+    }
+
+    fn emit_line(result: &mut String, line: &CLine) {
+        writeln!(result, "#line {} \"{}\"", line.line, line.file).unwrap();
+    }
+
+    fn emit_statements(&self, result: &mut String, statements: &[CStatement], indent: usize) {
+        let padding = "    ".repeat(indent);
+
+        for statement in statements {
+            match statement {
+                CStatement::Line(line) => Self::emit_line(result, line),
+                CStatement::Declaration(declaration) => {
+                    writeln!(result, "{}{};", padding, self.declaration(declaration)).unwrap();
+                }
+                CStatement::Expression(expression) => {
+                    writeln!(result, "{}{};", padding, Self::expression(expression)).unwrap();
+                }
+                CStatement::Assignment(place, op, value) => {
+                    writeln!(
+                        result,
+                        "{}{} {} {};",
+                        padding,
+                        Self::expression(place),
+                        op,
+                        Self::expression(value)
+                    )
+                    .unwrap();
+                }
+                CStatement::Return(None) => writeln!(result, "{}return;", padding).unwrap(),
+                CStatement::Return(Some(expression)) => {
+                    writeln!(
+                        result,
+                        "{}return {};",
+                        padding,
+                        Self::expression(expression)
+                    )
+                    .unwrap();
+                }
+                CStatement::If(condition, statements, otherwise) => {
+                    write!(result, "{}", padding).unwrap();
+
+                    let (mut condition, mut statements, mut otherwise) =
+                        (condition, statements, otherwise);
+
+                    // An `else` holding a single `if` is written as `else if`.
+                    loop {
+                        let test = match condition {
+                            CExpression::Binary(..) => Self::expression(condition),
+                            _ => format!("({})", Self::expression(condition)),
+                        };
+
+                        writeln!(result, "if {} {{", test).unwrap();
+                        self.emit_statements(result, statements, indent + 1);
+
+                        match otherwise.as_slice() {
+                            [] => break,
+                            [CStatement::If(next, next_statements, next_otherwise)] => {
+                                write!(result, "{}}} else ", padding).unwrap();
+
+                                condition = next;
+                                statements = next_statements;
+                                otherwise = next_otherwise;
+                            }
+                            _ => {
+                                writeln!(result, "{}}} else {{", padding).unwrap();
+                                self.emit_statements(result, otherwise, indent + 1);
+                                break;
+                            }
+                        }
+                    }
+
+                    writeln!(result, "{}}}", padding).unwrap();
+                }
+                CStatement::While(condition, statements) => {
+                    let test = match condition {
+                        CExpression::Binary(..) => Self::expression(condition),
+                        _ => format!("({})", Self::expression(condition)),
+                    };
+
+                    writeln!(result, "{}while {} {{", padding, test).unwrap();
+                    self.emit_statements(result, statements, indent + 1);
+                    writeln!(result, "{}}}", padding).unwrap();
+                }
+                CStatement::Block(statements) => {
+                    writeln!(result, "{}{{", padding).unwrap();
+                    self.emit_statements(result, statements, indent + 1);
+                    writeln!(result, "{}}}", padding).unwrap();
+                }
+            }
+        }
+    }
+
+    fn declaration(&self, declaration: &CDeclaration) -> String {
+        let mut result = self.declarator(declaration.ty, &declaration.name);
+
+        if declaration.constant {
+            result = format!("const {}", result);
+        }
+
+        if let Some(value) = &declaration.value {
+            write!(result, " = {}", Self::expression(value)).unwrap();
+        }
+
+        result
+    }
+
+    pub fn expression(expression: &CExpression) -> String {
+        match expression {
+            CExpression::Literal(literal) => literal.clone(),
+            CExpression::Variable(name) => name.clone(),
+            CExpression::Unary(op, operand) => format!("({}{})", op, Self::expression(operand)),
+            CExpression::Member(value, name) => format!("{}.{}", Self::expression(value), name),
+            CExpression::Binary(left, op, right) => format!(
+                "({} {} {})",
+                Self::expression(left),
+                op,
+                Self::expression(right)
+            ),
+            CExpression::Call(name, args) => format!(
+                "{}({})",
+                name,
+                args.iter()
+                    .map(Self::expression)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            CExpression::Index(value, index) => {
+                format!("{}[{}]", Self::expression(value), Self::expression(index))
+            }
+            CExpression::Initializer(elements) => Self::initializer(elements),
+            CExpression::Compound(name, elements) => {
+                format!("({}){}", name, Self::initializer(elements))
+            }
+            CExpression::Designated(name, value) => {
+                format!(".{} = {}", name, Self::expression(value))
+            }
+            CExpression::Cast(name, value) => format!("(({}){})", name, Self::expression(value)),
+        }
+    }
+
+    fn initializer(elements: &[CExpression]) -> String {
+        format!(
+            "{{ {} }}",
+            elements
+                .iter()
+                .map(Self::expression)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
@@ -1,8 +1,11 @@
 pub mod ast;
 pub mod builder;
+pub mod compiler;
+pub mod emit;
 
 pub use ast::*;
 pub use builder::*;
+pub use compiler::*;
 
 #[cfg(test)]
 mod tests {
@@ -10,4 +13,157 @@ mod tests {
 
     #[test]
     fn it_works() {}
+
+    fn main_file(builder: &mut Builder, value: CExpression) -> CFileId {
+        let file = builder.new_file("main.c");
+        let int32 = builder.new_type(file, CType::Integer(32));
+        let integer = builder.new_type(file, CType::Typedef("main_Integer".to_string(), int32));
+        let int = builder.new_type(file, CType::Typedef("main_Int".to_string(), int32));
+
+        builder.new_function(
+            file,
+            Function {
+                signature: FunctionSignature {
+                    name: "main".to_string(),
+                    ty: int,
+                    constant: false,
+                    args: vec![],
+                },
+                line: Some(CLine {
+                    file: "main.ros".to_string(),
+                    line: 3,
+                }),
+                body: vec![
+                    CStatement::Declaration(CDeclaration {
+                        name: "a".to_string(),
+                        ty: integer,
+                        constant: true,
+                        value: Some(value),
+                    }),
+                    CStatement::Return(Some(CExpression::Variable("a".to_string()))),
+                ],
+            },
+        );
+
+        file
+    }
+
+    #[test]
+    fn emit() {
+        let mut builder = Builder::new();
+        let file = main_file(&mut builder, CExpression::Literal("0".to_string()));
+
+        let byte = builder.new_type(file, CType::UnsignedInteger(8));
+        let bytes = builder.new_type(file, CType::Array(byte, 2));
+        builder.new_constant(
+            file,
+            CDeclaration {
+                name: "greeting".to_string(),
+                ty: bytes,
+                constant: true,
+                value: Some(CExpression::Initializer(vec![
+                    CExpression::Literal("104".to_string()),
+                    CExpression::Literal("105".to_string()),
+                ])),
+            },
+        );
+
+        let output = builder.file(file).emit();
+
+        assert!(output.contains("static const uint8_t greeting[2] = { 104, 105 };"));
+        assert!(output.contains("typedef int32_t main_Integer;"));
+        assert!(output.contains("#line 3 \"main.ros\"\nmain_Int main(void) {"));
+        assert!(output.contains("    const main_Integer a = 0;"));
+
+        let literal = |value: &str| CExpression::Literal(value.to_string());
+
+        let row = CExpression::Compound(
+            "main_Row".to_string(),
+            vec![CExpression::Initializer(vec![literal("1"), literal("2")])],
+        );
+        let element = CExpression::Index(
+            Box::new(CExpression::Member(Box::new(row), "elements".to_string())),
+            Box::new(literal("0")),
+        );
+
+        assert_eq!(
+            CFile::expression(&element),
+            "(main_Row){ { 1, 2 } }.elements[0]"
+        );
+
+        let variant = CExpression::Compound(
+            "main_Option".to_string(),
+            vec![
+                CExpression::Designated("kind".to_string(), Box::new(literal("1"))),
+                CExpression::Designated(
+                    "Some_0".to_string(),
+                    Box::new(CExpression::Cast(
+                        "main_Integer*".to_string(),
+                        Box::new(literal("pointer")),
+                    )),
+                ),
+            ],
+        );
+
+        assert_eq!(
+            CFile::expression(&variant),
+            "(main_Option){ .kind = 1, .Some_0 = ((main_Integer*)pointer) }"
+        );
+    }
+
+    #[test]
+    fn diagnostics() {
+        let gcc = CDiagnostic::parse("main.ros:4:5: error: 'b' undeclared").unwrap();
+
+        assert_eq!(gcc.file, "main.ros");
+        assert_eq!((gcc.line, gcc.column), (Some(4), Some(5)));
+        assert_eq!(gcc.severity, CSeverity::Error);
+        assert!(gcc.is_rosario_source());
+
+        let tcc = CDiagnostic::parse("main.c:12: warning: implicit declaration").unwrap();
+
+        assert_eq!((tcc.line, tcc.column), (Some(12), None));
+        assert_eq!(tcc.severity, CSeverity::Warning);
+
+        assert!(CDiagnostic::parse("In function 'main':").is_none());
+    }
+
+    #[test]
+    fn build() {
+        let Some(compiler) = CCompiler::find() else {
+            return;
+        };
+
+        let directory = std::env::temp_dir().join(format!("rosarioc-build-{}", std::process::id()));
+        let options = BuildOptions {
+            build_directory: directory.clone(),
+            output: directory.join("main"),
+            warnings: true,
+            ..Default::default()
+        };
+
+        let mut builder = Builder::new();
+        builder.result.compiler = compiler.clone();
+        main_file(&mut builder, CExpression::Literal("0".to_string()));
+
+        let outcome = builder.result.build(&options).unwrap();
+
+        assert!(outcome.success, "{}", outcome.output);
+        assert_eq!(outcome.executable, Some(directory.join("main")));
+
+        let mut builder = Builder::new();
+        builder.result.compiler = compiler;
+        main_file(&mut builder, CExpression::Variable("b".to_string()));
+
+        let outcome = builder.result.build(&options).unwrap();
+
+        assert!(!outcome.success);
+
+        let error = outcome.errors().next().unwrap();
+
+        assert!(error.is_rosario_source());
+        assert_eq!(error.line, Some(4));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
 }
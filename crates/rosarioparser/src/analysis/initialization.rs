@@ -0,0 +1,269 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    diagnostic::Diagnostic,
+    parser::ast::{Ast, DefinitionSignature, Expression, ExpressionId, Pattern, VariableId},
+};
+
+/// Variables assigned on the paths reaching a point, `None` once no path reaches it.
+type Assigned = Option<Flow>;
+
+#[derive(Debug, Clone, Default)]
+struct Flow {
+    /// Variables assigned on every path.
+    every: BTreeSet<VariableId>,
+    /// Variables assigned on at least one path.
+    some: BTreeSet<VariableId>,
+}
+
+/// Rejects reads of variables that may not have a value yet, and warns about variables
+/// that are never read.
+///
+/// A `let` without an initializer has to be assigned on every path through `if` branches
+/// and `match` arms before it's read, paths ending in a `return` don't count. Loop bodies
+/// may not run at all, so what they assign doesn't count after them.
+pub fn check_initialization(ast: &Ast) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    for (signature, body) in &ast.definitions {
+        let mut checker = InitializationChecker::new(ast, signature);
+
+        checker.visit(*body, &mut Some(Flow::default()));
+        checker.check_unread();
+
+        diagnostics.extend(checker.diagnostics);
+    }
+
+    diagnostics
+}
+
+/// The `=` assignments of whole variables that may not be their first, because the
+/// variable may already be assigned on a path reaching them, or because a loop repeats
+/// them.
+pub fn repeated_assignments(ast: &Ast) -> BTreeSet<ExpressionId> {
+    let mut result = BTreeSet::new();
+
+    for (signature, body) in &ast.definitions {
+        let mut checker = InitializationChecker::new(ast, signature);
+
+        checker.visit(*body, &mut Some(Flow::default()));
+
+        result.extend(checker.repeated);
+    }
+
+    result
+}
+
+struct InitializationChecker<'a> {
+    ast: &'a Ast,
+    signature: &'a DefinitionSignature,
+    /// Variables declared in the body, parameters always have a value.
+    declared: BTreeSet<VariableId>,
+    read: BTreeSet<VariableId>,
+    /// Variables already reported, only their first bad read is.
+    reported: BTreeSet<VariableId>,
+    /// The `=` assignments of whole variables, in the order they're visited.
+    writes: Vec<(VariableId, ExpressionId)>,
+    repeated: BTreeSet<ExpressionId>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> InitializationChecker<'a> {
+    fn new(ast: &'a Ast, signature: &'a DefinitionSignature) -> Self {
+        Self {
+            ast,
+            signature,
+            declared: BTreeSet::new(),
+            read: BTreeSet::new(),
+            reported: BTreeSet::new(),
+            writes: vec![],
+            repeated: BTreeSet::new(),
+            diagnostics: vec![],
+        }
+    }
+
+    fn visit(&mut self, id: ExpressionId, assigned: &mut Assigned) {
+        match self.ast.find_expression(id) {
+            Some(Expression::NewVariable(variable)) => {
+                let initializer = self
+                    .ast
+                    .variables
+                    .get(self.signature)
+                    .and_then(|variables| variables.get(variable))
+                    .and_then(|variable| variable.initializer);
+
+                if let Some(initializer) = initializer {
+                    self.visit(initializer, assigned);
+                    Self::assign(assigned, *variable);
+                }
+
+                self.declared.insert(*variable);
+            }
+            Some(Expression::Variable(variable)) => self.check_read(id, *variable, assigned),
+            Some(Expression::Assignment(assignment)) => {
+                self.visit(assignment.value, assigned);
+
+                match self.ast.find_expression(assignment.place) {
+                    Some(Expression::Variable(variable)) if assignment.op.is_none() => {
+                        if assigned
+                            .as_ref()
+                            .is_some_and(|flow| flow.some.contains(variable))
+                        {
+                            self.repeated.insert(id);
+                        }
+
+                        self.writes.push((*variable, id));
+                        Self::assign(assigned, *variable);
+                    }
+                    _ => self.visit(assignment.place, assigned),
+                }
+            }
+            Some(Expression::If(if_expression)) => {
+                let mut paths = vec![];
+
+                for (condition, body) in &if_expression.branches {
+                    self.visit(*condition, assigned);
+
+                    let mut path = assigned.clone();
+                    self.visit(*body, &mut path);
+                    paths.push(path);
+                }
+
+                if let Some(otherwise) = if_expression.otherwise {
+                    self.visit(otherwise, assigned);
+                }
+
+                paths.push(assigned.take());
+                *assigned = Self::merge(paths);
+            }
+            Some(Expression::Match(match_expression)) => {
+                self.visit(match_expression.value, assigned);
+
+                let mut paths = vec![];
+
+                for arm in &match_expression.arms {
+                    let mut path = assigned.clone();
+
+                    if let Pattern::Variant { bindings, .. } = &arm.pattern {
+                        for binding in bindings.iter().flatten() {
+                            self.declared.insert(*binding);
+                            Self::assign(&mut path, *binding);
+                        }
+                    }
+
+                    self.visit(arm.body, &mut path);
+                    paths.push(path);
+                }
+
+                *assigned = Self::merge(paths);
+            }
+            Some(Expression::Return(value)) => {
+                if let Some(value) = value {
+                    self.visit(*value, assigned);
+                }
+
+                *assigned = None;
+            }
+            Some(Expression::Loop(loop_expression)) => {
+                if let Some(condition) = loop_expression.condition {
+                    self.visit(condition, assigned);
+                }
+
+                self.visit_loop_body(loop_expression.body, assigned);
+
+                // Without a condition, only a `return` leaves the loop.
+                if loop_expression.condition.is_none() {
+                    *assigned = None;
+                }
+            }
+            Some(Expression::For(for_expression)) => {
+                self.visit(for_expression.container, assigned);
+
+                self.declared.insert(for_expression.element);
+                Self::assign(assigned, for_expression.element);
+
+                self.visit_loop_body(for_expression.body, assigned);
+            }
+            _ => {
+                for child in self.ast.children(id) {
+                    self.visit(child, assigned);
+                }
+            }
+        }
+    }
+
+    /// Visits a loop body, which may not run at all, so what it assigns is only maybe
+    /// assigned after it. What it assigns of the variables declared before it is assigned
+    /// again when the loop runs again.
+    fn visit_loop_body(&mut self, body: ExpressionId, assigned: &mut Assigned) {
+        let outer = self.declared.clone();
+        let start = self.writes.len();
+
+        let mut path = assigned.clone();
+        self.visit(body, &mut path);
+
+        let (Some(after), Some(path)) = (assigned, path) else {
+            return;
+        };
+
+        for (variable, id) in &self.writes[start..] {
+            if outer.contains(variable) && path.some.contains(variable) {
+                self.repeated.insert(*id);
+            }
+        }
+
+        after.some.extend(path.some);
+    }
+
+    fn assign(assigned: &mut Assigned, variable: VariableId) {
+        if let Some(flow) = assigned {
+            flow.every.insert(variable);
+            flow.some.insert(variable);
+        }
+    }
+
+    /// What's assigned on the paths that reach the end of a branch.
+    fn merge(paths: Vec<Assigned>) -> Assigned {
+        paths.into_iter().flatten().reduce(|left, right| Flow {
+            every: left.every.intersection(&right.every).copied().collect(),
+            some: left.some.union(&right.some).copied().collect(),
+        })
+    }
+
+    fn check_read(&mut self, id: ExpressionId, variable: VariableId, assigned: &Assigned) {
+        self.read.insert(variable);
+
+        let Some(flow) = assigned else {
+            return;
+        };
+
+        if !self.declared.contains(&variable)
+            || flow.every.contains(&variable)
+            || !self.reported.insert(variable)
+        {
+            return;
+        }
+
+        let name = &self.ast.variables[self.signature][&variable].name;
+
+        self.diagnostics.push(
+            Diagnostic::error(format!("`{}` may be read before it's assigned", name))
+                .at(self.ast.find_location(id))
+                .with_note(format!(
+                    "give `{}` a value when declaring it, or assign it on every path before this",
+                    name
+                )),
+        );
+    }
+
+    fn check_unread(&mut self) {
+        for variable in self.declared.difference(&self.read) {
+            let name = &self.ast.variables[self.signature][variable].name;
+
+            self.diagnostics.push(
+                Diagnostic::warning(format!("`{}` is never read", name))
+                    .at(self.ast.find_variable_location(*variable)),
+            );
+        }
+    }
+}
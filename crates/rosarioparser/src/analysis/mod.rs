@@ -0,0 +1,17 @@
+pub mod initialization;
+pub mod monomorphize;
+pub mod moves;
+pub mod mutability;
+pub mod ownership;
+pub mod resolve;
+pub mod trust;
+pub mod typecheck;
+
+pub use initialization::check_initialization;
+pub use monomorphize::monomorphize;
+pub use moves::check_moves;
+pub use mutability::check_mutability;
+pub use ownership::check_ownership;
+pub use resolve::resolve;
+pub use trust::check_trust;
+pub use typecheck::typecheck;
@@ -0,0 +1,1319 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    analysis::typecheck::{self, Type},
+    diagnostic::{Diagnostic, Location},
+    parser::ast::{
+        Assignment, Associated, Ast, BinOp, Body, Call, Cast, Conversion, DefinitionOwner,
+        DefinitionSignature, Expression, ExpressionId, Field, For, Generic, If, Index, Loop, Match,
+        MatchArm, MethodCall, Pattern, RosarioType, TypeBody, TypeExpression, TypeReference,
+        TypeSignature, UnOp, VariableId, VariableType,
+    },
+};
+
+/// Longest chain of nested instantiations before a generic is reported as unbounded.
+pub const MAX_INSTANTIATION_DEPTH: usize = 64;
+
+/// Replaces every generic type and definition of `ast` by its concrete instances.
+///
+/// Instances are named after the generic and the paths of its arguments (`Option<Int32>`
+/// becomes `Option<core::basic_types::Int32>`), so the returned `Ast` never contains a
+/// `Generic` outside of bounds.
+pub fn monomorphize(ast: &Ast) -> Result<Ast, Vec<Diagnostic>> {
+    let mut monomorphizer = Monomorphizer::new(ast);
+
+    monomorphizer.start();
+
+    if monomorphizer.diagnostics.is_empty() {
+        Ok(monomorphizer.result)
+    } else {
+        Err(monomorphizer.diagnostics)
+    }
+}
+
+/// Name given to the instance of `generic`, e.g. `Option<main::Option<main::Int32>>`.
+///
+/// Arguments are written with the path of their module and `<`, `,` and `::` can't be
+/// part of a name, so two instances never share a name, nor share one with a type or
+/// definition written in Rosario.
+pub fn mangle(generic: &Generic) -> String {
+    if generic.generics.is_empty() {
+        return generic.name.clone();
+    }
+
+    let arguments = generic
+        .generics
+        .iter()
+        .map(|argument| match argument.owner.is_empty() {
+            true => mangle(argument),
+            false => format!("{}::{}", argument.owner, mangle(argument)),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!("{}<{}>", generic.name, arguments)
+}
+
+/// How the value of `id` is borrowed, `None` when it's owned.
+///
+/// Reference variables, calls returning references and the fields and elements of a
+/// borrowed value are borrowed, elements behind a raw pointer aren't owned by anyone the
+/// compiler knows of.
+pub fn borrowed(
+    ast: &Ast,
+    signature: &DefinitionSignature,
+    id: ExpressionId,
+) -> Option<VariableType> {
+    let returned = |definition: Option<&DefinitionSignature>| {
+        definition
+            .map(|definition| definition.return_variable_type.clone())
+            .filter(VariableType::is_reference)
+    };
+
+    match ast.find_expression(id)? {
+        Expression::Variable(variable) => {
+            let variable = ast.variables.get(signature)?.get(variable)?;
+            Some(variable.variable_type.clone()).filter(VariableType::is_reference)
+        }
+        expression @ (Expression::Call(_) | Expression::Associated(_)) => {
+            returned(ast.find_called(expression))
+        }
+        Expression::MethodCall(call) => match typecheck::infer(ast, signature, call.receiver) {
+            Type::Named(ty) => returned(ast.find_method(&ty, &call.name)),
+            _ => None,
+        },
+        Expression::Field(field) => borrowed(ast, signature, field.base),
+        Expression::Index(index) => match typecheck::infer(ast, signature, index.base) {
+            Type::Pointer(_) => None,
+            _ => borrowed(ast, signature, index.base),
+        },
+        _ => None,
+    }
+}
+
+type Substitutions = BTreeMap<String, Generic>;
+
+struct Monomorphizer<'a> {
+    source: &'a Ast,
+    result: Ast,
+    diagnostics: Vec<Diagnostic>,
+    reported: BTreeSet<String>,
+    /// The instance of each generic type, by its qualified arguments.
+    instances: BTreeMap<Generic, TypeSignature>,
+    /// The generic definitions already copied, with their owner and arguments.
+    copied: BTreeSet<(DefinitionSignature, DefinitionOwner, Vec<Generic>)>,
+    /// How deep each instance of a method with generics of its own was instantiated.
+    method_depths: BTreeMap<(DefinitionOwner, String), usize>,
+    /// Where the type or call being made concrete is written. The instances it needs
+    /// report their errors there rather than inside the generic code.
+    site: Option<Location>,
+}
+
+impl<'a> Monomorphizer<'a> {
+    fn new(source: &'a Ast) -> Self {
+        let mut result = source.clone();
+
+        result.types.retain(|_, ty| ty.generics.is_empty());
+        result
+            .definitions
+            .retain(|signature, _| signature.generics.is_empty());
+        result
+            .variables
+            .retain(|signature, _| signature.generics.is_empty());
+
+        Self {
+            source,
+            result,
+            diagnostics: vec![],
+            reported: BTreeSet::new(),
+            instances: BTreeMap::new(),
+            copied: BTreeSet::new(),
+            method_depths: BTreeMap::new(),
+            site: None,
+        }
+    }
+
+    fn start(&mut self) {
+        for (signature, ty) in &self.source.types {
+            if !ty.generics.is_empty() {
+                continue;
+            }
+
+            let body = self.substitute_body(&ty.ty, &Substitutions::new(), 0);
+            self.result.types.get_mut(signature).unwrap().ty = body;
+        }
+
+        for (signature, variables) in &self.source.variables {
+            if !signature.generics.is_empty() {
+                continue;
+            }
+
+            for (id, variable) in variables {
+                self.site = self.source.find_variable_location(*id);
+
+                let concrete = self.concrete_expression(&variable.ty, &Substitutions::new(), 0);
+
+                let variable = self
+                    .result
+                    .variables
+                    .get_mut(signature)
+                    .unwrap()
+                    .get_mut(id)
+                    .unwrap();
+
+                variable.ty = concrete;
+            }
+        }
+
+        for (signature, body) in &self.source.definitions {
+            if signature.generics.is_empty() {
+                self.rewrite_calls(*body);
+            }
+        }
+
+        for signature in self.source.definitions.keys() {
+            if signature.generics.is_empty() {
+                self.concrete_return_type(signature);
+            }
+        }
+
+        self.instantiate_method_calls();
+        self.mutable_operators();
+        self.bind_references();
+    }
+
+    /// Makes `value?` call the `mutable operator "?"` of its type where what it gives is
+    /// changed: in the place of an assignment, or as the receiver of a `mutable self`
+    /// method. Like the bindings of a `match`, this depends on the concrete types.
+    fn mutable_operators(&mut self) {
+        let definitions = self
+            .result
+            .definitions
+            .iter()
+            .map(|(signature, body)| (signature.clone(), *body))
+            .collect::<Vec<_>>();
+
+        for (signature, body) in definitions {
+            self.mutable_operators_in(&signature, body);
+        }
+    }
+
+    /// Outer places come first, so a changed `value??` changes both of its operators.
+    fn mutable_operators_in(&mut self, signature: &DefinitionSignature, id: ExpressionId) {
+        let changed = match self.result.find_expression(id) {
+            Some(Expression::Assignment(assignment)) => Some(assignment.place),
+            Some(Expression::MethodCall(call)) => {
+                let method = match typecheck::infer(&self.result, signature, call.receiver) {
+                    Type::Named(ty) => self.result.find_method(&ty, &call.name),
+                    _ => None,
+                };
+
+                method
+                    .and_then(|method| method.args.first())
+                    .and_then(|receiver| self.result.find_variable_by_id(*receiver))
+                    .filter(|receiver| receiver.variable_type == VariableType::MutableReference)
+                    .map(|_| call.receiver)
+            }
+            _ => None,
+        };
+
+        if let Some(place) = changed {
+            self.mutable_operator(signature, place);
+        }
+
+        for child in self.result.children(id) {
+            self.mutable_operators_in(signature, child);
+        }
+    }
+
+    /// Switches the `?` a changed place is reached through to the `mutable` one, when its
+    /// type has it.
+    fn mutable_operator(&mut self, signature: &DefinitionSignature, id: ExpressionId) {
+        match self.result.find_expression(id) {
+            Some(Expression::Field(field)) => self.mutable_operator(signature, field.base),
+            Some(Expression::Index(index)) => self.mutable_operator(signature, index.base),
+            Some(Expression::MethodCall(call)) if call.name == "?" => {
+                let Type::Named(ty) = typecheck::infer(&self.result, signature, call.receiver)
+                else {
+                    return;
+                };
+
+                if self.result.find_method(&ty, "mutable ?").is_none() {
+                    return;
+                }
+
+                if let Some(Expression::MethodCall(call)) = self.result.find_mut_expression(id) {
+                    call.name = "mutable ?".to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Makes the bindings of a `match` on a borrowed value references into it.
+    ///
+    /// The value belongs to someone else, so copies of its fields could be moved out and
+    /// dropped twice: matching a `&Option<String>` binds `Some(s)` as a `&String`, and as
+    /// a `mutable &String` when it's matched through a `mutable &`. Which values are
+    /// borrowed depends on the definitions called, so it's only known for concrete ones.
+    fn bind_references(&mut self) {
+        let definitions = self
+            .result
+            .definitions
+            .iter()
+            .map(|(signature, body)| (signature.clone(), *body))
+            .collect::<Vec<_>>();
+
+        for (signature, body) in definitions {
+            self.bind_references_in(&signature, body);
+        }
+    }
+
+    /// Outer matches come first, so the bindings of a match on a binding are known.
+    fn bind_references_in(&mut self, signature: &DefinitionSignature, id: ExpressionId) {
+        if let Some(Expression::Match(match_expression)) = self.result.find_expression(id)
+            && let Some(variable_type) = borrowed(&self.result, signature, match_expression.value)
+        {
+            let bindings = match_expression
+                .arms
+                .iter()
+                .flat_map(|arm| match &arm.pattern {
+                    Pattern::Variant { bindings, .. } => bindings.clone(),
+                    Pattern::Wildcard => vec![],
+                })
+                .flatten()
+                .collect::<Vec<_>>();
+
+            let variables = self.result.variables.entry(signature.clone()).or_default();
+
+            for binding in bindings {
+                if let Some(variable) = variables.get_mut(&binding) {
+                    variable.variable_type = variable_type.clone();
+                }
+            }
+        }
+
+        for child in self.result.children(id) {
+            self.bind_references_in(signature, child);
+        }
+    }
+
+    /// Gives a definition returning an instance of a generic type, like `Vector<Int32>`,
+    /// the concrete type as its return type.
+    fn concrete_return_type(&mut self, signature: &DefinitionSignature) {
+        let Some(ty) = &signature.return_type else {
+            return;
+        };
+
+        self.site = None;
+
+        let concrete = self.concrete_expression(ty, &Substitutions::new(), 0);
+
+        if concrete == *ty {
+            return;
+        }
+
+        let instance = DefinitionSignature {
+            return_type: Some(concrete),
+            ..signature.clone()
+        };
+
+        if let Some(body) = self.result.definitions.remove(signature) {
+            self.result.definitions.insert(instance.clone(), body);
+        }
+
+        if let Some(variables) = self.result.variables.remove(signature) {
+            self.result.variables.insert(instance, variables);
+        }
+    }
+
+    /// Finds a generic type by its resolved owner, or by name alone when unresolved.
+    fn find_generic_type(
+        &self,
+        owner: &str,
+        name: &str,
+    ) -> Option<(&'a TypeSignature, &'a RosarioType)> {
+        self.source.types.iter().find(|(signature, ty)| {
+            signature.name == name
+                && (owner.is_empty() || signature.owner == owner)
+                && !ty.generics.is_empty()
+        })
+    }
+
+    fn find_generic_definition(
+        &self,
+        call: &Call,
+    ) -> Option<(&'a DefinitionSignature, &'a ExpressionId)> {
+        self.source.definitions.iter().find(|(signature, _)| {
+            signature.name == call.name
+                && matches!(signature.owner, DefinitionOwner::Path(_))
+                && (call.owner.is_empty()
+                    || signature.owner == DefinitionOwner::Path(call.owner.clone()))
+                && !signature.generics.is_empty()
+        })
+    }
+
+    /// `generic` with the path of every type it names and nothing else than its names,
+    /// so equal instances compare equal.
+    fn qualify(&self, generic: &Generic) -> Generic {
+        let owner = match generic.owner.is_empty() {
+            true => self.source.find_type_signature_owner(generic.name.clone()),
+            false => generic.owner.clone(),
+        };
+
+        Generic {
+            owner,
+            name: generic.name.clone(),
+            generics: generic.generics.iter().map(|g| self.qualify(g)).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn substitute(generic: &Generic, substitutions: &Substitutions) -> Generic {
+        if generic.generics.is_empty()
+            && let Some(argument) = substitutions.get(&generic.name)
+        {
+            return Generic {
+                end: generic.end.clone(),
+                ..argument.clone()
+            };
+        }
+
+        Generic {
+            generics: generic
+                .generics
+                .iter()
+                .map(|g| Self::substitute(g, substitutions))
+                .collect(),
+            ..generic.clone()
+        }
+    }
+
+    /// Reports the arguments missing a trait their parameter of `name` is bounded by.
+    fn check_bounds(&mut self, name: &str, parameters: &[Generic], arguments: &[TypeSignature]) {
+        for (parameter, argument) in parameters.iter().zip(arguments) {
+            for bound in &parameter.bounds {
+                if self.satisfies(argument, bound) {
+                    continue;
+                }
+
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` doesn't implement `{}`", argument.name, bound))
+                        .at(self.site.clone())
+                        .with_note(format!(
+                            "`{}` needs it of its parameter `{}`",
+                            name, parameter.name
+                        )),
+                );
+            }
+        }
+    }
+
+    /// Whether `ty` implements the trait `bound`. `Copy` types are their own clones.
+    fn satisfies(&self, ty: &TypeSignature, bound: &str) -> bool {
+        self.result.implements(ty, bound) || (bound == "Clone" && self.result.is_copy(ty))
+    }
+
+    fn check_arity(
+        &mut self,
+        name: &str,
+        expected: usize,
+        found: usize,
+        location: Option<Location>,
+    ) -> bool {
+        if expected == found {
+            return true;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "`{}` expects {} generic argument(s), found {}",
+                name, expected, found
+            ))
+            .at(location),
+        );
+
+        false
+    }
+
+    fn check_depth(&mut self, generic: &Generic, depth: usize) -> bool {
+        if depth <= MAX_INSTANTIATION_DEPTH {
+            return true;
+        }
+
+        if self.reported.insert(generic.name.clone()) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "unbounded recursive instantiation of `{}`",
+                    generic.name
+                ))
+                .with_note(format!(
+                    "instantiation chain exceeds {} levels, last instance was `{}`",
+                    MAX_INSTANTIATION_DEPTH, generic
+                )),
+            );
+        }
+
+        false
+    }
+
+    /// Returns the signature of the concrete type for a fully substituted `generic`.
+    fn instantiate(&mut self, generic: &Generic, depth: usize) -> TypeSignature {
+        let Some((signature, ty)) = self.find_generic_type(&generic.owner, &generic.name) else {
+            if !generic.generics.is_empty() {
+                self.diagnostics.push(Diagnostic::error(format!(
+                    "`{}` is not a generic type",
+                    generic.name
+                )));
+            }
+
+            let owner = if generic.owner.is_empty() {
+                self.source.find_type_signature_owner(generic.name.clone())
+            } else {
+                generic.owner.clone()
+            };
+
+            return TypeSignature {
+                owner,
+                name: generic.name.clone(),
+            };
+        };
+
+        let generic = &self.qualify(generic);
+
+        if let Some(instance) = self.instances.get(generic) {
+            return instance.clone();
+        }
+
+        let instance = TypeSignature {
+            owner: signature.owner.clone(),
+            name: mangle(generic),
+        };
+
+        if !self.check_arity(
+            &generic.name,
+            ty.generics.len(),
+            generic.generics.len(),
+            None,
+        ) || !self.check_depth(generic, depth)
+        {
+            return instance;
+        }
+
+        // Inserted before the body so self-referencing instances stop here.
+        self.instances.insert(generic.clone(), instance.clone());
+        self.result
+            .types
+            .insert(instance.clone(), RosarioType::default());
+
+        let arguments = generic
+            .generics
+            .iter()
+            .map(|argument| self.instantiate(argument, depth))
+            .collect::<Vec<_>>();
+
+        self.check_bounds(&generic.name, &ty.generics, &arguments);
+
+        let substitutions = ty
+            .generics
+            .iter()
+            .map(|parameter| parameter.name.clone())
+            .zip(generic.generics.iter().cloned())
+            .collect::<Substitutions>();
+
+        let body = self.substitute_body(&ty.ty, &substitutions, depth + 1);
+
+        self.result.types.insert(
+            instance.clone(),
+            RosarioType {
+                generics: vec![],
+                ty: body,
+            },
+        );
+
+        if let Some(traits) = self.source.implementations.get(signature) {
+            self.result
+                .implementations
+                .insert(instance.clone(), traits.clone());
+        }
+
+        self.instantiate_methods(signature, &instance, generic, depth + 1);
+
+        instance
+    }
+
+    /// Instantiates the definitions implemented for a generic type along with its instance.
+    ///
+    /// Their first generics are the ones of the implementation. Methods with generics of
+    /// their own are left out, each of their calls gets an instance of them instead. An
+    /// implementation can bound its generics, like `implement Clone for Vector<T: Clone>`,
+    /// and only the instances whose arguments meet the bounds get its definitions and
+    /// implement its trait.
+    fn instantiate_methods(
+        &mut self,
+        generic_type: &TypeSignature,
+        instance: &TypeSignature,
+        generic: &Generic,
+        depth: usize,
+    ) {
+        let arguments = generic
+            .generics
+            .iter()
+            .map(|argument| self.instantiate(argument, depth))
+            .collect::<Vec<_>>();
+
+        let owner = DefinitionOwner::Type(generic_type.clone());
+        let (methods, unmet): (Vec<_>, Vec<_>) = self
+            .source
+            .definitions
+            .iter()
+            .filter(|(signature, _)| {
+                signature.owner == owner && signature.generics.len() == generic.generics.len()
+            })
+            .partition(|(signature, _)| {
+                signature
+                    .generics
+                    .iter()
+                    .zip(&arguments)
+                    .all(|(parameter, argument)| {
+                        parameter
+                            .bounds
+                            .iter()
+                            .all(|bound| self.satisfies(argument, bound))
+                    })
+            });
+
+        // A trait is only implemented with all of its definitions.
+        if let Some(traits) = self.result.implementations.get_mut(instance) {
+            traits.retain(|name| {
+                let suffix = format!("::{}", name);
+                let mut names = self
+                    .source
+                    .traits
+                    .iter()
+                    .filter(|(path, _)| path.ends_with(&suffix))
+                    .flat_map(|(_, names)| names);
+
+                !names.any(|name| unmet.iter().any(|(signature, _)| signature.name == *name))
+            });
+        }
+
+        for (signature, body) in methods {
+            let substitutions = signature
+                .generics
+                .iter()
+                .map(|parameter| parameter.name.clone())
+                .zip(generic.generics.iter().cloned())
+                .collect::<Substitutions>();
+
+            let target = DefinitionSignature {
+                owner: DefinitionOwner::Type(instance.clone()),
+                ..signature.clone()
+            };
+
+            self.copy_definition(signature, *body, target, &substitutions, depth);
+        }
+    }
+
+    /// Points every call of a method with generics of its own at its instance.
+    ///
+    /// Which method is called depends on the type of the receiver, so this runs once every
+    /// type is concrete, and again on the instances it adds, until no call is left.
+    fn instantiate_method_calls(&mut self) {
+        let names = self
+            .source
+            .definitions
+            .keys()
+            .filter(|signature| self.method_generics(signature) > 0)
+            .map(|signature| signature.name.clone())
+            .collect::<BTreeSet<_>>();
+
+        let mut done = BTreeSet::new();
+
+        loop {
+            let mut calls = vec![];
+
+            for (signature, body) in &self.result.definitions {
+                self.method_calls(signature, *body, &names, &mut calls);
+            }
+
+            calls.retain(|(_, id)| !done.contains(id));
+
+            let mut progress = false;
+
+            for (signature, id) in calls {
+                if self.instantiate_method_call(&signature, id) {
+                    done.insert(id);
+                    progress = true;
+                }
+            }
+
+            if !progress {
+                break;
+            }
+        }
+    }
+
+    /// How many generics `signature` has after the ones of the type it's implemented for.
+    fn method_generics(&self, signature: &DefinitionSignature) -> usize {
+        let DefinitionOwner::Type(ty) = &signature.owner else {
+            return 0;
+        };
+
+        let owner = self.source.types.get(ty).map_or(0, |ty| ty.generics.len());
+
+        signature.generics.len().saturating_sub(owner)
+    }
+
+    /// Collects the method calls of the body `id` of `signature` that may need an instance,
+    /// the ones giving generics and the ones named like a method with generics.
+    fn method_calls(
+        &self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+        names: &BTreeSet<String>,
+        calls: &mut Vec<(DefinitionSignature, ExpressionId)>,
+    ) {
+        let children = match self.result.find_expression(id) {
+            Some(Expression::MethodCall(MethodCall { name, generics, .. }))
+            | Some(Expression::Associated(Associated { name, generics, .. }))
+                if !generics.is_empty() || names.contains(name) =>
+            {
+                calls.push((signature.clone(), id));
+                self.result.children(id)
+            }
+            // Instances share the ids of their variables, so they're found by definition.
+            Some(Expression::NewVariable(variable)) => self
+                .result
+                .variables
+                .get(signature)
+                .and_then(|variables| variables.get(variable))
+                .and_then(|variable| variable.initializer)
+                .into_iter()
+                .collect(),
+            _ => self.result.children(id),
+        };
+
+        for child in children {
+            self.method_calls(signature, child, names, calls);
+        }
+    }
+
+    /// Instantiates the method called by `id`, inside `signature`, and renames the call after
+    /// the instance. Returns whether the method is known, which needs the receiver's type.
+    fn instantiate_method_call(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+    ) -> bool {
+        let (ty, name, generics) = match self.result.find_expression(id) {
+            Some(Expression::MethodCall(call)) => {
+                match typecheck::infer(&self.result, signature, call.receiver) {
+                    Type::Named(ty) => (ty, call.name.clone(), call.generics.clone()),
+                    _ => return false,
+                }
+            }
+            Some(Expression::Associated(associated)) => (
+                associated.ty.ty.clone(),
+                associated.name.clone(),
+                associated.generics.clone(),
+            ),
+            _ => return false,
+        };
+
+        // The generic type and arguments `ty` is an instance of, if it is one.
+        let (generic_type, arguments) = match self
+            .instances
+            .iter()
+            .find(|(_, instance)| **instance == ty)
+            .and_then(|(generic, _)| {
+                let (signature, _) = self.find_generic_type(&generic.owner, &generic.name)?;
+                Some((signature.clone(), generic.generics.clone()))
+            }) {
+            Some(found) => found,
+            None => (ty.clone(), vec![]),
+        };
+
+        let owner = DefinitionOwner::Type(generic_type);
+
+        let Some((method, body)) = self
+            .source
+            .definitions
+            .iter()
+            .find(|(method, _)| method.owner == owner && method.name == name)
+        else {
+            // Not a method, or an unknown one the type checker reports.
+            return true;
+        };
+
+        let expected = method.generics.len().saturating_sub(arguments.len());
+        let location = self.result.find_location(id);
+
+        self.site = location.clone();
+
+        if (expected == 0 && generics.is_empty())
+            || !self.check_arity(&name, expected, generics.len(), location)
+        {
+            return true;
+        }
+
+        let generic = Generic {
+            name: name.clone(),
+            generics: generics.iter().map(|g| self.qualify(g)).collect(),
+            ..Default::default()
+        };
+
+        let depth = self
+            .method_depths
+            .get(&(signature.owner.clone(), signature.name.clone()))
+            .map_or(0, |depth| depth + 1);
+
+        if !self.check_depth(&generic, depth) {
+            return true;
+        }
+
+        for argument in &generic.generics {
+            self.instantiate(argument, depth);
+        }
+
+        let substitutions = method
+            .generics
+            .iter()
+            .map(|parameter| parameter.name.clone())
+            .zip(
+                arguments
+                    .into_iter()
+                    .chain(generic.generics.iter().cloned()),
+            )
+            .collect::<Substitutions>();
+
+        let target = DefinitionSignature {
+            owner: DefinitionOwner::Type(ty),
+            name: mangle(&generic),
+            ..method.clone()
+        };
+
+        self.method_depths
+            .insert((target.owner.clone(), target.name.clone()), depth);
+        self.copy_definition(method, *body, target, &substitutions, depth);
+
+        match self.result.find_mut_expression(id) {
+            Some(Expression::MethodCall(call)) => {
+                call.name = mangle(&generic);
+                call.generics = vec![];
+            }
+            Some(Expression::Associated(associated)) => {
+                associated.name = mangle(&generic);
+                associated.generics = vec![];
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    fn concrete_reference(
+        &mut self,
+        reference: &TypeReference,
+        substitutions: &Substitutions,
+        depth: usize,
+    ) -> TypeReference {
+        if reference.generics.is_empty() && !substitutions.contains_key(&reference.ty.name) {
+            if let Some((_, ty)) = self.find_generic_type(&reference.ty.owner, &reference.ty.name) {
+                self.check_arity(&reference.ty.name, ty.generics.len(), 0, None);
+            }
+
+            return reference.clone();
+        }
+
+        let generic = Self::substitute(
+            &Generic {
+                owner: reference.ty.owner.clone(),
+                name: reference.ty.name.clone(),
+                generics: reference.generics.clone(),
+                ..Default::default()
+            },
+            substitutions,
+        );
+
+        TypeReference {
+            ty: self.instantiate(&generic, depth),
+            generics: vec![],
+        }
+    }
+
+    /// Instantiates every named type of `expression`.
+    fn concrete_expression(
+        &mut self,
+        expression: &TypeExpression,
+        substitutions: &Substitutions,
+        depth: usize,
+    ) -> TypeExpression {
+        let mut result = expression.clone();
+
+        for reference in result.references_mut() {
+            *reference = self.concrete_reference(reference, substitutions, depth);
+        }
+
+        result
+    }
+
+    fn substitute_body(
+        &mut self,
+        body: &TypeBody,
+        substitutions: &Substitutions,
+        depth: usize,
+    ) -> TypeBody {
+        match body {
+            TypeBody::Enum(variants) => {
+                let mut result = variants.clone();
+
+                for variant in &mut result {
+                    for field in &mut variant.fields {
+                        *field = self.concrete_expression(field, substitutions, depth);
+                    }
+                }
+
+                TypeBody::Enum(result)
+            }
+            _ => body.clone(),
+        }
+    }
+
+    /// Points every generic call of a concrete definition body at its instance.
+    fn rewrite_calls(&mut self, id: ExpressionId) {
+        let Some(expression) = self.source.find_expression(id) else {
+            return;
+        };
+
+        match expression {
+            Expression::UnaryOperation(un_op) => self.rewrite_calls(un_op.operand),
+            Expression::Conversion(conversion) => self.rewrite_calls(conversion.value),
+            Expression::MethodCall(_)
+            | Expression::Foreign(_)
+            | Expression::Field(_)
+            | Expression::Index(_)
+            | Expression::Array(_)
+            | Expression::Assignment(_)
+            | Expression::If(_)
+            | Expression::Match(_)
+            | Expression::Loop(_)
+            | Expression::For(_) => {
+                for child in self.source.children(id) {
+                    self.rewrite_calls(child);
+                }
+            }
+            Expression::BinaryOperation(bin_op) => {
+                self.rewrite_calls(bin_op.left);
+                self.rewrite_calls(bin_op.right);
+            }
+            Expression::Return(value) => {
+                if let Some(value) = value {
+                    self.rewrite_calls(*value);
+                }
+            }
+            Expression::Body(body) => {
+                for content in &body.contents {
+                    self.rewrite_calls(*content);
+                }
+            }
+            Expression::NewVariable(variable) => {
+                let initializer = self
+                    .source
+                    .variables
+                    .values()
+                    .find_map(|variables| variables.get(variable))
+                    .and_then(|variable| variable.initializer);
+
+                if let Some(initializer) = initializer {
+                    self.rewrite_calls(initializer);
+                }
+            }
+            Expression::Call(call) => {
+                for arg in &call.args {
+                    self.rewrite_calls(*arg);
+                }
+
+                if !call.generics.is_empty() {
+                    self.site = self.source.find_location(id);
+
+                    let name = self.instantiate_definition(call, &Substitutions::new(), 0);
+
+                    if let Some(Expression::Call(call)) = self.result.find_mut_expression(id) {
+                        call.name = name;
+                        call.generics = vec![];
+                    }
+                }
+            }
+            Expression::Associated(associated) => {
+                for arg in &associated.args {
+                    self.rewrite_calls(*arg);
+                }
+
+                self.site = self.source.find_location(id);
+
+                let ty = self.concrete_reference(&associated.ty, &Substitutions::new(), 0);
+
+                if let Some(Expression::Associated(associated)) =
+                    self.result.find_mut_expression(id)
+                {
+                    associated.ty = ty;
+                }
+            }
+            Expression::Cast(cast) => {
+                self.rewrite_calls(cast.value);
+
+                self.site = self.source.find_location(id);
+
+                let ty = self.concrete_expression(&cast.ty, &Substitutions::new(), 0);
+
+                if let Some(Expression::Cast(cast)) = self.result.find_mut_expression(id) {
+                    cast.ty = ty;
+                }
+            }
+            Expression::SizeOf(ty) => {
+                self.site = self.source.find_location(id);
+
+                let ty = self.concrete_expression(ty, &Substitutions::new(), 0);
+
+                if let Some(Expression::SizeOf(size)) = self.result.find_mut_expression(id) {
+                    *size = ty;
+                }
+            }
+            Expression::None
+            | Expression::Number(_)
+            | Expression::String(_)
+            | Expression::Variable(_) => {}
+        }
+    }
+
+    /// Returns the name of the concrete definition called by `call`.
+    fn instantiate_definition(
+        &mut self,
+        call: &Call,
+        substitutions: &Substitutions,
+        depth: usize,
+    ) -> String {
+        let generic = Generic {
+            name: call.name.clone(),
+            generics: call
+                .generics
+                .iter()
+                .map(|g| self.qualify(&Self::substitute(g, substitutions)))
+                .collect(),
+            ..Default::default()
+        };
+
+        let Some((signature, body)) = self.find_generic_definition(call) else {
+            self.diagnostics.push(Diagnostic::error(format!(
+                "`{}` is not a generic definition",
+                call.name
+            )));
+
+            return call.name.clone();
+        };
+
+        let name = mangle(&generic);
+
+        if !self.check_arity(
+            &call.name,
+            signature.generics.len(),
+            generic.generics.len(),
+            None,
+        ) || !self.check_depth(&generic, depth)
+        {
+            return name;
+        }
+
+        let arguments = generic
+            .generics
+            .iter()
+            .map(|argument| self.instantiate(argument, depth))
+            .collect::<Vec<_>>();
+
+        self.check_bounds(&call.name, &signature.generics, &arguments);
+
+        let substitutions = signature
+            .generics
+            .iter()
+            .map(|parameter| parameter.name.clone())
+            .zip(generic.generics.iter().cloned())
+            .collect::<Substitutions>();
+
+        let target = DefinitionSignature {
+            name: name.clone(),
+            ..signature.clone()
+        };
+
+        self.copy_definition(signature, *body, target, &substitutions, depth + 1);
+
+        name
+    }
+
+    /// Inserts `target`, with concrete types, as an instance of the generic `signature`.
+    fn copy_definition(
+        &mut self,
+        signature: &DefinitionSignature,
+        body: ExpressionId,
+        target: DefinitionSignature,
+        substitutions: &Substitutions,
+        depth: usize,
+    ) {
+        let return_type = signature
+            .return_type
+            .as_ref()
+            .map(|ty| self.concrete_expression(ty, substitutions, depth));
+
+        let instance = DefinitionSignature {
+            generics: vec![],
+            return_type,
+            ..target
+        };
+
+        let copy = (
+            signature.clone(),
+            instance.owner.clone(),
+            substitutions.values().cloned().collect(),
+        );
+
+        if !self.copied.insert(copy) {
+            return;
+        }
+
+        self.result
+            .definitions
+            .insert(instance.clone(), ExpressionId::default());
+
+        let context = Instance {
+            source: signature,
+            target: &instance,
+            substitutions,
+            depth,
+        };
+
+        for argument in &signature.args {
+            self.copy_variable(*argument, &context);
+        }
+
+        let body = self.copy_expression(body, &context);
+
+        self.result.definitions.insert(instance, body);
+    }
+
+    /// Copies the body of a generic definition into one of its instances.
+    fn copy_expression(&mut self, id: ExpressionId, instance: &Instance) -> ExpressionId {
+        let Some(expression) = self.source.find_expression(id) else {
+            return id;
+        };
+
+        let expression = match expression {
+            Expression::UnaryOperation(un_op) => Expression::UnaryOperation(UnOp {
+                op: un_op.op.clone(),
+                operand: self.copy_expression(un_op.operand, instance),
+            }),
+            Expression::Return(value) => {
+                Expression::Return(value.map(|value| self.copy_expression(value, instance)))
+            }
+            Expression::MethodCall(call) => Expression::MethodCall(MethodCall {
+                receiver: self.copy_expression(call.receiver, instance),
+                name: call.name.clone(),
+                generics: call
+                    .generics
+                    .iter()
+                    .map(|g| Self::substitute(g, instance.substitutions))
+                    .collect(),
+                args: call
+                    .args
+                    .iter()
+                    .map(|arg| self.copy_expression(*arg, instance))
+                    .collect(),
+            }),
+            Expression::Field(field) => Expression::Field(Field {
+                base: self.copy_expression(field.base, instance),
+                name: field.name.clone(),
+            }),
+            Expression::Index(index) => Expression::Index(Index {
+                base: self.copy_expression(index.base, instance),
+                index: self.copy_expression(index.index, instance),
+            }),
+            Expression::Array(elements) => Expression::Array(
+                elements
+                    .iter()
+                    .map(|element| self.copy_expression(*element, instance))
+                    .collect(),
+            ),
+            Expression::Assignment(assignment) => Expression::Assignment(Assignment {
+                place: self.copy_expression(assignment.place, instance),
+                op: assignment.op.clone(),
+                value: self.copy_expression(assignment.value, instance),
+            }),
+            Expression::If(if_expression) => Expression::If(If {
+                branches: if_expression
+                    .branches
+                    .iter()
+                    .map(|(condition, body)| {
+                        (
+                            self.copy_expression(*condition, instance),
+                            self.copy_expression(*body, instance),
+                        )
+                    })
+                    .collect(),
+                otherwise: if_expression
+                    .otherwise
+                    .map(|otherwise| self.copy_expression(otherwise, instance)),
+            }),
+            Expression::Match(match_expression) => {
+                for arm in &match_expression.arms {
+                    if let Pattern::Variant { bindings, .. } = &arm.pattern {
+                        for binding in bindings.iter().flatten() {
+                            self.copy_variable(*binding, instance);
+                        }
+                    }
+                }
+
+                Expression::Match(Match {
+                    value: self.copy_expression(match_expression.value, instance),
+                    arms: match_expression
+                        .arms
+                        .iter()
+                        .map(|arm| MatchArm {
+                            pattern: arm.pattern.clone(),
+                            body: self.copy_expression(arm.body, instance),
+                        })
+                        .collect(),
+                })
+            }
+            Expression::Conversion(conversion) => {
+                let reference = TypeReference {
+                    ty: conversion.ty.clone(),
+                    generics: vec![],
+                };
+
+                Expression::Conversion(Conversion {
+                    value: self.copy_expression(conversion.value, instance),
+                    ty: self
+                        .concrete_reference(&reference, instance.substitutions, instance.depth)
+                        .ty,
+                    balanced: conversion.balanced,
+                })
+            }
+            Expression::BinaryOperation(bin_op) => Expression::BinaryOperation(BinOp {
+                op: bin_op.op.clone(),
+                left: self.copy_expression(bin_op.left, instance),
+                right: self.copy_expression(bin_op.right, instance),
+            }),
+            Expression::Body(body) => Expression::Body(Body {
+                contents: body
+                    .contents
+                    .iter()
+                    .map(|content| self.copy_expression(*content, instance))
+                    .collect(),
+            }),
+            Expression::Call(call) => {
+                let args = call
+                    .args
+                    .iter()
+                    .map(|arg| self.copy_expression(*arg, instance))
+                    .collect();
+
+                let name = if call.generics.is_empty() {
+                    call.name.clone()
+                } else {
+                    self.instantiate_definition(call, instance.substitutions, instance.depth)
+                };
+
+                Expression::Call(Call {
+                    owner: call.owner.clone(),
+                    name,
+                    generics: vec![],
+                    args,
+                })
+            }
+            Expression::Foreign(call) => Expression::Foreign(Call {
+                args: call
+                    .args
+                    .iter()
+                    .map(|arg| self.copy_expression(*arg, instance))
+                    .collect(),
+                ..call.clone()
+            }),
+            Expression::Associated(associated) => Expression::Associated(Associated {
+                ty: self.concrete_reference(&associated.ty, instance.substitutions, instance.depth),
+                name: associated.name.clone(),
+                generics: associated
+                    .generics
+                    .iter()
+                    .map(|g| Self::substitute(g, instance.substitutions))
+                    .collect(),
+                args: associated
+                    .args
+                    .iter()
+                    .map(|arg| self.copy_expression(*arg, instance))
+                    .collect(),
+            }),
+            Expression::Cast(cast) => Expression::Cast(Cast {
+                value: self.copy_expression(cast.value, instance),
+                ty: self.concrete_expression(&cast.ty, instance.substitutions, instance.depth),
+            }),
+            Expression::SizeOf(ty) => Expression::SizeOf(self.concrete_expression(
+                ty,
+                instance.substitutions,
+                instance.depth,
+            )),
+            Expression::Loop(loop_expression) => Expression::Loop(Loop {
+                condition: loop_expression
+                    .condition
+                    .map(|condition| self.copy_expression(condition, instance)),
+                body: self.copy_expression(loop_expression.body, instance),
+            }),
+            Expression::For(for_expression) => {
+                self.copy_variable(for_expression.element, instance);
+
+                Expression::For(For {
+                    element: for_expression.element,
+                    container: self.copy_expression(for_expression.container, instance),
+                    body: self.copy_expression(for_expression.body, instance),
+                })
+            }
+            Expression::NewVariable(id) => {
+                self.copy_variable(*id, instance);
+
+                Expression::NewVariable(*id)
+            }
+            _ => expression.clone(),
+        };
+
+        let copy = self.result.new_expression(expression);
+
+        // Errors in an instance point at the generic code it comes from.
+        if let Some(location) = self.source.find_location(id) {
+            self.result.locations.insert(copy, location);
+        }
+
+        copy
+    }
+
+    /// Copies a variable of a generic definition, with concrete types, into its instance.
+    fn copy_variable(&mut self, id: VariableId, instance: &Instance) {
+        let mut variable = self.source.variables[instance.source][&id].clone();
+
+        variable.ty =
+            self.concrete_expression(&variable.ty, instance.substitutions, instance.depth);
+        variable.initializer = variable
+            .initializer
+            .map(|initializer| self.copy_expression(initializer, instance));
+
+        self.result
+            .variables
+            .entry(instance.target.clone())
+            .or_default()
+            .insert(id, variable);
+    }
+}
+
+struct Instance<'a> {
+    source: &'a DefinitionSignature,
+    target: &'a DefinitionSignature,
+    substitutions: &'a Substitutions,
+    depth: usize,
+}
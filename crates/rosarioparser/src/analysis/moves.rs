@@ -0,0 +1,556 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    analysis::typecheck::{self, Type},
+    diagnostic::{Diagnostic, Location},
+    parser::ast::{
+        Ast, DefinitionSignature, Expression, ExpressionId, Match, MatchArm, Pattern,
+        TypeSignature, VariableId,
+    },
+};
+
+/// Whether `arm` of a match on a `ty` takes fields out of the matched value, by binding a
+/// field whose type isn't `Copy` to a variable that isn't a reference.
+pub fn takes(
+    ast: &Ast,
+    signature: &DefinitionSignature,
+    ty: &TypeSignature,
+    arm: &MatchArm,
+) -> bool {
+    let Pattern::Variant { name, bindings } = &arm.pattern else {
+        return false;
+    };
+
+    let Some((_, variant)) = ast.find_variant(ty, name) else {
+        return false;
+    };
+
+    bindings
+        .iter()
+        .zip(&variant.fields)
+        .filter_map(|(binding, field)| Some((binding.as_ref()?, field.signature()?)))
+        .any(|(binding, field)| {
+            !ast.variables[signature][binding]
+                .variable_type
+                .is_reference()
+                && !ast.is_copy(field)
+        })
+}
+
+/// Where a variable was moved out.
+#[derive(Debug, Clone, Copy)]
+struct Move {
+    at: ExpressionId,
+    /// Whether it's moved on every path reaching a point, or only on some of them.
+    always: bool,
+}
+
+/// Variables moved out on the paths reaching a point, `None` once no path reaches it.
+type Moved = Option<BTreeMap<VariableId, Move>>;
+
+/// Rejects uses of variables after their value is moved out.
+///
+/// Values whose type isn't `Copy` are moved when they're passed to a parameter taken by
+/// value, assigned, or returned, `&` references only borrow them. A variable moved in some
+/// `if` branches or `match` arms only may not be used after them either, until it's
+/// assigned again. Loop bodies can't move variables declared outside of them, the next
+/// iteration would use them again.
+pub fn check_moves(ast: &Ast) -> Vec<Diagnostic> {
+    let mut diagnostics = check_implementations(ast);
+
+    for (signature, body) in &ast.definitions {
+        let mut checker = MoveChecker {
+            ast,
+            signature,
+            reported: BTreeSet::new(),
+            types: BTreeMap::new(),
+            diagnostics: vec![],
+        };
+
+        checker.check_definition(*body);
+
+        diagnostics.extend(checker.diagnostics);
+    }
+
+    diagnostics
+}
+
+/// Copies of a value implementing `Drop`, or holding one, would each drop it.
+fn check_implementations(ast: &Ast) -> Vec<Diagnostic> {
+    ast.implementations
+        .iter()
+        .filter(|(ty, traits)| {
+            traits.contains("Copy") && (traits.contains("Drop") || ast.needs_drop(ty))
+        })
+        .map(|(ty, traits)| {
+            let message = match traits.contains("Drop") || ast.find_drop(ty).is_some() {
+                true => format!("`{}` can't implement both `Copy` and `Drop`", ty.name),
+                false => format!(
+                    "`{}` can't implement `Copy`, its fields need dropping",
+                    ty.name
+                ),
+            };
+
+            Diagnostic::error(message)
+                .at(ast
+                    .item_locations
+                    .get(&format!("{}::{}", ty.owner, ty.name))
+                    .cloned())
+                .with_note("every copy would be dropped, freeing the same value again".to_string())
+        })
+        .collect()
+}
+
+struct MoveChecker<'a> {
+    ast: &'a Ast,
+    signature: &'a DefinitionSignature,
+    /// Variables already reported, only their first use after a move is.
+    reported: BTreeSet<VariableId>,
+    /// Types of the moved variables.
+    types: BTreeMap<VariableId, TypeSignature>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl MoveChecker<'_> {
+    fn check_definition(&mut self, body: ExpressionId) {
+        let mut moved = Some(BTreeMap::new());
+
+        match self.ast.find_expression(body) {
+            Some(Expression::Body(_) | Expression::If(_)) => self.visit(body, &mut moved),
+            Some(_) if self.signature.return_type.is_some() => self.returned(body, &mut moved),
+            Some(_) => self.visit(body, &mut moved),
+            None => {}
+        }
+    }
+
+    fn location(&self, id: ExpressionId) -> Option<Location> {
+        self.ast.find_location(id)
+    }
+
+    fn visit(&mut self, id: ExpressionId, moved: &mut Moved) {
+        match self.ast.find_expression(id) {
+            Some(Expression::Variable(variable)) => self.check_use(id, *variable, moved),
+            Some(Expression::NewVariable(variable)) => {
+                let variable = &self.ast.variables[self.signature][variable];
+
+                if let Some(initializer) = variable.initializer {
+                    match variable.variable_type.is_reference() {
+                        true => self.visit(initializer, moved),
+                        false => self.moved(initializer, moved),
+                    }
+                }
+            }
+            Some(Expression::Assignment(assignment)) => {
+                self.moved(assignment.value, moved);
+
+                // Assigning a whole variable gives it a value again.
+                match self.ast.find_expression(assignment.place) {
+                    Some(Expression::Variable(variable)) if assignment.op.is_none() => {
+                        if let Some(moved) = moved {
+                            moved.remove(variable);
+                        }
+                    }
+                    _ => self.visit(assignment.place, moved),
+                }
+            }
+            Some(Expression::Return(value)) => {
+                if let Some(value) = value {
+                    self.returned(*value, moved);
+                }
+
+                *moved = None;
+            }
+            Some(Expression::If(if_expression)) => {
+                let mut paths = vec![];
+
+                for (condition, body) in &if_expression.branches {
+                    self.visit(*condition, moved);
+
+                    let mut path = moved.clone();
+                    self.visit(*body, &mut path);
+                    paths.push(path);
+                }
+
+                if let Some(otherwise) = if_expression.otherwise {
+                    self.visit(otherwise, moved);
+                }
+
+                paths.push(moved.take());
+                *moved = Self::merge(paths);
+            }
+            Some(Expression::Match(match_expression)) => {
+                self.visit_match(match_expression, moved, false);
+            }
+            Some(expression @ (Expression::Call(call) | Expression::Foreign(call))) => {
+                match self.ast.find_called(expression) {
+                    Some(definition) => {
+                        self.visit_arguments(definition, &call.args, moved);
+                    }
+                    None => {
+                        for arg in &call.args {
+                            self.visit(*arg, moved);
+                        }
+                    }
+                }
+            }
+            Some(Expression::Associated(associated)) => {
+                if self
+                    .ast
+                    .find_variant(&associated.ty.ty, &associated.name)
+                    .is_some()
+                {
+                    for arg in &associated.args {
+                        self.moved(*arg, moved);
+                    }
+                } else if let Some(definition) =
+                    self.ast.find_method(&associated.ty.ty, &associated.name)
+                {
+                    self.visit_arguments(definition, &associated.args, moved);
+                } else {
+                    for arg in &associated.args {
+                        self.visit(*arg, moved);
+                    }
+                }
+            }
+            Some(Expression::Loop(loop_expression)) => {
+                if let Some(condition) = loop_expression.condition {
+                    self.visit(condition, moved);
+                }
+
+                self.visit_loop_body(loop_expression.body, moved);
+
+                // Without a condition, only a `return` leaves the loop.
+                if loop_expression.condition.is_none() {
+                    *moved = None;
+                }
+            }
+            Some(Expression::For(for_expression)) => {
+                self.visit(for_expression.container, moved);
+                self.visit_loop_body(for_expression.body, moved);
+            }
+            Some(Expression::MethodCall(call)) => {
+                let method = match typecheck::infer(self.ast, self.signature, call.receiver) {
+                    Type::Named(ty) => self.ast.find_method(&ty, &call.name),
+                    _ => None,
+                };
+
+                let arguments = [call.receiver]
+                    .into_iter()
+                    .chain(call.args.iter().copied())
+                    .collect::<Vec<_>>();
+
+                match method {
+                    Some(method) => self.visit_arguments(method, &arguments, moved),
+                    None => {
+                        for argument in arguments {
+                            self.visit(argument, moved);
+                        }
+                    }
+                }
+            }
+            _ => {
+                for child in self.ast.children(id) {
+                    self.visit(child, moved);
+                }
+            }
+        }
+    }
+
+    /// Arguments passed by value are moved, the ones passed by reference only used.
+    fn visit_arguments(
+        &mut self,
+        definition: &DefinitionSignature,
+        arguments: &[ExpressionId],
+        moved: &mut Moved,
+    ) {
+        for (parameter, argument) in definition.args.iter().zip(arguments) {
+            let by_reference = self.ast.variables[definition][parameter]
+                .variable_type
+                .is_reference();
+
+            match by_reference {
+                true => self.visit(*argument, moved),
+                false => self.moved(*argument, moved),
+            }
+        }
+    }
+
+    /// Visits a loop body, which runs any number of times, as a path of its own.
+    fn visit_loop_body(&mut self, body: ExpressionId, moved: &mut Moved) {
+        let mut path = moved.clone();
+        self.visit(body, &mut path);
+
+        let mut declared = BTreeSet::new();
+        Self::declared(self.ast, body, &mut declared);
+
+        if let (Some(before), Some(after)) = (moved.as_ref(), path.as_ref()) {
+            let repeated = after
+                .iter()
+                .filter(|(variable, _)| {
+                    !before.contains_key(variable) && !declared.contains(variable)
+                })
+                .map(|(variable, found)| (*variable, found.at))
+                .collect::<Vec<_>>();
+
+            for (variable, at) in repeated {
+                if !self.reported.insert(variable) {
+                    continue;
+                }
+
+                let name = &self.ast.variables[self.signature][&variable].name;
+
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` is moved inside a loop", name))
+                        .at(self.location(at))
+                        .with_note(format!(
+                            "the next iteration would use `{}` after it's moved, assign it again \
+                             before the end of the loop",
+                            name
+                        ))
+                        .with_note(Self::not_copy(&self.types[&variable])),
+                );
+            }
+        }
+
+        *moved = Self::merge(vec![moved.take(), path]);
+    }
+
+    /// Variables declared inside the expression `id`, which each iteration declares again.
+    fn declared(ast: &Ast, id: ExpressionId, result: &mut BTreeSet<VariableId>) {
+        match ast.find_expression(id) {
+            Some(Expression::NewVariable(variable)) => {
+                result.insert(*variable);
+            }
+            Some(Expression::For(for_expression)) => {
+                result.insert(for_expression.element);
+            }
+            Some(Expression::Match(match_expression)) => {
+                for arm in &match_expression.arms {
+                    if let Pattern::Variant { bindings, .. } = &arm.pattern {
+                        result.extend(bindings.iter().flatten());
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for child in ast.children(id) {
+            Self::declared(ast, child, result);
+        }
+    }
+
+    /// Visits each arm as its own path, moving their values when `values` is set.
+    ///
+    /// An arm taking fields out of a variable moves the variable.
+    fn visit_match(&mut self, match_expression: &Match, moved: &mut Moved, values: bool) {
+        self.visit(match_expression.value, moved);
+
+        let ty = match typecheck::infer(self.ast, self.signature, match_expression.value) {
+            Type::Named(ty) => Some(ty),
+            _ => None,
+        };
+
+        let owned = match self.ast.find_expression(match_expression.value) {
+            Some(Expression::Variable(variable))
+                if !self.ast.variables[self.signature][variable]
+                    .variable_type
+                    .is_reference() =>
+            {
+                Some(*variable)
+            }
+            _ => None,
+        };
+
+        let mut paths = vec![];
+
+        for arm in &match_expression.arms {
+            let mut path = moved.clone();
+
+            if let Some(ty) = &ty
+                && takes(self.ast, self.signature, ty, arm)
+            {
+                self.check_taken(match_expression.value, ty);
+
+                if let Some(variable) = owned {
+                    self.move_out(match_expression.value, variable, &mut path);
+                }
+            }
+
+            match values {
+                true => self.moved(arm.body, &mut path),
+                false => self.visit(arm.body, &mut path),
+            }
+
+            paths.push(path);
+        }
+
+        *moved = Self::merge(paths);
+    }
+
+    /// Fields can't be taken out of a value whose `Drop` needs all of it.
+    fn check_taken(&mut self, value: ExpressionId, ty: &TypeSignature) {
+        if self.ast.find_drop(ty).is_none() {
+            return;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "cannot move fields out of `{}`, it implements `Drop`",
+                ty.name
+            ))
+            .at(self.location(value))
+            .with_note(
+                "its `Drop` needs the whole value, match a reference to it instead".to_string(),
+            ),
+        );
+    }
+
+    /// Variables moved on any path that reaches the end of a branch.
+    fn merge(paths: Vec<Moved>) -> Moved {
+        let paths = paths.into_iter().flatten().collect::<Vec<_>>();
+
+        if paths.is_empty() {
+            return None;
+        }
+
+        let mut result = BTreeMap::new();
+
+        for path in &paths {
+            for (variable, first) in path {
+                let always = paths
+                    .iter()
+                    .all(|path| path.get(variable).is_some_and(|moved| moved.always));
+
+                result.entry(*variable).or_insert(Move {
+                    at: first.at,
+                    always,
+                });
+            }
+        }
+
+        Some(result)
+    }
+
+    fn returned(&mut self, value: ExpressionId, moved: &mut Moved) {
+        match self.signature.return_variable_type.is_reference() {
+            true => self.visit(value, moved),
+            false => self.moved(value, moved),
+        }
+    }
+
+    /// Visits a value that's moved out, along with the variable holding it.
+    fn moved(&mut self, id: ExpressionId, moved: &mut Moved) {
+        match self.ast.find_expression(id) {
+            Some(Expression::Variable(variable)) => {
+                self.check_use(id, *variable, moved);
+                self.move_out(id, *variable, moved);
+            }
+            Some(Expression::Match(match_expression)) => {
+                self.visit_match(match_expression, moved, true);
+            }
+            Some(Expression::Index(index)) => {
+                self.visit(id, moved);
+
+                // Raw pointers don't own what they point to, trusted code reads it out.
+                let pointer = matches!(
+                    typecheck::infer(self.ast, self.signature, index.base),
+                    Type::Pointer(_)
+                );
+
+                // Only whole variables are tracked, an element can't be moved out alone.
+                if let Type::Named(ty) = typecheck::infer(self.ast, self.signature, id)
+                    && !pointer
+                    && !self.ast.is_copy(&ty)
+                {
+                    self.diagnostics.push(
+                        Diagnostic::error("cannot move out of an array element".to_string())
+                            .at(self.location(id))
+                            .with_note(Self::not_copy(&ty)),
+                    );
+                }
+            }
+            _ => self.visit(id, moved),
+        }
+    }
+
+    fn move_out(&mut self, id: ExpressionId, variable: VariableId, moved: &mut Moved) {
+        let data = &self.ast.variables[self.signature][&variable];
+
+        let ty = match data.ty.signature() {
+            // Pattern bindings take the type of the field they bind.
+            Some(ty) if ty.name.is_empty() => {
+                match typecheck::infer(self.ast, self.signature, id) {
+                    Type::Named(ty) => ty,
+                    _ => return,
+                }
+            }
+            Some(ty) => ty.clone(),
+            None => return,
+        };
+
+        if self.ast.is_copy(&ty) {
+            return;
+        }
+
+        if data.variable_type.is_reference() {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "cannot move out of `{}`, it's a reference",
+                    data.name
+                ))
+                .at(self.location(id))
+                .with_note(Self::not_copy(&ty)),
+            );
+
+            return;
+        }
+
+        if let Some(moved) = moved {
+            moved.insert(
+                variable,
+                Move {
+                    at: id,
+                    always: true,
+                },
+            );
+        }
+
+        self.types.insert(variable, ty);
+    }
+
+    fn not_copy(ty: &TypeSignature) -> String {
+        format!(
+            "`{}` doesn't implement `Copy`, so its values are moved instead of copied",
+            ty.name
+        )
+    }
+
+    fn check_use(&mut self, id: ExpressionId, variable: VariableId, moved: &Moved) {
+        let Some(found) = moved.as_ref().and_then(|moved| moved.get(&variable)) else {
+            return;
+        };
+
+        if !self.reported.insert(variable) {
+            return;
+        }
+
+        let name = &self.ast.variables[self.signature][&variable].name;
+
+        let (message, note) = match found.always {
+            true => ("is used after it's moved", "is moved"),
+            false => ("may be used after it's moved", "may have been moved"),
+        };
+
+        let at = match self.location(found.at) {
+            Some(location) => format!(" at {}:{}", location.line, location.column),
+            None => String::new(),
+        };
+
+        self.diagnostics.push(
+            Diagnostic::error(format!("`{}` {}", name, message))
+                .at(self.location(id))
+                .with_note(format!("`{}` {}{}", name, note, at))
+                .with_note(Self::not_copy(&self.types[&variable])),
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    analysis::{
+        initialization::repeated_assignments,
+        typecheck::{self, Type},
+    },
+    diagnostic::Diagnostic,
+    parser::ast::{
+        Assignment, Ast, DefinitionSignature, Expression, ExpressionId, MethodCall, Pattern, UnOp,
+        UnOpType, Variable, VariableId, VariableType,
+    },
+};
+
+/// Rejects assignments to immutable bindings and `mutable self` calls on immutable ones.
+///
+/// Only places, variables, fields and dereferences, and the `mutable &` results of calls,
+/// can be assigned to. A `let` without an initializer may still be assigned once with `=`,
+/// which is how it gets its value, as long as no path through the definition can assign
+/// it before.
+/// Containers can't be changed while a `for` loop iterates over them.
+pub fn check_mutability(ast: &Ast) -> Vec<Diagnostic> {
+    let mut checker = MutabilityChecker {
+        ast,
+        bindings: BTreeSet::new(),
+        iterated: vec![],
+        repeated: repeated_assignments(ast),
+        diagnostics: vec![],
+    };
+
+    for (signature, body) in &ast.definitions {
+        checker.check(signature, *body);
+    }
+
+    checker.diagnostics
+}
+
+pub fn is_mutable(variable: &Variable) -> bool {
+    matches!(
+        variable.variable_type,
+        VariableType::MutableValue | VariableType::MutableReference
+    )
+}
+
+struct MutabilityChecker<'a> {
+    ast: &'a Ast,
+    /// Variables bound by `match` patterns, which already have a value.
+    bindings: BTreeSet<VariableId>,
+    /// Containers of the `for` loops being checked, innermost last.
+    iterated: Vec<VariableId>,
+    /// Assignments that may not be the first of their variable.
+    repeated: BTreeSet<ExpressionId>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> MutabilityChecker<'a> {
+    fn check(&mut self, signature: &DefinitionSignature, id: ExpressionId) {
+        match self.ast.find_expression(id) {
+            Some(Expression::Assignment(assignment)) => {
+                self.check_assignment(signature, id, assignment);
+            }
+            Some(Expression::MethodCall(call)) => {
+                self.check_method_call(signature, id, call);
+
+                if let Type::Named(ty) = typecheck::infer(self.ast, signature, call.receiver)
+                    && let Some(method) = self.ast.find_method(&ty, &call.name)
+                {
+                    self.check_arguments(signature, method.args.iter().skip(1), &call.args);
+                }
+            }
+            Some(expression @ (Expression::Call(call) | Expression::Foreign(call))) => {
+                if let Some(definition) = self.ast.find_called(expression) {
+                    self.check_arguments(signature, definition.args.iter(), &call.args);
+                }
+            }
+            Some(Expression::Associated(associated)) => {
+                if let Some(definition) = self.ast.find_method(&associated.ty.ty, &associated.name)
+                {
+                    self.check_arguments(signature, definition.args.iter(), &associated.args);
+                }
+            }
+            Some(Expression::For(for_expression)) => {
+                self.check(signature, for_expression.container);
+
+                let container = self
+                    .place_root(signature, for_expression.container)
+                    .ok()
+                    .flatten()
+                    .and_then(|root| self.binding(signature, root))
+                    .map(|(id, _)| id);
+
+                self.iterated.extend(container);
+                self.check(signature, for_expression.body);
+
+                if container.is_some() {
+                    self.iterated.pop();
+                }
+
+                return;
+            }
+            Some(Expression::NewVariable(variable)) => {
+                if let Some(variable) = self.ast.variables[signature].get(variable)
+                    && variable.variable_type == VariableType::MutableReference
+                    && let Some(initializer) = variable.initializer
+                {
+                    self.check_mutable_reference(signature, initializer);
+                }
+            }
+            Some(Expression::Match(match_expression)) => {
+                for arm in &match_expression.arms {
+                    if let Pattern::Variant { bindings, .. } = &arm.pattern {
+                        self.bindings.extend(bindings.iter().flatten());
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for child in self.ast.children(id) {
+            self.check(signature, child);
+        }
+    }
+
+    fn binding(
+        &self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+    ) -> Option<(VariableId, &'a Variable)> {
+        match self.ast.find_expression(id) {
+            Some(Expression::Variable(variable)) => self
+                .ast
+                .variables
+                .get(signature)?
+                .get(variable)
+                .map(|binding| (*variable, binding)),
+            _ => None,
+        }
+    }
+
+    /// How to make `variable` mutable.
+    fn suggestion(
+        &self,
+        signature: &DefinitionSignature,
+        id: VariableId,
+        variable: &Variable,
+    ) -> String {
+        if variable.name == "self" {
+            "declare the receiver as `mutable self`".to_string()
+        } else if self.bindings.contains(&id) {
+            format!(
+                "`{}` is bound by a pattern, copy it into a `let mutable`",
+                variable.name
+            )
+        } else if variable.variable_type == VariableType::Reference {
+            format!("declare `{}` as a `mutable &` reference", variable.name)
+        } else if signature.args.contains(&id) {
+            format!("declare the parameter as `mutable {}`", variable.name)
+        } else {
+            format!("declare it with `let mutable {}`", variable.name)
+        }
+    }
+
+    /// Arguments passed to `mutable &` parameters must reference mutable places.
+    fn check_arguments<'b>(
+        &mut self,
+        signature: &DefinitionSignature,
+        parameters: impl Iterator<Item = &'b VariableId>,
+        arguments: &[ExpressionId],
+    ) {
+        for (parameter, argument) in parameters.zip(arguments) {
+            let takes_mutable_reference = self
+                .ast
+                .find_variable_by_id(*parameter)
+                .is_some_and(|parameter| parameter.variable_type == VariableType::MutableReference);
+
+            if takes_mutable_reference {
+                self.check_mutable_reference(signature, *argument);
+            }
+        }
+    }
+
+    /// `value` is used as a `mutable &` reference, so what it points to must be mutable.
+    fn check_mutable_reference(&mut self, signature: &DefinitionSignature, value: ExpressionId) {
+        let root = match self.ast.find_expression(value) {
+            Some(Expression::UnaryOperation(UnOp {
+                op: UnOpType::Reference,
+                operand,
+            })) => self.place_root(signature, *operand).ok().flatten(),
+            Some(Expression::Variable(_)) => Some(value),
+            _ => None,
+        };
+
+        let Some((variable_id, variable)) = root.and_then(|root| self.binding(signature, root))
+        else {
+            return;
+        };
+
+        if self.check_iterated(value, variable_id, variable) {
+            return;
+        }
+
+        let mutable = match root == Some(value) {
+            // A reference passed along keeps the mutability it was created with.
+            true => variable.variable_type != VariableType::Reference,
+            false => is_mutable(variable),
+        };
+
+        if mutable {
+            return;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "cannot take a `mutable &` reference to `{}`, it isn't mutable",
+                variable.name
+            ))
+            .at(self.ast.find_location(value))
+            .with_note(self.suggestion(signature, variable_id, variable)),
+        );
+    }
+
+    /// Reports changing a container while a `for` loop iterates over it.
+    fn check_iterated(
+        &mut self,
+        id: ExpressionId,
+        variable_id: VariableId,
+        variable: &Variable,
+    ) -> bool {
+        if !self.iterated.contains(&variable_id) {
+            return false;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "cannot change `{}` while it's iterated",
+                variable.name
+            ))
+            .at(self.ast.find_location(id))
+            .with_note("the `for` loop holds a reference to its current element".to_string()),
+        );
+
+        true
+    }
+
+    /// The variable a place writes into, `None` when it writes through a dereference.
+    fn place_root(
+        &self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+    ) -> Result<Option<ExpressionId>, ()> {
+        match self.ast.find_expression(id) {
+            Some(Expression::Variable(_)) => Ok(Some(id)),
+            Some(Expression::Field(field)) => self.place_root(signature, field.base),
+            // Indexing a raw pointer writes through it, like a dereference.
+            Some(Expression::Index(index)) => {
+                match typecheck::infer(self.ast, signature, index.base) {
+                    Type::Pointer(_) => Ok(None),
+                    _ => self.place_root(signature, index.base),
+                }
+            }
+            Some(Expression::UnaryOperation(UnOp {
+                op: UnOpType::Dereference,
+                ..
+            })) => Ok(None),
+            // A call giving a `mutable &` writes through it, its receiver is checked as one.
+            Some(expression) if self.gives_mutable_reference(signature, expression) => Ok(None),
+            _ => Err(()),
+        }
+    }
+
+    fn gives_mutable_reference(
+        &self,
+        signature: &DefinitionSignature,
+        expression: &Expression,
+    ) -> bool {
+        let called = match expression {
+            Expression::Call(_) | Expression::Foreign(_) => self.ast.find_called(expression),
+            Expression::Associated(associated) => {
+                self.ast.find_method(&associated.ty.ty, &associated.name)
+            }
+            Expression::MethodCall(call) => {
+                match typecheck::infer(self.ast, signature, call.receiver) {
+                    Type::Named(ty) => self.ast.find_method(&ty, &call.name),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        called.is_some_and(|called| called.return_variable_type == VariableType::MutableReference)
+    }
+
+    fn check_assignment(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+        assignment: &Assignment,
+    ) {
+        let Ok(root) = self.place_root(signature, assignment.place) else {
+            self.diagnostics.push(
+                Diagnostic::error("this expression can't be assigned to".to_string())
+                    .at(self.ast.find_location(assignment.place))
+                    .with_note(
+                        "only variables, fields, elements, dereferences and `mutable &` results \
+                         can be assigned to"
+                            .to_string(),
+                    ),
+            );
+
+            return;
+        };
+
+        let Some((variable_id, variable)) = root.and_then(|root| self.binding(signature, root))
+        else {
+            return;
+        };
+
+        if self.check_iterated(id, variable_id, variable) {
+            return;
+        }
+
+        let whole = root == Some(assignment.place);
+        let deferred = whole
+            && variable.initializer.is_none()
+            && assignment.op.is_none()
+            && !signature.args.contains(&variable_id)
+            && !self.bindings.contains(&variable_id);
+
+        if is_mutable(variable) || deferred && !self.repeated.contains(&id) {
+            return;
+        }
+
+        let message = match whole {
+            true => format!("cannot assign to `{}`, it isn't mutable", variable.name),
+            false => format!(
+                "cannot assign to a field of `{}`, it isn't mutable",
+                variable.name
+            ),
+        };
+
+        let mut diagnostic = Diagnostic::error(message).at(self.ast.find_location(id));
+
+        if deferred {
+            diagnostic = diagnostic.with_note(format!(
+                "`{}` may already be assigned here, and it can only be assigned once",
+                variable.name
+            ));
+        }
+
+        self.diagnostics.push(diagnostic.with_note(self.suggestion(
+            signature,
+            variable_id,
+            variable,
+        )));
+    }
+
+    fn check_method_call(
+        &mut self,
+        signature: &DefinitionSignature,
+        id: ExpressionId,
+        call: &MethodCall,
+    ) {
+        let Some((variable_id, variable)) = self.binding(signature, call.receiver) else {
+            return;
+        };
+
+        if is_mutable(variable) && !self.iterated.contains(&variable_id) {
+            return;
+        }
+
+        let Type::Named(ty) = typecheck::infer(self.ast, signature, call.receiver) else {
+            return;
+        };
+
+        let takes_mutable_self = self
+            .ast
+            .find_method(&ty, &call.name)
+            .and_then(|method| {
+                let receiver = method.args.first()?;
+                self.ast.variables.get(method)?.get(receiver)
+            })
+            .is_some_and(|receiver| {
+                receiver.name == "self" && receiver.variable_type == VariableType::MutableReference
+            });
+
+        if !takes_mutable_self
+            || self.check_iterated(id, variable_id, variable)
+            || is_mutable(variable)
+        {
+            return;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "cannot call `{}` on `{}`, it isn't mutable",
+                call.name, variable.name
+            ))
+            .at(self.ast.find_location(id))
+            .with_note(format!("`{}` takes `mutable self`", call.name))
+            .with_note(self.suggestion(signature, variable_id, variable)),
+        );
+    }
+}
@@ -0,0 +1,909 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    analysis::typecheck::{self, Type},
+    diagnostic::{Diagnostic, Location},
+    parser::ast::{
+        Ast, DefinitionSignature, Expression, ExpressionId, MethodCall, Pattern, UnOp, UnOpType,
+        Variable, VariableId, VariableType,
+    },
+};
+
+/// Checks that references never outlive the values they point to.
+///
+/// Every definition is a tree of nodes: its body, and the blocks, `if` branches, `match`
+/// arms and loop bodies inside it. Each variable is owned by the node declaring it and dropped when that
+/// node ends, so a reference may only be stored by a node its target's owner outlives,
+/// and only references received from the caller can be returned.
+///
+/// A reference also keeps the variable it points into borrowed: while it's used later on,
+/// that variable can't be changed through a `mutable self` method or a `mutable &`
+/// parameter, assigned or moved.
+pub fn check_ownership(ast: &Ast) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    for (signature, body) in &ast.definitions {
+        let mut checker = OwnershipChecker {
+            ast,
+            signature,
+            nodes: BTreeMap::new(),
+            owners: BTreeMap::new(),
+            targets: BTreeMap::new(),
+            loans: BTreeSet::new(),
+            changed: Some(BTreeMap::new()),
+            reported: BTreeSet::new(),
+            diagnostics: vec![],
+        };
+
+        checker.check_definition(*body);
+
+        diagnostics.extend(checker.diagnostics);
+    }
+
+    diagnostics
+}
+
+/// What a reference points to.
+#[derive(Debug, Clone)]
+struct Target {
+    /// Name of the variable it points into.
+    name: String,
+    /// Node owning the variable, `None` when it's owned by the caller.
+    owner: Option<ExpressionId>,
+    /// The variable itself, `None` when it isn't one of the definition.
+    variable: Option<VariableId>,
+}
+
+impl Target {
+    fn caller(name: String) -> Self {
+        Self {
+            name,
+            owner: None,
+            variable: None,
+        }
+    }
+}
+
+/// Errors for the references whose target was changed on some path reaching a point,
+/// reported if they're used again, `None` once no path reaches it.
+type Changed = Option<BTreeMap<VariableId, Diagnostic>>;
+
+struct Node {
+    parent: Option<ExpressionId>,
+    description: String,
+}
+
+struct OwnershipChecker<'a> {
+    ast: &'a Ast,
+    signature: &'a DefinitionSignature,
+    nodes: BTreeMap<ExpressionId, Node>,
+    /// Node owning each variable of the definition.
+    owners: BTreeMap<VariableId, ExpressionId>,
+    /// What each reference variable points to.
+    targets: BTreeMap<VariableId, Target>,
+    /// Reference variables declared in the definition, which keep their target borrowed.
+    loans: BTreeSet<VariableId>,
+    changed: Changed,
+    /// References already reported, only their first use after a change is.
+    reported: BTreeSet<VariableId>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl OwnershipChecker<'_> {
+    fn check_definition(&mut self, body: ExpressionId) {
+        self.nodes.insert(
+            body,
+            Node {
+                parent: None,
+                description: format!("the body of `{}`", self.signature.name),
+            },
+        );
+
+        for argument in &self.signature.args {
+            self.owners.insert(*argument, body);
+
+            let variable = self.variable(*argument);
+
+            // Referenced arguments belong to the caller, which outlives the whole body.
+            if variable.variable_type.is_reference() {
+                let target = Target {
+                    variable: Some(*argument),
+                    ..Target::caller(variable.name.clone())
+                };
+
+                self.targets.insert(*argument, target);
+            }
+        }
+
+        let returns_reference = self.signature.return_variable_type.is_reference();
+
+        match self.ast.find_expression(body) {
+            Some(Expression::Body(_) | Expression::If(_)) => self.visit_contents(body, body),
+            Some(_) if returns_reference => self.check_returned(body, body),
+            Some(_) => self.visit(body, body),
+            None => {}
+        }
+    }
+
+    fn variable(&self, id: VariableId) -> &Variable {
+        &self.ast.variables[self.signature][&id]
+    }
+
+    fn location(&self, id: ExpressionId) -> Option<Location> {
+        self.ast.find_location(id)
+    }
+
+    /// Adds a node to the tree, described with the location of `at`.
+    fn new_node(&mut self, id: ExpressionId, parent: ExpressionId, kind: &str, at: ExpressionId) {
+        let description = match self.location(at) {
+            Some(location) => format!("the {} at {}:{}", kind, location.line, location.column),
+            None => format!("a {}", kind),
+        };
+
+        self.nodes.insert(
+            id,
+            Node {
+                parent: Some(parent),
+                description,
+            },
+        );
+    }
+
+    /// Whether `owner` lives at least as long as `node`, the caller outlives every node.
+    fn outlives(&self, owner: Option<ExpressionId>, node: ExpressionId) -> bool {
+        let Some(owner) = owner else {
+            return true;
+        };
+
+        let mut current = Some(node);
+
+        while let Some(id) = current {
+            if id == owner {
+                return true;
+            }
+
+            current = self.nodes.get(&id).and_then(|node| node.parent);
+        }
+
+        false
+    }
+
+    fn describe(&self, owner: Option<ExpressionId>) -> String {
+        match owner.and_then(|owner| self.nodes.get(&owner)) {
+            Some(node) => node.description.clone(),
+            None => "the caller".to_string(),
+        }
+    }
+
+    /// Visits the contents of a `Body` or an `if` as the node `node`.
+    fn visit_contents(&mut self, id: ExpressionId, node: ExpressionId) {
+        match self.ast.find_expression(id) {
+            Some(Expression::Body(body)) => {
+                for content in &body.contents {
+                    self.visit(*content, node);
+                }
+            }
+            _ => self.visit(id, node),
+        }
+    }
+
+    fn visit(&mut self, id: ExpressionId, node: ExpressionId) {
+        match self.ast.find_expression(id) {
+            Some(Expression::Variable(variable)) => self.used(*variable, id),
+            Some(Expression::Body(_)) => {
+                self.new_node(id, node, "block", id);
+                self.visit_contents(id, id);
+            }
+            Some(Expression::If(if_expression)) => {
+                let mut paths = vec![];
+
+                for (condition, body) in &if_expression.branches {
+                    self.visit(*condition, node);
+                    self.new_node(*body, node, "`if` branch", *condition);
+
+                    let start = self.changed.clone();
+                    self.visit_contents(*body, *body);
+                    paths.push(std::mem::replace(&mut self.changed, start));
+                }
+
+                if let Some(otherwise) = if_expression.otherwise {
+                    self.new_node(otherwise, node, "`else` branch", id);
+                    self.visit_contents(otherwise, otherwise);
+                }
+
+                paths.push(self.changed.take());
+                self.changed = Self::merge(paths);
+            }
+            Some(Expression::Match(match_expression)) => {
+                self.visit(match_expression.value, node);
+
+                let start = self.changed.take();
+                let mut paths = vec![];
+
+                for arm in &match_expression.arms {
+                    self.changed = start.clone();
+                    self.new_arm(arm.body, &arm.pattern, match_expression.value, node);
+                    self.visit(arm.body, arm.body);
+                    paths.push(self.changed.take());
+                }
+
+                self.changed = Self::merge(paths);
+            }
+            Some(Expression::Loop(loop_expression)) => {
+                if let Some(condition) = loop_expression.condition {
+                    self.visit(condition, node);
+                }
+
+                self.new_node(loop_expression.body, node, "loop", id);
+                self.visit_loop_body(loop_expression.body);
+
+                // Without a condition, only a `return` leaves the loop.
+                if loop_expression.condition.is_none() {
+                    self.changed = None;
+                }
+            }
+            Some(Expression::For(for_expression)) => {
+                self.visit(for_expression.container, node);
+                self.new_node(for_expression.body, node, "`for` loop", id);
+
+                // The element points into the container, which outlives the loop.
+                self.owners
+                    .insert(for_expression.element, for_expression.body);
+
+                if let Some(target) = self.place_target(for_expression.container) {
+                    self.targets.insert(for_expression.element, target);
+                }
+
+                self.visit_loop_body(for_expression.body);
+            }
+            Some(Expression::Associated(associated)) => {
+                let parameters = self
+                    .ast
+                    .find_method(&associated.ty.ty, &associated.name)
+                    .map(|definition| definition.args.clone())
+                    .unwrap_or_default();
+
+                self.visit_arguments(&parameters, &associated.args, node);
+            }
+            Some(Expression::NewVariable(variable)) => self.check_declaration(*variable, node),
+            Some(Expression::Assignment(assignment)) => {
+                self.visit(assignment.place, node);
+                self.visit(assignment.value, node);
+                self.moved(assignment.value);
+
+                if let Some(variable) = self.place_variable(assignment.place) {
+                    let name = &self.variable(variable).name;
+                    let action = format!("cannot assign to `{}`", name);
+                    self.change(variable, assignment.place, action);
+                }
+            }
+            Some(Expression::Return(value)) => {
+                match value {
+                    Some(value) if self.signature.return_variable_type.is_reference() => {
+                        self.check_returned(*value, node);
+                    }
+                    Some(value) => self.visit(*value, node),
+                    None => {}
+                }
+
+                self.changed = None;
+            }
+            Some(expression @ (Expression::Call(call) | Expression::Foreign(call))) => {
+                let parameters = self
+                    .ast
+                    .find_called(expression)
+                    .map(|definition| definition.args.clone())
+                    .unwrap_or_default();
+
+                self.visit_arguments(&parameters, &call.args, node);
+            }
+            Some(Expression::MethodCall(call)) => {
+                self.visit(call.receiver, node);
+                self.check_receiver(call);
+
+                // A `mutable self` method may change anything the receiver holds.
+                let changes = self
+                    .method(call.receiver, &call.name)
+                    .and_then(|method| method.args.first())
+                    .and_then(|receiver| self.parameter(*receiver))
+                    .is_some_and(|receiver| {
+                        receiver.variable_type == VariableType::MutableReference
+                    });
+
+                if let Some(variable) = self.place_variable(call.receiver)
+                    && changes
+                {
+                    let name = &self.variable(variable).name;
+                    let action = format!("cannot call `{}` on `{}`", call.name, name);
+                    self.change(variable, call.receiver, action);
+                }
+
+                // The receiver is borrowed implicitly, only the other arguments are checked.
+                let parameters = self
+                    .method(call.receiver, &call.name)
+                    .map(|definition| definition.args.iter().skip(1).copied().collect::<Vec<_>>())
+                    .unwrap_or_default();
+
+                self.visit_arguments(&parameters, &call.args, node);
+            }
+            Some(Expression::UnaryOperation(UnOp {
+                op: UnOpType::Reference,
+                ..
+            })) => {
+                self.diagnostics.push(
+                    Diagnostic::error("a reference can't be used as a value".to_string())
+                        .at(self.location(id))
+                        .with_note(
+                            "references can only be stored in `&` variables, passed to `&` \
+                             parameters or returned as `&` results"
+                                .to_string(),
+                        ),
+                );
+            }
+            _ => {
+                for child in self.ast.children(id) {
+                    self.visit(child, node);
+                }
+            }
+        }
+    }
+
+    /// Visits a loop body, which runs any number of times, as a path of its own.
+    ///
+    /// A reference declared before the loop and used anywhere in its body can't see its
+    /// target changed inside it, the next iteration would use it afterwards.
+    fn visit_loop_body(&mut self, body: ExpressionId) {
+        let before = self.changed.clone();
+        self.visit_contents(body, body);
+
+        let changed = self
+            .changed
+            .iter()
+            .flatten()
+            .filter(|(reference, _)| {
+                before
+                    .as_ref()
+                    .is_some_and(|before| !before.contains_key(reference))
+            })
+            .map(|(reference, diagnostic)| (*reference, diagnostic.clone()))
+            .collect::<Vec<_>>();
+
+        for (reference, diagnostic) in changed {
+            if let Some(used) = self.find_use(body, reference) {
+                self.report(reference, diagnostic, used);
+            }
+        }
+
+        self.changed = Self::merge(vec![before, self.changed.take()]);
+    }
+
+    /// First use of `variable` inside the expression `id`.
+    fn find_use(&self, id: ExpressionId, variable: VariableId) -> Option<ExpressionId> {
+        if let Some(Expression::Variable(used)) = self.ast.find_expression(id)
+            && *used == variable
+        {
+            return Some(id);
+        }
+
+        self.ast
+            .children(id)
+            .into_iter()
+            .find_map(|child| self.find_use(child, variable))
+    }
+
+    /// References changed on any path that reaches the end of a branch.
+    fn merge(paths: Vec<Changed>) -> Changed {
+        let mut result: Changed = None;
+
+        for path in paths.into_iter().flatten() {
+            let result = result.get_or_insert_with(BTreeMap::new);
+
+            for (reference, diagnostic) in path {
+                result.entry(reference).or_insert(diagnostic);
+            }
+        }
+
+        result
+    }
+
+    /// Reports the use of a reference whose target was changed before.
+    fn used(&mut self, variable: VariableId, id: ExpressionId) {
+        let Some(diagnostic) = self
+            .changed
+            .as_mut()
+            .and_then(|changed| changed.remove(&variable))
+        else {
+            return;
+        };
+
+        self.report(variable, diagnostic, id);
+    }
+
+    fn report(&mut self, reference: VariableId, diagnostic: Diagnostic, used: ExpressionId) {
+        if !self.reported.insert(reference) {
+            return;
+        }
+
+        let note = match self.location(used) {
+            Some(location) => format!(
+                "`{}` is used afterwards at {}:{}",
+                self.variable(reference).name,
+                location.line,
+                location.column
+            ),
+            None => format!("`{}` is used afterwards", self.variable(reference).name),
+        };
+
+        self.diagnostics.push(diagnostic.with_note(note));
+    }
+
+    /// Marks the references into `variable` as changed by the expression `id`, but the one
+    /// it's changed through when it's a reference itself.
+    fn change(&mut self, variable: VariableId, id: ExpressionId, action: String) {
+        let changed = self
+            .targets
+            .get(&variable)
+            .and_then(|target| target.variable)
+            .unwrap_or(variable);
+
+        let Some(paths) = self.changed.as_mut() else {
+            return;
+        };
+
+        for reference in &self.loans {
+            let points_into = self
+                .targets
+                .get(reference)
+                .is_some_and(|target| target.variable == Some(changed));
+
+            if *reference == variable || !points_into || paths.contains_key(reference) {
+                continue;
+            }
+
+            paths.insert(
+                *reference,
+                Diagnostic::error(format!(
+                    "{} while `{}` points into it",
+                    action, self.ast.variables[self.signature][reference].name
+                ))
+                .at(self.ast.find_location(id)),
+            );
+        }
+    }
+
+    /// A variable used as a value is moved out when its type isn't `Copy`.
+    fn moved(&mut self, id: ExpressionId) {
+        let Some(Expression::Variable(variable)) = self.ast.find_expression(id) else {
+            return;
+        };
+
+        if self.targets.contains_key(variable) {
+            return;
+        }
+
+        if let Type::Named(ty) = typecheck::infer(self.ast, self.signature, id)
+            && !self.ast.is_copy(&ty)
+        {
+            let action = format!("cannot move `{}`", self.variable(*variable).name);
+            self.change(*variable, id, action);
+        }
+    }
+
+    /// The variable holding a place, `None` for places behind a pointer.
+    fn place_variable(&self, id: ExpressionId) -> Option<VariableId> {
+        match self.ast.find_expression(id)? {
+            Expression::Variable(variable) => Some(*variable),
+            Expression::Field(field) => self.place_variable(field.base),
+            Expression::Index(index) => {
+                match typecheck::infer(self.ast, self.signature, index.base) {
+                    Type::Pointer(_) => None,
+                    _ => self.place_variable(index.base),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Adds the node of an arm matching `value`, whose bindings are references into it when
+    /// it's borrowed.
+    fn new_arm(
+        &mut self,
+        arm: ExpressionId,
+        pattern: &Pattern,
+        value: ExpressionId,
+        node: ExpressionId,
+    ) {
+        self.new_node(arm, node, "`match` arm", arm);
+
+        let Pattern::Variant { bindings, .. } = pattern else {
+            return;
+        };
+
+        for binding in bindings.iter().flatten() {
+            self.owners.insert(*binding, arm);
+
+            if !self.variable(*binding).variable_type.is_reference() {
+                continue;
+            }
+
+            if let Some(target) = self.target(value).or_else(|| self.place_target(value)) {
+                self.targets.insert(*binding, target);
+                self.loans.insert(*binding);
+            }
+        }
+    }
+
+    fn method(&self, receiver: ExpressionId, name: &str) -> Option<&DefinitionSignature> {
+        match typecheck::infer(self.ast, self.signature, receiver) {
+            Type::Named(ty) => self.ast.find_method(&ty, name),
+            _ => None,
+        }
+    }
+
+    /// Methods taking `&self` or `mutable self` borrow their receiver, which needs a place to
+    /// live in, a value returned by a call has none.
+    fn check_receiver(&mut self, call: &MethodCall) {
+        let Some(method) = self.method(call.receiver, &call.name) else {
+            return;
+        };
+
+        let borrowed = method
+            .args
+            .first()
+            .and_then(|receiver| self.parameter(*receiver))
+            .is_some_and(|receiver| receiver.variable_type.is_reference());
+
+        let called = match self.ast.find_expression(call.receiver) {
+            Some(expression @ (Expression::Call(_) | Expression::Foreign(_))) => {
+                self.ast.find_called(expression)
+            }
+            Some(Expression::MethodCall(inner)) => self.method(inner.receiver, &inner.name),
+            Some(Expression::Associated(associated)) => {
+                self.ast.find_method(&associated.ty.ty, &associated.name)
+            }
+            _ => return,
+        };
+
+        if !borrowed || called.is_none_or(|called| called.return_variable_type.is_reference()) {
+            return;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "`{}` borrows its receiver, which is a value returned by a call",
+                call.name
+            ))
+            .at(self.location(call.receiver))
+            .with_note("store the value in a variable first".to_string()),
+        );
+    }
+
+    fn visit_arguments(
+        &mut self,
+        parameters: &[VariableId],
+        arguments: &[ExpressionId],
+        node: ExpressionId,
+    ) {
+        for (i, argument) in arguments.iter().enumerate() {
+            let variable_type = parameters
+                .get(i)
+                .and_then(|parameter| self.parameter(*parameter))
+                .map(|parameter| parameter.variable_type.clone())
+                .unwrap_or_default();
+
+            // The call ends before any node does, so any reference can be passed to it.
+            match variable_type.is_reference() {
+                true => {
+                    self.reference(*argument, node);
+                }
+                false => {
+                    self.visit(*argument, node);
+                    self.moved(*argument);
+                }
+            }
+
+            let changed = match self.ast.find_expression(*argument) {
+                Some(Expression::UnaryOperation(UnOp {
+                    op: UnOpType::Reference,
+                    operand,
+                })) => self.place_variable(*operand),
+                _ => self.place_variable(*argument),
+            };
+
+            if let Some(variable) = changed
+                && variable_type == VariableType::MutableReference
+            {
+                let name = &self.variable(variable).name;
+                let action = format!("cannot pass `{}` as a `mutable &` reference", name);
+                self.change(variable, *argument, action);
+            }
+        }
+    }
+
+    /// Parameters may belong to other definitions, so they're looked up in the whole ast.
+    fn parameter(&self, id: VariableId) -> Option<&Variable> {
+        self.ast.find_variable_by_id(id)
+    }
+
+    fn check_declaration(&mut self, id: VariableId, node: ExpressionId) {
+        self.owners.insert(id, node);
+
+        let variable = self.variable(id);
+        let name = variable.name.clone();
+        let initializer = variable.initializer;
+
+        if !variable.variable_type.is_reference() {
+            if let Some(initializer) = initializer {
+                self.visit(initializer, node);
+                self.moved(initializer);
+            }
+
+            return;
+        }
+
+        let Some(initializer) = initializer else {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "`{}` is a reference, it needs a value when it's declared",
+                    name
+                ))
+                .at(self.ast.find_variable_location(id))
+                .with_note("references can't be assigned after they're declared".to_string()),
+            );
+
+            return;
+        };
+
+        for (value, target) in self.references(initializer, node) {
+            if !self.outlives(target.owner, node) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "`{}` doesn't live as long as `{}`",
+                        target.name, name
+                    ))
+                    .at(self.location(value))
+                    .with_note(format!(
+                        "`{}` is owned by {}",
+                        target.name,
+                        self.describe(target.owner)
+                    ))
+                    .with_note(format!(
+                        "`{}` is owned by {}, which outlives it",
+                        name,
+                        self.describe(Some(node))
+                    )),
+                );
+            }
+
+            self.targets.insert(id, target);
+            self.loans.insert(id);
+        }
+    }
+
+    /// Checks a value returned as a reference, which can only point into the caller.
+    fn check_returned(&mut self, value: ExpressionId, node: ExpressionId) {
+        for (value, target) in self.references(value, node) {
+            if target.owner.is_some() {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("cannot return a reference to `{}`", target.name))
+                        .at(self.location(value))
+                        .with_note(format!(
+                            "`{}` is owned by {}, which is dropped when `{}` returns",
+                            target.name,
+                            self.describe(target.owner),
+                            self.signature.name
+                        )),
+                );
+            }
+        }
+    }
+
+    /// Targets of an expression used as a reference, with the expression giving each.
+    ///
+    /// A `match` gives one target per arm, anything else at most one.
+    fn references(&mut self, id: ExpressionId, node: ExpressionId) -> Vec<(ExpressionId, Target)> {
+        if let Some(Expression::Match(match_expression)) = self.ast.find_expression(id) {
+            self.visit(match_expression.value, node);
+
+            let start = self.changed.take();
+            let mut paths = vec![];
+            let mut result = vec![];
+
+            for arm in &match_expression.arms {
+                self.changed = start.clone();
+                self.new_arm(arm.body, &arm.pattern, match_expression.value, node);
+                result.extend(self.references(arm.body, arm.body));
+                paths.push(self.changed.take());
+            }
+
+            self.changed = Self::merge(paths);
+
+            return result;
+        }
+
+        self.reference(id, node)
+            .map(|target| (id, target))
+            .into_iter()
+            .collect()
+    }
+
+    /// Checks an expression used as a reference, returning its target when it's known.
+    fn reference(&mut self, id: ExpressionId, node: ExpressionId) -> Option<Target> {
+        let is_reference = match self.ast.find_expression(id) {
+            Some(Expression::UnaryOperation(UnOp {
+                op: UnOpType::Reference,
+                operand,
+            })) => {
+                return match self.check_place(*operand, node) {
+                    true => self.target(id),
+                    false => None,
+                };
+            }
+            Some(Expression::Variable(variable)) => self.targets.contains_key(variable),
+            Some(expression @ (Expression::Call(_) | Expression::Foreign(_))) => self
+                .ast
+                .find_called(expression)
+                .is_some_and(|definition| definition.return_variable_type.is_reference()),
+            Some(Expression::MethodCall(call)) => self
+                .method(call.receiver, &call.name)
+                .is_some_and(|definition| definition.return_variable_type.is_reference()),
+            Some(Expression::Associated(associated)) => self
+                .ast
+                .find_method(&associated.ty.ty, &associated.name)
+                .is_some_and(|definition| definition.return_variable_type.is_reference()),
+            _ => false,
+        };
+
+        self.visit(id, node);
+
+        if !is_reference {
+            self.diagnostics.push(
+                Diagnostic::error("expected a reference, found a value".to_string())
+                    .at(self.location(id))
+                    .with_note("take a reference to it with `&`".to_string()),
+            );
+
+            return None;
+        }
+
+        self.target(id)
+    }
+
+    /// Whether `id` can be referenced, which only places can.
+    fn check_place(&mut self, id: ExpressionId, node: ExpressionId) -> bool {
+        match self.ast.find_expression(id) {
+            Some(Expression::Variable(variable)) => {
+                self.used(*variable, id);
+                true
+            }
+            Some(Expression::Field(field)) => self.check_place(field.base, node),
+            Some(Expression::Index(index)) => {
+                self.visit(index.index, node);
+                self.check_place(index.base, node)
+            }
+            Some(Expression::UnaryOperation(UnOp {
+                op: UnOpType::Dereference,
+                operand,
+            })) => {
+                self.visit(*operand, node);
+                true
+            }
+            _ => {
+                self.visit(id, node);
+
+                self.diagnostics.push(
+                    Diagnostic::error("this expression can't be referenced".to_string())
+                        .at(self.location(id))
+                        .with_note(
+                            "only variables, fields, elements and dereferences can be referenced"
+                                .to_string(),
+                        ),
+                );
+
+                false
+            }
+        }
+    }
+
+    /// Target of an already checked reference, `None` when it isn't tracked.
+    fn target(&self, id: ExpressionId) -> Option<Target> {
+        match self.ast.find_expression(id)? {
+            Expression::UnaryOperation(UnOp {
+                op: UnOpType::Reference,
+                operand,
+            }) => self.place_target(*operand),
+            Expression::Variable(variable) => self.targets.get(variable).cloned(),
+            expression @ (Expression::Call(call) | Expression::Foreign(call)) => {
+                let definition = self.ast.find_called(expression)?;
+                self.returned_target(definition, definition.args.iter().zip(&call.args))
+            }
+            Expression::Associated(associated) => {
+                let definition = self.ast.find_method(&associated.ty.ty, &associated.name)?;
+                self.returned_target(definition, definition.args.iter().zip(&associated.args))
+            }
+            Expression::MethodCall(call) => {
+                let definition = self.method(call.receiver, &call.name)?;
+                let returned = self
+                    .returned_target(definition, definition.args.iter().skip(1).zip(&call.args))?;
+
+                let borrows_receiver = definition
+                    .args
+                    .first()
+                    .and_then(|receiver| self.parameter(*receiver))
+                    .is_some_and(|receiver| receiver.variable_type.is_reference());
+
+                // The returned reference may point into a receiver that is borrowed.
+                match borrows_receiver.then(|| self.place_target(call.receiver)) {
+                    Some(Some(receiver)) => Some(self.shorter(receiver, returned)),
+                    _ => Some(returned),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Target of `&place`: the variable the place is in, or what that variable points to.
+    fn place_target(&self, id: ExpressionId) -> Option<Target> {
+        match self.ast.find_expression(id)? {
+            Expression::Variable(variable) => match self.targets.get(variable) {
+                Some(target) => Some(target.clone()),
+                None => Some(Target {
+                    name: self.variable(*variable).name.clone(),
+                    owner: self.owners.get(variable).copied(),
+                    variable: Some(*variable),
+                }),
+            },
+            Expression::Field(field) => self.place_target(field.base),
+            Expression::Index(index) => {
+                match typecheck::infer(self.ast, self.signature, index.base) {
+                    Type::Pointer(_) => None,
+                    _ => self.place_target(index.base),
+                }
+            }
+            // Pointers don't take part in ownership, what they point to isn't tracked.
+            _ => None,
+        }
+    }
+
+    /// Target of a call returning a reference, which may point into any referenced argument.
+    fn returned_target<'b>(
+        &self,
+        definition: &DefinitionSignature,
+        pairs: impl Iterator<Item = (&'b VariableId, &'b ExpressionId)>,
+    ) -> Option<Target> {
+        if !definition.return_variable_type.is_reference() {
+            return None;
+        }
+
+        let mut result: Option<Target> = None;
+
+        for (parameter, argument) in pairs {
+            let takes_reference = self
+                .parameter(*parameter)
+                .is_some_and(|parameter| parameter.variable_type.is_reference());
+
+            let Some(target) = takes_reference.then(|| self.target(*argument)).flatten() else {
+                continue;
+            };
+
+            result = Some(match result {
+                Some(current) => self.shorter(current, target),
+                None => target,
+            });
+        }
+
+        // Without referenced arguments it can only return what its own caller gave it.
+        result.or_else(|| Some(Target::caller(definition.name.clone())))
+    }
+
+    /// The target dropped first of two, both owned by nodes on the same path.
+    fn shorter(&self, left: Target, right: Target) -> Target {
+        match right
+            .owner
+            .is_some_and(|owner| self.outlives(left.owner, owner))
+        {
+            true => right,
+            false => left,
+        }
+    }
+}
@@ -0,0 +1,892 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    diagnostic::{Diagnostic, Location},
+    parser::ast::{
+        Ast, DefinitionOwner, DefinitionSignature, Expression, ExpressionId, Generic, Number,
+        Package, TypeBody, TypeExpression, TypeReference, TypeSignature, Use, Variable, VariableId,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Namespace {
+    Type,
+    Definition,
+}
+
+impl Namespace {
+    fn describe(&self) -> &'static str {
+        match self {
+            Namespace::Type => "type",
+            Namespace::Definition => "definition",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Item {
+    pub namespace: Namespace,
+    pub owner: String,
+    pub name: String,
+}
+
+impl Item {
+    pub fn path(&self) -> String {
+        format!("{}::{}", self.owner, self.name)
+    }
+}
+
+/// Points every type and definition name of `ast` at the module declaring it.
+///
+/// Inside a module, generic parameters and `Self` come first, then the module's own
+/// items, then explicit `use` imports and finally `use ...::*` globs. Private items are
+/// only visible from their own module and the packages below it.
+pub fn resolve(ast: &mut Ast) -> Vec<Diagnostic> {
+    let mut resolver = Resolver::new(ast);
+
+    resolver.check_uses();
+    resolver.resolve_aliases(ast);
+    resolver.resolve_types(ast);
+    resolver.resolve_implementations(ast);
+    resolver.resolve_foreign(ast);
+    resolver.resolve_definitions(ast);
+    resolver.check_traits(ast);
+
+    resolver.diagnostics
+}
+
+/// Levenshtein distance between `a` and `b`, used to suggest near-miss names.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Where a name is being resolved from.
+#[derive(Debug, Default, Clone)]
+struct Context {
+    module: String,
+    parameters: Vec<String>,
+    self_type: Option<(TypeSignature, Vec<String>)>,
+    location: Option<Location>,
+}
+
+struct Resolver {
+    items: BTreeMap<String, Vec<Item>>,
+    public_items: BTreeSet<String>,
+    packages: BTreeMap<String, Vec<Package>>,
+    parents: BTreeMap<String, String>,
+    uses: BTreeMap<String, Vec<Use>>,
+    /// The type each alias names, after following aliases of aliases.
+    aliases: BTreeMap<TypeSignature, TypeSignature>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    fn new(ast: &Ast) -> Self {
+        let mut items = BTreeMap::<String, Vec<Item>>::new();
+
+        for signature in ast.types.keys() {
+            items
+                .entry(signature.owner.clone())
+                .or_default()
+                .push(Item {
+                    namespace: Namespace::Type,
+                    owner: signature.owner.clone(),
+                    name: signature.name.clone(),
+                });
+        }
+
+        for signature in ast.definitions.keys() {
+            if let DefinitionOwner::Path(path) = &signature.owner {
+                items.entry(path.clone()).or_default().push(Item {
+                    namespace: Namespace::Definition,
+                    owner: path.clone(),
+                    name: signature.name.clone(),
+                });
+            }
+        }
+
+        let mut parents = BTreeMap::new();
+
+        // The standard library is its own root, `library::` inside it stays inside it.
+        for (module, packages) in &ast.packages {
+            for package in packages.iter().filter(|package| !package.library) {
+                parents
+                    .entry(package.module.clone())
+                    .or_insert_with(|| module.clone());
+            }
+        }
+
+        Self {
+            items,
+            public_items: ast.public_items.clone(),
+            packages: ast.packages.clone(),
+            parents,
+            uses: ast.uses.clone(),
+            aliases: BTreeMap::new(),
+            diagnostics: vec![],
+        }
+    }
+
+    fn is_descendant(&self, module: &str, ancestor: &str) -> bool {
+        let mut current = module;
+
+        loop {
+            if current == ancestor {
+                return true;
+            }
+
+            match self.parents.get(current) {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+    }
+
+    fn root<'a>(&'a self, module: &'a str) -> &'a str {
+        let mut current = module;
+
+        while let Some(parent) = self.parents.get(current) {
+            current = parent;
+        }
+
+        current
+    }
+
+    fn is_accessible(&self, item: &Item, from: &str) -> bool {
+        self.public_items.contains(&item.path()) || self.is_descendant(from, &item.owner)
+    }
+
+    fn resolve_module(&self, from: &str, segments: &[String]) -> Result<String, Diagnostic> {
+        let mut current = from.to_string();
+
+        for (i, segment) in segments.iter().enumerate() {
+            if i == 0 && segment == "library" {
+                current = self.root(from).to_string();
+                continue;
+            }
+
+            let packages = self.packages.get(&current).cloned().unwrap_or_default();
+
+            match packages.iter().find(|package| package.name == *segment) {
+                Some(package) if package.public || self.is_descendant(from, &current) => {
+                    current = package.module.clone();
+                }
+                Some(_) => {
+                    return Err(Diagnostic::error(format!(
+                        "package `{}` is private to `{}`",
+                        segment, current
+                    )));
+                }
+                None => {
+                    let names = packages.iter().map(|package| package.name.as_str());
+
+                    return Err(with_suggestion(
+                        Diagnostic::error(format!(
+                            "cannot find package `{}` in `{}`",
+                            segment, current
+                        )),
+                        segment,
+                        names,
+                    ));
+                }
+            }
+        }
+
+        Ok(current)
+    }
+
+    /// Items of `module` that `from` can see, including the ones `module` re-exports.
+    fn exposed(&self, module: &str, from: &str, visiting: &mut BTreeSet<String>) -> Vec<Item> {
+        let mut result = self
+            .items
+            .get(module)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter(|item| self.is_accessible(item, from))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        if !visiting.insert(module.to_string()) {
+            return result;
+        }
+
+        for import in self.uses.get(module).into_iter().flatten() {
+            if import.public {
+                result.extend(self.imported(module, import, visiting));
+            }
+        }
+
+        visiting.remove(module);
+
+        result
+    }
+
+    /// Items brought into `module` by `import`.
+    fn imported(&self, module: &str, import: &Use, visiting: &mut BTreeSet<String>) -> Vec<Item> {
+        if import.glob {
+            return match self.resolve_module(module, &import.path) {
+                Ok(target) => self.exposed(&target, module, visiting),
+                Err(_) => vec![],
+            };
+        }
+
+        let (name, path) = import.path.split_last().unwrap();
+
+        match self.resolve_module(module, path) {
+            Ok(target) => self
+                .exposed(&target, module, visiting)
+                .into_iter()
+                .filter(|item| item.name == *name)
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn check_uses(&mut self) {
+        let mut diagnostics = vec![];
+
+        for (module, imports) in &self.uses {
+            for import in imports {
+                let path = match import.glob {
+                    true => &import.path[..],
+                    false => &import.path[..import.path.len() - 1],
+                };
+
+                let error = match self.resolve_module(module, path) {
+                    Err(error) => Some(error),
+                    Ok(_) if import.glob => None,
+                    Ok(target) => {
+                        let name = import.path.last().unwrap();
+
+                        if !self
+                            .imported(module, import, &mut BTreeSet::new())
+                            .is_empty()
+                        {
+                            None
+                        } else if self
+                            .items
+                            .get(&target)
+                            .is_some_and(|items| items.iter().any(|item| item.name == *name))
+                        {
+                            Some(Diagnostic::error(format!(
+                                "`{}` is private to `{}`",
+                                name, target
+                            )))
+                        } else {
+                            let names = self
+                                .exposed(&target, module, &mut BTreeSet::new())
+                                .into_iter()
+                                .map(|item| item.name);
+
+                            Some(with_suggestion(
+                                Diagnostic::error(format!(
+                                    "unresolved import `{}`",
+                                    import.path.join("::")
+                                )),
+                                name,
+                                names,
+                            ))
+                        }
+                    }
+                };
+
+                if let Some(error) = error {
+                    diagnostics.push(error.at(Some(import.location.clone())));
+                }
+            }
+        }
+
+        self.diagnostics.extend(diagnostics);
+    }
+
+    fn lookup(
+        &self,
+        namespace: Namespace,
+        name: &str,
+        context: &Context,
+    ) -> Result<Item, Diagnostic> {
+        let matches = |items: Vec<Item>| {
+            items
+                .into_iter()
+                .filter(|item| item.namespace == namespace && item.name == name)
+                .collect::<BTreeSet<_>>()
+        };
+
+        let module = context.module.as_str();
+        let imports = self.uses.get(module).cloned().unwrap_or_default();
+
+        let local = matches(self.items.get(module).cloned().unwrap_or_default());
+
+        let explicit = matches(
+            imports
+                .iter()
+                .filter(|import| !import.glob && import.path.last().unwrap() == name)
+                .flat_map(|import| self.imported(module, import, &mut BTreeSet::new()))
+                .collect(),
+        );
+
+        let glob = matches(
+            imports
+                .iter()
+                .filter(|import| import.glob)
+                .flat_map(|import| self.imported(module, import, &mut BTreeSet::new()))
+                .collect(),
+        );
+
+        for candidates in [local, explicit, glob] {
+            match candidates.len() {
+                0 => continue,
+                1 => return Ok(candidates.into_iter().next().unwrap()),
+                _ => {
+                    let mut error = Diagnostic::error(format!("`{}` is ambiguous", name));
+
+                    for candidate in candidates {
+                        error = error.with_note(format!("it may refer to `{}`", candidate.path()));
+                    }
+
+                    return Err(error);
+                }
+            }
+        }
+
+        Err(self.not_found(namespace, name, context))
+    }
+
+    fn not_found(&self, namespace: Namespace, name: &str, context: &Context) -> Diagnostic {
+        let elsewhere = self
+            .items
+            .values()
+            .flatten()
+            .find(|item| item.namespace == namespace && item.name == name);
+
+        if let Some(item) = elsewhere {
+            if !self.is_accessible(item, &context.module) {
+                return Diagnostic::error(format!("`{}` is private to `{}`", name, item.owner));
+            }
+
+            return Diagnostic::error(format!(
+                "cannot find {} `{}` in this scope",
+                namespace.describe(),
+                name
+            ))
+            .with_note(format!("`{}` exists but isn't imported", item.path()));
+        }
+
+        let module = context.module.as_str();
+
+        let mut candidates = self.items.get(module).cloned().unwrap_or_default();
+
+        for import in self.uses.get(module).into_iter().flatten() {
+            candidates.extend(self.imported(module, import, &mut BTreeSet::new()));
+        }
+
+        let names = candidates
+            .into_iter()
+            .filter(|item| item.namespace == namespace)
+            .map(|item| item.name)
+            .chain(context.parameters.iter().cloned());
+
+        with_suggestion(
+            Diagnostic::error(format!(
+                "cannot find {} `{}` in this scope",
+                namespace.describe(),
+                name
+            )),
+            name,
+            names,
+        )
+    }
+
+    fn report(&mut self, error: Diagnostic, context: &Context) {
+        self.diagnostics.push(error.at(context.location.clone()));
+    }
+
+    fn resolve_type_name(&mut self, name: &str, context: &Context) -> Option<TypeSignature> {
+        if context.parameters.iter().any(|parameter| parameter == name) {
+            return None;
+        }
+
+        if name == "Self" {
+            match &context.self_type {
+                Some((ty, _)) => return Some(ty.clone()),
+                None => {
+                    self.report(
+                        Diagnostic::error("`Self` is only available inside types".to_string()),
+                        context,
+                    );
+                    return None;
+                }
+            }
+        }
+
+        match self.lookup(Namespace::Type, name, context) {
+            Ok(item) => {
+                let signature = TypeSignature {
+                    owner: item.owner,
+                    name: item.name,
+                };
+
+                Some(self.aliases.get(&signature).cloned().unwrap_or(signature))
+            }
+            Err(error) => {
+                self.report(error, context);
+                None
+            }
+        }
+    }
+
+    fn resolve_generic(&mut self, generic: &mut Generic, context: &Context) {
+        // Like in `Forget<Self>(self)`.
+        if generic.name == "Self" && generic.generics.is_empty() {
+            generic.generics = self_generics(context);
+        }
+
+        if let Some(signature) = self.resolve_type_name(&generic.name, context) {
+            generic.owner = signature.owner;
+            generic.name = signature.name;
+        }
+
+        for argument in &mut generic.generics {
+            self.resolve_generic(argument, context);
+        }
+    }
+
+    fn resolve_reference(&mut self, reference: &mut TypeReference, context: &Context) {
+        if reference.ty.name == "Self" && reference.generics.is_empty() {
+            reference.generics = self_generics(context);
+        }
+
+        if let Some(signature) = self.resolve_type_name(&reference.ty.name, context) {
+            reference.ty = signature;
+        }
+
+        for argument in &mut reference.generics {
+            self.resolve_generic(argument, context);
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &mut TypeExpression, context: &Context) {
+        for reference in expression.references_mut() {
+            self.resolve_reference(reference, context);
+        }
+    }
+
+    /// Resolves the types named by aliases, then removes the aliases: the names resolved
+    /// afterwards point at the types themselves.
+    fn resolve_aliases(&mut self, ast: &mut Ast) {
+        let mut targets = BTreeMap::new();
+
+        for (signature, ty) in &ast.types {
+            let TypeBody::Alias(target) = &ty.ty else {
+                continue;
+            };
+
+            let context = Context {
+                module: signature.owner.clone(),
+                location: ast
+                    .item_locations
+                    .get(&format!("{}::{}", signature.owner, signature.name))
+                    .cloned(),
+                ..Default::default()
+            };
+
+            let Some(resolved) = self.resolve_type_name(&target.ty.name, &context) else {
+                continue;
+            };
+
+            let generic = ast
+                .types
+                .get(&resolved)
+                .is_some_and(|ty| !ty.generics.is_empty());
+
+            if generic || !ty.generics.is_empty() || !target.generics.is_empty() {
+                self.report(
+                    Diagnostic::error(format!(
+                        "`{}` can only be another name for a type without generics",
+                        signature.name
+                    )),
+                    &context,
+                );
+                continue;
+            }
+
+            targets.insert(signature.clone(), (resolved, context));
+        }
+
+        for (signature, (target, context)) in &targets {
+            let mut seen = BTreeSet::from([signature]);
+            let mut target = target;
+
+            while let Some((next, _)) = targets.get(target) {
+                if !seen.insert(target) {
+                    break;
+                }
+
+                target = next;
+            }
+
+            if seen.contains(target) {
+                self.report(
+                    Diagnostic::error(format!("`{}` names a cycle of aliases", signature.name)),
+                    context,
+                );
+                continue;
+            }
+
+            self.aliases.insert(signature.clone(), target.clone());
+        }
+
+        ast.types
+            .retain(|_, ty| !matches!(ty.ty, TypeBody::Alias(_)));
+    }
+
+    fn resolve_types(&mut self, ast: &mut Ast) {
+        let mut bounds = vec![];
+
+        for (signature, ty) in ast.types.iter_mut() {
+            let parameters = ty
+                .generics
+                .iter()
+                .map(|generic| generic.name.clone())
+                .collect::<Vec<_>>();
+
+            let context = Context {
+                module: signature.owner.clone(),
+                parameters: parameters.clone(),
+                self_type: Some((signature.clone(), parameters)),
+                location: ast
+                    .item_locations
+                    .get(&format!("{}::{}", signature.owner, signature.name))
+                    .cloned(),
+            };
+
+            match &mut ty.ty {
+                TypeBody::Enum(variants) => {
+                    for field in variants.iter_mut().flat_map(|variant| &mut variant.fields) {
+                        self.resolve_expression(field, &context);
+                    }
+                }
+                TypeBody::Range(range) => bounds.push((vec![range.min, range.max], context)),
+                TypeBody::Fixed(fixed) => {
+                    bounds.push((vec![fixed.delta, fixed.range.min, fixed.range.max], context))
+                }
+                TypeBody::Float(digits) => bounds.push((vec![*digits], context)),
+                TypeBody::Alias(_) | TypeBody::Unknown => {}
+            }
+        }
+
+        // Bounds can name other types, like `range 1..UInteger::Max()`.
+        for (ids, mut context) in bounds {
+            for id in ids {
+                self.resolve_names(ast, id, &mut context);
+            }
+        }
+    }
+
+    /// Implementations name their type from the file they're written in.
+    ///
+    /// Unknown types aren't reported here, the definitions of the implementation report them.
+    fn resolve_implementations(&mut self, ast: &mut Ast) {
+        for (ty, traits) in std::mem::take(&mut ast.implementations) {
+            let context = Context {
+                module: ty.owner.clone(),
+                ..Default::default()
+            };
+
+            let ty = match self.lookup(Namespace::Type, &ty.name, &context) {
+                Ok(item) => TypeSignature {
+                    owner: item.owner,
+                    name: item.name,
+                },
+                Err(_) => ty,
+            };
+
+            ast.implementations.entry(ty).or_default().extend(traits);
+        }
+    }
+
+    fn definition_context(&self, ast: &Ast, signature: &DefinitionSignature) -> Context {
+        let (module, item, self_type) = match &signature.owner {
+            DefinitionOwner::Path(path) => (path.clone(), signature.name.clone(), None),
+            DefinitionOwner::Type(ty) => (
+                ty.owner.clone(),
+                format!("{}::{}", ty.name, signature.name),
+                Some((ty.clone(), vec![])),
+            ),
+            DefinitionOwner::Unknown => (String::new(), signature.name.clone(), None),
+        };
+
+        Context {
+            location: ast
+                .item_locations
+                .get(&format!("{}::{}", module, item))
+                .cloned(),
+            module,
+            parameters: signature
+                .generics
+                .iter()
+                .map(|generic| generic.name.clone())
+                .collect(),
+            self_type,
+        }
+    }
+
+    /// Resolves the return type of `signature` and the types of its variables.
+    fn resolve_signature(
+        &mut self,
+        ast: &Ast,
+        signature: &mut DefinitionSignature,
+        variables: &mut BTreeMap<VariableId, Variable>,
+        context: &mut Context,
+    ) {
+        if let Some(return_type) = &mut signature.return_type {
+            self.resolve_expression(return_type, context);
+        }
+
+        for (id, variable) in variables.iter_mut() {
+            // Pattern bindings take the type of the field they bind.
+            if variable.ty.is_inferred() {
+                continue;
+            }
+
+            context.location = ast.find_variable_location(*id);
+
+            self.resolve_expression(&mut variable.ty, context);
+        }
+    }
+
+    /// C functions are resolved from the module declaring them, like its definitions.
+    fn resolve_foreign(&mut self, ast: &mut Ast) {
+        for (name, mut signature) in std::mem::take(&mut ast.foreign) {
+            let mut context = self.definition_context(ast, &signature);
+            let mut variables = ast.variables.remove(&signature).unwrap_or_default();
+
+            self.resolve_signature(ast, &mut signature, &mut variables, &mut context);
+
+            if !variables.is_empty() {
+                ast.variables.insert(signature.clone(), variables);
+            }
+
+            ast.foreign.insert(name, signature);
+        }
+    }
+
+    /// Types implementing a trait must define everything the trait declares.
+    ///
+    /// Traits are matched by name, like `Copy` and `Drop`, so the ones the compiler knows
+    /// about don't need a declaration.
+    fn check_traits(&mut self, ast: &Ast) {
+        for (ty, traits) in &ast.implementations {
+            for name in traits {
+                let declared = ast
+                    .traits
+                    .iter()
+                    .find(|(path, _)| path.rsplit("::").next() == Some(name.as_str()))
+                    .map(|(_, declared)| declared);
+
+                for method in declared.into_iter().flatten() {
+                    if ast.find_method(ty, method).is_some() {
+                        continue;
+                    }
+
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "`{}` implements `{}` without its `{}`",
+                            ty.name, name, method
+                        ))
+                        .at(ast
+                            .item_locations
+                            .get(&format!("{}::{}", ty.owner, ty.name))
+                            .cloned()),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Resolves the definitions and types named inside the expression `id`.
+    fn resolve_names(&mut self, ast: &mut Ast, id: ExpressionId, context: &mut Context) {
+        let mut references = vec![];
+        collect_references(ast, id, &mut references);
+
+        for id in references {
+            context.location = ast.find_location(id);
+
+            let expression = match ast.find_expression(id).cloned() {
+                Some(Expression::Call(mut call)) => {
+                    match self.lookup(Namespace::Definition, &call.name, context) {
+                        Ok(item) => call.owner = item.owner,
+                        Err(error) => self.report(error, context),
+                    }
+
+                    for generic in &mut call.generics {
+                        self.resolve_generic(generic, context);
+                    }
+
+                    Expression::Call(call)
+                }
+                Some(Expression::Conversion(mut conversion)) => {
+                    if let Some(ty) = self.resolve_type_name(&conversion.ty.name, context) {
+                        conversion.ty = ty;
+                    }
+
+                    Expression::Conversion(conversion)
+                }
+                Some(Expression::Number(mut number)) => {
+                    if let Some(suffix) = &mut number.suffix
+                        && let Some(ty) = self.resolve_type_name(&suffix.name, context)
+                    {
+                        *suffix = ty;
+                    }
+
+                    Expression::Number(number)
+                }
+                Some(Expression::MethodCall(mut call)) => {
+                    for generic in &mut call.generics {
+                        self.resolve_generic(generic, context);
+                    }
+
+                    Expression::MethodCall(call)
+                }
+                Some(Expression::Associated(mut associated)) => {
+                    self.resolve_reference(&mut associated.ty, context);
+
+                    for generic in &mut associated.generics {
+                        self.resolve_generic(generic, context);
+                    }
+
+                    Expression::Associated(associated)
+                }
+                Some(Expression::Cast(mut cast)) => {
+                    self.resolve_expression(&mut cast.ty, context);
+
+                    Expression::Cast(cast)
+                }
+                Some(Expression::SizeOf(mut ty)) => {
+                    self.resolve_expression(&mut ty, context);
+
+                    Expression::SizeOf(ty)
+                }
+                _ => continue,
+            };
+
+            *ast.find_mut_expression(id).unwrap() = expression;
+        }
+    }
+
+    fn resolve_definitions(&mut self, ast: &mut Ast) {
+        let definitions = std::mem::take(&mut ast.definitions);
+        let mut variables = std::mem::take(&mut ast.variables);
+
+        // The owner and return type are part of the signature, so the definition and its
+        // variables are inserted again under the resolved one.
+        for (mut signature, body) in definitions {
+            let mut context = self.definition_context(ast, &signature);
+            let mut definition_variables = variables.remove(&signature).unwrap_or_default();
+
+            // Implementations name their type from the file they're written in.
+            if let DefinitionOwner::Type(ty) = &signature.owner
+                && let Some(resolved) = self.resolve_type_name(&ty.name, &context)
+            {
+                // `Self` takes the generics of the implementation, which come first.
+                let count = ast
+                    .types
+                    .get(&resolved)
+                    .map_or(0, |rosario_type| rosario_type.generics.len());
+                let parameters = context.parameters.iter().take(count).cloned().collect();
+
+                context.self_type = Some((resolved.clone(), parameters));
+                signature.owner = DefinitionOwner::Type(resolved);
+            }
+
+            self.resolve_signature(ast, &mut signature, &mut definition_variables, &mut context);
+
+            if !definition_variables.is_empty() {
+                ast.variables
+                    .insert(signature.clone(), definition_variables);
+            }
+
+            self.resolve_names(ast, body, &mut context);
+
+            ast.definitions.insert(signature, body);
+        }
+
+        ast.variables.extend(variables);
+    }
+}
+
+/// The generics `Self` stands for with `context`, the parameters of the implementation.
+fn self_generics(context: &Context) -> Vec<Generic> {
+    let parameters = context
+        .self_type
+        .iter()
+        .flat_map(|(_, parameters)| parameters);
+
+    parameters
+        .map(|parameter| Generic {
+            name: parameter.clone(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Collects the expressions naming definitions and types, like calls and conversions.
+fn collect_references(ast: &Ast, id: ExpressionId, references: &mut Vec<ExpressionId>) {
+    if matches!(
+        ast.find_expression(id),
+        Some(
+            Expression::Call(_)
+                | Expression::MethodCall(_)
+                | Expression::Conversion(_)
+                | Expression::Associated(_)
+                | Expression::Cast(_)
+                | Expression::SizeOf(_)
+                | Expression::Number(Number {
+                    suffix: Some(_),
+                    ..
+                })
+        )
+    ) {
+        references.push(id);
+    }
+
+    for child in ast.children(id) {
+        collect_references(ast, child, references);
+    }
+}
+
+fn with_suggestion<'a>(
+    error: Diagnostic,
+    name: &str,
+    candidates: impl Iterator<Item = impl AsRef<str> + 'a>,
+) -> Diagnostic {
+    let best = candidates
+        .map(|candidate| {
+            let candidate = candidate.as_ref().to_string();
+            (edit_distance(name, &candidate), candidate)
+        })
+        .filter(|(distance, _)| *distance <= (name.len() / 3).max(1))
+        .min();
+
+    match best {
+        Some((_, candidate)) => error.with_note(format!("did you mean `{}`?", candidate)),
+        None => error,
+    }
+}
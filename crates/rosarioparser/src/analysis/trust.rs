@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    diagnostic::Diagnostic,
+    parser::ast::{Ast, UnsafeCode, UnsafeKind},
+};
+
+/// Rejects raw pointers, their dereferences and `C_LANG` outside of trusted packages.
+///
+/// The compiler can't check that they're used safely, so a package has to be declared with
+/// `trusted package name;` to use them. The main file is never trusted.
+pub fn check_trust(ast: &Ast) -> Vec<Diagnostic> {
+    let trusted = ast.trusted_modules();
+
+    ast.unsafe_code
+        .iter()
+        .filter(|code| !trusted.contains(code.module.as_str()))
+        .map(|code| {
+            let message = match code.kind {
+                UnsafeKind::Pointer => "raw pointers can only be used in trusted packages",
+                UnsafeKind::Dereference => {
+                    "raw pointers can only be dereferenced in trusted packages"
+                }
+                UnsafeKind::Foreign
+                | UnsafeKind::ForeignDeclaration
+                | UnsafeKind::ForeignInclude => "`C_LANG` can only be used in trusted packages",
+            };
+
+            Diagnostic::error(message.to_string())
+                .at(Some(code.location.clone()))
+                .with_note(untrusted_note(ast, &code.module))
+        })
+        .collect()
+}
+
+/// Explains how the untrusted `module` could be trusted, if it can be.
+pub fn untrusted_note(ast: &Ast, module: &str) -> String {
+    let declaration = ast
+        .packages
+        .values()
+        .flatten()
+        .find(|package| package.module == module);
+
+    match declaration {
+        Some(package) => format!(
+            "`{}` isn't trusted, it can be declared with `trusted package {};`",
+            module, package.name
+        ),
+        None => format!(
+            "`{}` is the main file, only packages can be trusted",
+            module
+        ),
+    }
+}
+
+/// Every trusted package of a build with its unsafe code, so audits know what to read.
+///
+/// Trusted packages without any unsafe code are listed too, they don't need the trust.
+pub fn trusted_code(ast: &Ast) -> BTreeMap<&str, Vec<&UnsafeCode>> {
+    let mut result = ast
+        .trusted_modules()
+        .into_iter()
+        .map(|module| (module, vec![]))
+        .collect::<BTreeMap<_, _>>();
+
+    for code in &ast.unsafe_code {
+        if let Some(uses) = result.get_mut(code.module.as_str()) {
+            uses.push(code);
+        }
+    }
+
+    result
+}
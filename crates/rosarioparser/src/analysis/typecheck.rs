@@ -0,0 +1,1675 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use crate::{
+    analysis::trust::untrusted_note,
+    diagnostic::Diagnostic,
+    parser::ast::{
+        Assignment, Associated, Ast, BinOp, BinOpType, Call, Cast, Conversion, DefinitionOwner,
+        DefinitionSignature, EnumVariant, Expression, ExpressionId, For, Index, Match, MethodCall,
+        Number, Pattern, Range, TypeBody, TypeExpression, TypeSignature, UnOpType, Variable,
+        VariableId,
+    },
+    parser::number::Exact,
+};
+
+/// C functions that stop the program, so calling them ends every path.
+const NEVER_RETURNING: [&str; 3] = ["abort", "exit", "_Exit"];
+
+/// Type of an expression as seen by the type checker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    /// A constant integer, which fits any range type holding its value.
+    Literal(i128),
+    Decimal,
+    Named(TypeSignature),
+    /// A raw pointer to a value of the type.
+    Pointer(Box<Type>),
+    /// An `array<R of T>`, kept as written since its length comes from `R`.
+    Array(TypeExpression),
+    /// A `size_of T`, which fits any range type.
+    Size,
+    /// The result of a comparison, which `if` branches on.
+    Condition,
+    /// Statements and calls to definitions without a return type.
+    Nothing,
+    /// Already reported, checking stops here.
+    Unknown,
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Literal(value) => write!(f, "the integer `{}`", value),
+            Type::Decimal => write!(f, "a decimal number"),
+            Type::Named(ty) => write!(f, "`{}`", ty.name),
+            Type::Pointer(ty) => write!(f, "a pointer to {}", ty),
+            Type::Array(ty) => write!(f, "`{}`", ty),
+            Type::Size => write!(f, "a size"),
+            Type::Condition => write!(f, "a condition"),
+            Type::Nothing => write!(f, "nothing"),
+            Type::Unknown => write!(f, "an unknown type"),
+        }
+    }
+}
+
+impl Type {
+    /// Type of the values of a written type.
+    pub fn of(ty: &TypeExpression) -> Self {
+        match ty {
+            TypeExpression::Named(reference) => Type::Named(reference.ty.clone()),
+            TypeExpression::Pointer(ty) => Type::Pointer(Box::new(Type::of(ty))),
+            TypeExpression::Reference(ty) | TypeExpression::MutableReference(ty) => Type::of(ty),
+            TypeExpression::Array { .. } => Type::Array(ty.clone()),
+        }
+    }
+}
+
+/// Checks the types of every definition of a monomorphized `ast`.
+///
+/// Integer literals take the type they're used as and must fit in its range, while two
+/// different range types can't be mixed in the same operation.
+pub fn typecheck(ast: &Ast) -> Vec<Diagnostic> {
+    let mut checker = TypeChecker::new(ast, None);
+
+    for (signature, ty) in &ast.types {
+        match &ty.ty {
+            TypeBody::Range(range) => checker.check_range(signature, range),
+            TypeBody::Fixed(fixed) => checker.check_fixed(signature, &fixed.range),
+            _ => {}
+        }
+    }
+
+    for (signature, body) in &ast.definitions {
+        checker.check_definition(signature, *body);
+    }
+
+    checker.diagnostics
+}
+
+/// The array type each `[a, b, ...]` literal of a monomorphized `ast` is used as.
+pub fn array_literals(ast: &Ast) -> BTreeMap<ExpressionId, TypeExpression> {
+    let mut checker = TypeChecker::new(ast, None);
+
+    for (signature, body) in &ast.definitions {
+        checker.check_definition(signature, *body);
+    }
+
+    checker.array_literals
+}
+
+/// Type of the expression `id` inside the definition `signature`, without reporting errors.
+pub fn infer(ast: &Ast, signature: &DefinitionSignature, id: ExpressionId) -> Type {
+    let mut checker = TypeChecker::new(ast, Some(signature));
+
+    // Pattern bindings only get their types when the whole body is checked.
+    if let Some(body) = ast.definitions.get(signature) {
+        checker.check_definition(signature, *body);
+    }
+
+    checker.infer(id)
+}
+
+/// Inclusive bounds of the range type `ty`, `None` if it isn't a constant range.
+pub fn range_bounds(ast: &Ast, ty: &TypeSignature) -> Option<(i128, i128)> {
+    match &ast.types.get(ty)?.ty {
+        TypeBody::Range(range) => ast.evaluate(range.min).zip(ast.evaluate(range.max)),
+        _ => None,
+    }
+}
+
+/// Bits of the C floating-point type holding `digits` significant decimal digits.
+pub fn float_bits(digits: i128) -> Option<usize> {
+    match digits {
+        1..=6 => Some(32),
+        7..=15 => Some(64),
+        16..=18 => Some(80),
+        _ => None,
+    }
+}
+
+/// Significant decimal digits of the floating-point type `ty`, `None` if it isn't one.
+pub fn float_digits(ast: &Ast, ty: &TypeSignature) -> Option<i128> {
+    match &ast.types.get(ty)?.ty {
+        TypeBody::Float(digits) => ast.evaluate(*digits),
+        _ => None,
+    }
+}
+
+/// The delta of the fixed-point type `ty` and its inclusive bounds as multiples of it,
+/// `None` if it isn't a fixed-point type with a positive constant delta and constant bounds.
+pub fn fixed_bounds(ast: &Ast, ty: &TypeSignature) -> Option<(Exact, i128, i128)> {
+    let TypeBody::Fixed(fixed) = &ast.types.get(ty)?.ty else {
+        return None;
+    };
+
+    let (false, delta) = ast.evaluate_real(fixed.delta)? else {
+        return None;
+    };
+
+    // Bounds that aren't multiples of the delta are rounded toward the inside, which is
+    // away from zero when the counts were truncated toward it.
+    let min = ast.evaluate_real(fixed.range.min)?;
+    let (count, exact) = multiple(&min, &delta)?;
+    let min = if exact || min.0 { count } else { count + 1 };
+
+    let max = ast.evaluate_real(fixed.range.max)?;
+    let (count, exact) = multiple(&max, &delta)?;
+    let max = if exact || !max.0 { count } else { count - 1 };
+
+    Some((delta, min, max))
+}
+
+/// How many `delta`s make the signed `value`, rounded toward zero, and whether it's exact.
+fn multiple((negative, value): &(bool, Exact), delta: &Exact) -> Option<(i128, bool)> {
+    let (count, exact) = value.divide(delta)?;
+
+    Some((if *negative { -count } else { count }, exact))
+}
+
+/// How an expression of a fixed-point type is lowered, since its values are stored as
+/// multiples of the type's delta.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scaling {
+    /// A constant, stored as this many deltas.
+    Constant(i128),
+    /// `left * right` of two values of the type, which is `left * right * delta` deltas.
+    Product(TypeSignature),
+    /// `left / right` of two values of the type, which is `left / right / delta` deltas.
+    Quotient(TypeSignature),
+}
+
+/// How each constant and product of fixed-point values of a monomorphized `ast` is scaled.
+pub fn scalings(ast: &Ast) -> BTreeMap<ExpressionId, Scaling> {
+    let mut checker = TypeChecker::new(ast, None);
+
+    for (signature, body) in &ast.definitions {
+        checker.check_definition(signature, *body);
+    }
+
+    checker.scalings
+}
+
+/// Number of elements of an `array<R of T>`, one for each value of `R`.
+pub fn array_length(ast: &Ast, ty: &TypeExpression) -> Option<i128> {
+    let TypeExpression::Array { index, .. } = ty else {
+        return None;
+    };
+
+    let (min, max) = range_bounds(ast, index.signature()?)?;
+    Some(max - min + 1)
+}
+
+/// Why the constant expression `id` doesn't fold: it isn't constant, divides by zero or
+/// overflows.
+fn folding_error(ast: &Ast, id: ExpressionId) -> &'static str {
+    match ast.find_expression(id) {
+        Some(Expression::Number(number)) if !number.real && number.suffix.is_none() => "overflows",
+        Some(Expression::UnaryOperation(un_op)) if ast.evaluate(un_op.operand).is_none() => {
+            folding_error(ast, un_op.operand)
+        }
+        Some(Expression::UnaryOperation(_)) => "overflows",
+        Some(Expression::BinaryOperation(bin_op)) => {
+            match (ast.evaluate(bin_op.left), ast.evaluate(bin_op.right)) {
+                (None, _) => folding_error(ast, bin_op.left),
+                (_, None) => folding_error(ast, bin_op.right),
+                (_, Some(0)) if matches!(bin_op.op, BinOpType::Div | BinOpType::Mod) => {
+                    "divides by zero"
+                }
+                _ if bin_op.op.is_comparison() => "isn't constant",
+                _ => "overflows",
+            }
+        }
+        _ => "isn't constant",
+    }
+}
+
+struct TypeChecker<'a> {
+    ast: &'a Ast,
+    signature: Option<&'a DefinitionSignature>,
+    /// Types of the variables bound by `match` patterns and `for` loops, taken from the
+    /// variant's fields and the container's elements.
+    bindings: BTreeMap<VariableId, TypeExpression>,
+    /// The array type each `[a, b, ...]` literal was checked against.
+    array_literals: BTreeMap<ExpressionId, TypeExpression>,
+    scalings: BTreeMap<ExpressionId, Scaling>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> TypeChecker<'a> {
+    fn new(ast: &'a Ast, signature: Option<&'a DefinitionSignature>) -> Self {
+        Self {
+            ast,
+            signature,
+            bindings: BTreeMap::new(),
+            array_literals: BTreeMap::new(),
+            scalings: BTreeMap::new(),
+            diagnostics: vec![],
+        }
+    }
+
+    fn check_definition(&mut self, signature: &'a DefinitionSignature, body: ExpressionId) {
+        self.signature = Some(signature);
+
+        match (&signature.return_type, self.ast.find_expression(body)) {
+            (Some(ty), Some(expression))
+                if !matches!(expression, Expression::Body(_) | Expression::If(_)) =>
+            {
+                self.expect_expression(body, ty);
+            }
+            (return_type, _) => {
+                self.infer(body);
+
+                if let Some(ty) = return_type
+                    && self.reaches_end(body, &mut BTreeSet::new())
+                {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "`{}` must return a `{}` on every path",
+                            signature.name, ty
+                        ))
+                        .at(self.ast.find_location(body))
+                        .with_note(format!(
+                            "the end of `{}` can be reached without a `return`",
+                            signature.name
+                        )),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Whether running the statement `id` can go on after it, without a `return`, nor a
+    /// call stopping the program. `visiting` holds the bodies of the definitions called.
+    fn reaches_end(&self, id: ExpressionId, visiting: &mut BTreeSet<ExpressionId>) -> bool {
+        let expression = self.ast.find_expression(id);
+
+        match expression {
+            Some(Expression::Return(_)) => false,
+            Some(Expression::Body(body)) => body
+                .contents
+                .iter()
+                .all(|id| self.reaches_end(*id, visiting)),
+            Some(Expression::If(if_expression)) => match if_expression.otherwise {
+                Some(otherwise) => {
+                    self.reaches_end(otherwise, visiting)
+                        || if_expression
+                            .branches
+                            .iter()
+                            .any(|(_, body)| self.reaches_end(*body, visiting))
+                }
+                None => true,
+            },
+            // Matches are exhaustive, so one of the arms runs.
+            Some(Expression::Match(match_expression)) => match_expression
+                .arms
+                .iter()
+                .any(|arm| self.reaches_end(arm.body, visiting)),
+            // Without a condition, only a `return` leaves the loop.
+            Some(Expression::Loop(loop_expression)) => loop_expression.condition.is_some(),
+            Some(Expression::Foreign(call)) => !NEVER_RETURNING.contains(&call.name.as_str()),
+            Some(Expression::Call(_)) => {
+                let Some(definition) = expression.and_then(|call| self.ast.find_called(call))
+                else {
+                    return true;
+                };
+
+                // A definition stops the program when its end can't be reached and it has
+                // no `return`.
+                match self.ast.definitions.get(definition) {
+                    Some(body) if visiting.insert(*body) => {
+                        let reaches = self.has_return(*body) || self.reaches_end(*body, visiting);
+                        visiting.remove(body);
+                        reaches
+                    }
+                    _ => true,
+                }
+            }
+            _ => true,
+        }
+    }
+
+    fn has_return(&self, id: ExpressionId) -> bool {
+        matches!(self.ast.find_expression(id), Some(Expression::Return(_)))
+            || self
+                .ast
+                .children(id)
+                .into_iter()
+                .any(|child| self.has_return(child))
+    }
+
+    fn error(&mut self, message: String, id: ExpressionId) {
+        self.diagnostics
+            .push(Diagnostic::error(message).at(self.ast.find_location(id)));
+    }
+
+    fn variable(&self, id: VariableId) -> Option<&'a Variable> {
+        self.signature
+            .and_then(|signature| self.ast.variables.get(signature))
+            .and_then(|variables| variables.get(&id))
+            .or_else(|| self.ast.find_variable_by_id(id))
+    }
+
+    fn infer(&mut self, id: ExpressionId) -> Type {
+        let Some(expression) = self.ast.find_expression(id) else {
+            return Type::Unknown;
+        };
+
+        match expression {
+            Expression::None => Type::Nothing,
+            Expression::Number(number) => self.infer_number(id, number),
+            Expression::String(_) => match self.ast.static_string() {
+                Some(ty) => Type::Named(ty.clone()),
+                None => {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "string literals are `StaticString`s of the core library".to_string(),
+                        )
+                        .at(self.ast.find_location(id))
+                        .with_note("load it with `use core::*;`".to_string()),
+                    );
+                    Type::Unknown
+                }
+            },
+            Expression::UnaryOperation(un_op) => match (&un_op.op, self.infer(un_op.operand)) {
+                (UnOpType::Dereference, Type::Unknown) => Type::Unknown,
+                (UnOpType::Dereference, Type::Pointer(ty)) => *ty,
+                (UnOpType::Dereference, ty) => {
+                    self.error(
+                        format!("{ty} can't be dereferenced, it isn't a pointer"),
+                        id,
+                    );
+                    Type::Unknown
+                }
+                (UnOpType::Reference, ty) => ty,
+                (_, Type::Literal(_)) => self.constant(id),
+                (_, ty) => ty,
+            },
+            Expression::BinaryOperation(bin_op) => self.infer_binary_operation(id, bin_op),
+            Expression::Variable(variable) => match self.bindings.get(variable) {
+                Some(ty) => Type::of(ty),
+                None => match self
+                    .variable(*variable)
+                    .map(|variable| Type::of(&variable.ty))
+                {
+                    // Bindings of a pattern or a `for` that couldn't be checked.
+                    Some(Type::Named(ty)) if ty.name.is_empty() => Type::Unknown,
+                    Some(ty) => ty,
+                    None => Type::Unknown,
+                },
+            },
+            Expression::NewVariable(variable) => {
+                if let Some(variable) = self.variable(*variable)
+                    && let Some(initializer) = variable.initializer
+                {
+                    self.expect_expression(initializer, &variable.ty);
+                }
+
+                Type::Nothing
+            }
+            Expression::Call(call) => self.infer_call(id, call),
+            Expression::Foreign(call) => self.infer_foreign_call(id, call),
+            Expression::MethodCall(call) => self.infer_method_call(id, call),
+            Expression::Associated(associated) => self.infer_associated(id, associated),
+            Expression::Index(index) => self.infer_index(id, index),
+            Expression::Array(elements) => {
+                for element in elements {
+                    self.infer(*element);
+                }
+
+                self.error(
+                    "the type of an array literal must be known from where it's used".to_string(),
+                    id,
+                );
+
+                Type::Unknown
+            }
+            Expression::Field(field) => {
+                if let Type::Named(ty) = self.infer(field.base) {
+                    self.error(format!("`{}` has no field `{}`", ty.name, field.name), id);
+                }
+
+                Type::Unknown
+            }
+            Expression::Assignment(assignment) => {
+                self.check_assignment(assignment);
+                Type::Nothing
+            }
+            Expression::Conversion(conversion) => self.infer_conversion(id, conversion),
+            Expression::Cast(cast) => self.infer_cast(id, cast),
+            Expression::SizeOf(_) => Type::Size,
+            Expression::If(if_expression) => {
+                for (condition, body) in &if_expression.branches {
+                    self.check_condition(*condition);
+                    self.infer(*body);
+                }
+
+                if let Some(otherwise) = if_expression.otherwise {
+                    self.infer(otherwise);
+                }
+
+                Type::Nothing
+            }
+            Expression::Match(match_expression) => {
+                self.check_match(id, match_expression, None);
+                Type::Nothing
+            }
+            Expression::Loop(loop_expression) => {
+                if let Some(condition) = loop_expression.condition {
+                    self.check_condition(condition);
+                }
+
+                self.infer(loop_expression.body);
+                Type::Nothing
+            }
+            Expression::For(for_expression) => {
+                self.check_for(for_expression);
+                Type::Nothing
+            }
+            Expression::Return(value) => {
+                self.check_return(id, *value);
+                Type::Nothing
+            }
+            Expression::Body(body) => {
+                for content in &body.contents {
+                    self.infer(*content);
+                }
+
+                Type::Nothing
+            }
+        }
+    }
+
+    fn infer_number(&mut self, id: ExpressionId, number: &Number) -> Type {
+        let Some(ty) = &number.suffix else {
+            return match number.real {
+                true => Type::Decimal,
+                false => self.constant(id),
+            };
+        };
+
+        if !self.ast.types.contains_key(ty) {
+            return Type::Unknown;
+        }
+
+        if self.is_real(ty) {
+            self.check_real_fits(id, ty);
+            return Type::Named(ty.clone());
+        }
+
+        if !self.is_range(ty) {
+            self.error(
+                format!("number suffixes name number types, `{}` isn't one", ty.name),
+                id,
+            );
+
+            return Type::Unknown;
+        }
+
+        match number.value.integer() {
+            Some(value) => self.check_fits(id, value, ty),
+            None if number.value.is_integer() => self.error(
+                format!("`{}` doesn't fit in `{}`", number.value, ty.name),
+                id,
+            ),
+            None => self.error(
+                format!(
+                    "`{}` isn't a whole number, so it can't be a `{}`",
+                    number.value, ty.name
+                ),
+                id,
+            ),
+        }
+
+        Type::Named(ty.clone())
+    }
+
+    fn constant(&mut self, id: ExpressionId) -> Type {
+        if let Some(value) = self.ast.evaluate(id) {
+            return Type::Literal(value);
+        }
+
+        let message = format!("this constant expression {}", folding_error(self.ast, id));
+        self.error(message, id);
+
+        Type::Unknown
+    }
+
+    /// Reports the bounds of the range type `signature` that don't fold, or that make it
+    /// empty.
+    fn check_range(&mut self, signature: &TypeSignature, range: &Range) {
+        let bounds = [("lower", range.min), ("upper", range.max)].map(|(bound, id)| {
+            let value = self.ast.evaluate(id);
+
+            if value.is_none() {
+                let message = format!(
+                    "the {} bound of `{}` {}",
+                    bound,
+                    signature.name,
+                    folding_error(self.ast, id)
+                );
+                self.error(message, id);
+            }
+
+            value
+        });
+
+        if let [Some(min), Some(max)] = bounds
+            && min > max
+        {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` is an empty range", signature.name))
+                    .at(self.ast.find_location(range.min))
+                    .with_note(format!("`{}` is more than `{}`", min, max)),
+            );
+        }
+    }
+
+    fn check_fixed(&mut self, signature: &TypeSignature, range: &Range) {
+        let Some((delta, min, max)) = fixed_bounds(self.ast, signature) else {
+            return;
+        };
+
+        if min > max {
+            let bound = |id: ExpressionId| {
+                let (negative, value) = self.ast.evaluate_real(id).unwrap_or_default();
+                format!("{}{}", if negative { "-" } else { "" }, value)
+            };
+
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` is an empty range", signature.name))
+                    .at(self.ast.find_location(range.min))
+                    .with_note(format!(
+                        "no multiple of {} lies between `{}` and `{}`",
+                        delta,
+                        bound(range.min),
+                        bound(range.max)
+                    )),
+            );
+        }
+    }
+
+    fn infer_binary_operation(&mut self, id: ExpressionId, bin_op: &BinOp) -> Type {
+        let left = self.infer(bin_op.left);
+        let right = self.infer(bin_op.right);
+        let comparison = bin_op.op.is_comparison();
+
+        let ty = match (left, right) {
+            (Type::Unknown, _) | (_, Type::Unknown) => Type::Unknown,
+            (Type::Literal(_), Type::Literal(_)) if comparison => Type::Condition,
+            (Type::Literal(_), Type::Literal(_)) => self.constant(id),
+            (Type::Named(ty), Type::Literal(value)) => {
+                self.check_arithmetic(bin_op.left, &ty, &bin_op.op);
+
+                // Fixed-point values are multiplied and divided by plain counts.
+                if !self.is_fixed(&ty) || !bin_op.op.is_scaling() {
+                    self.check_fits(bin_op.right, value, &ty);
+                }
+
+                Type::Named(ty)
+            }
+            (Type::Literal(value), Type::Named(ty)) => {
+                self.check_arithmetic(bin_op.right, &ty, &bin_op.op);
+
+                if !self.is_fixed(&ty) || !bin_op.op.is_scaling() {
+                    self.check_fits(bin_op.left, value, &ty);
+                }
+
+                Type::Named(ty)
+            }
+            (Type::Named(ty), Type::Decimal) if self.is_real(&ty) => {
+                self.check_arithmetic(bin_op.left, &ty, &bin_op.op);
+                self.check_real_fits(bin_op.right, &ty);
+                self.scale_operation(id, &ty, &bin_op.op);
+                Type::Named(ty)
+            }
+            (Type::Decimal, Type::Named(ty)) if self.is_real(&ty) => {
+                self.check_arithmetic(bin_op.right, &ty, &bin_op.op);
+                self.check_real_fits(bin_op.left, &ty);
+                self.scale_operation(id, &ty, &bin_op.op);
+                Type::Named(ty)
+            }
+            (Type::Named(left), Type::Named(right)) if left == right => {
+                self.check_arithmetic(bin_op.left, &left, &bin_op.op);
+                self.scale_operation(id, &left, &bin_op.op);
+                Type::Named(left)
+            }
+            // A fixed-point value times or divided by a count.
+            (Type::Named(left), Type::Named(right))
+                if self.is_fixed(&left) && self.is_range(&right) && bin_op.op.is_scaling() =>
+            {
+                Type::Named(left)
+            }
+            (Type::Named(left), Type::Named(right))
+                if self.is_range(&left) && self.is_fixed(&right) && bin_op.op == BinOpType::Mul =>
+            {
+                Type::Named(right)
+            }
+            (Type::Named(left), Type::Named(right)) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "`{}` can't be used with `{}` in `{}`",
+                        left.name, right.name, bin_op.op
+                    ))
+                    .at(self.ast.find_location(id))
+                    .with_note(format!(
+                        "`{}` and `{}` are unrelated range types, convert one of them first",
+                        left.name, right.name
+                    )),
+                );
+
+                Type::Unknown
+            }
+            (Type::Named(ty), Type::Size) => {
+                self.check_arithmetic(bin_op.left, &ty, &bin_op.op);
+                self.check_size(bin_op.left, &ty);
+                Type::Named(ty)
+            }
+            (Type::Size, Type::Named(ty)) => {
+                self.check_arithmetic(bin_op.right, &ty, &bin_op.op);
+                self.check_size(bin_op.right, &ty);
+                Type::Named(ty)
+            }
+            (Type::Size | Type::Literal(_), Type::Size) | (Type::Size, Type::Literal(_)) => {
+                Type::Size
+            }
+            (Type::Decimal, Type::Decimal | Type::Literal(_))
+            | (Type::Literal(_), Type::Decimal) => Type::Decimal,
+            (left, right) => {
+                self.error(
+                    format!("{} can't be used with {} in `{}`", left, right, bin_op.op),
+                    id,
+                );
+
+                Type::Unknown
+            }
+        };
+
+        match ty {
+            Type::Named(_) | Type::Decimal | Type::Size if comparison => Type::Condition,
+            ty => ty,
+        }
+    }
+
+    fn check_condition(&mut self, id: ExpressionId) {
+        match self.infer(id) {
+            Type::Condition | Type::Unknown => {}
+            found => self.error(format!("expected a condition, found {}", found), id),
+        }
+    }
+
+    /// Checks the patterns of a `match`, and its arms against `expected` when it's a value.
+    fn check_match(&mut self, id: ExpressionId, match_expression: &Match, expected: Option<&Type>) {
+        let variants = match self.infer(match_expression.value) {
+            Type::Named(ty) => match self.ast.types.get(&ty).map(|rosario_type| &rosario_type.ty) {
+                Some(TypeBody::Enum(variants)) => Some((ty, variants)),
+                Some(_) => {
+                    self.error(
+                        format!("`match` needs an enum, `{}` isn't one", ty.name),
+                        match_expression.value,
+                    );
+                    None
+                }
+                None => None,
+            },
+            Type::Unknown => None,
+            found => {
+                self.error(
+                    format!("`match` needs an enum, found {}", found),
+                    match_expression.value,
+                );
+                None
+            }
+        };
+
+        let mut covered = BTreeSet::new();
+        let mut wildcard = false;
+
+        for arm in &match_expression.arms {
+            match &arm.pattern {
+                Pattern::Wildcard => wildcard = true,
+                Pattern::Variant { name, bindings } => {
+                    if let Some((ty, variants)) = &variants {
+                        self.check_pattern(arm.body, ty, variants, name, bindings);
+                    }
+
+                    covered.insert(name.as_str());
+                }
+            }
+
+            match expected {
+                Some(ty) => self.expect_type(arm.body, ty),
+                None => {
+                    self.infer(arm.body);
+                }
+            }
+        }
+
+        if let Some((_, variants)) = &variants
+            && !wildcard
+        {
+            let missing = variants
+                .iter()
+                .filter(|variant| !covered.contains(variant.name.as_str()))
+                .map(|variant| format!("`{}`", variant.name))
+                .collect::<Vec<_>>();
+
+            if !missing.is_empty() {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`match` doesn't cover {}", missing.join(", ")))
+                        .at(self.ast.find_location(id))
+                        .with_note("add an arm for each of them, or a `_` arm".to_string()),
+                );
+            }
+        }
+    }
+
+    /// Gives the variables bound by `Variant(a, b)` the types of the variant's fields.
+    fn check_pattern(
+        &mut self,
+        arm: ExpressionId,
+        ty: &TypeSignature,
+        variants: &[EnumVariant],
+        name: &str,
+        bindings: &[Option<VariableId>],
+    ) {
+        let Some(variant) = variants.iter().find(|variant| variant.name == name) else {
+            self.error(format!("`{}` has no variant `{}`", ty.name, name), arm);
+            return;
+        };
+
+        self.check_private(arm, ty, variant);
+
+        if variant.fields.len() != bindings.len() {
+            self.error(
+                format!(
+                    "`{}` has {} field{} but {} {} bound",
+                    name,
+                    variant.fields.len(),
+                    if variant.fields.len() == 1 { "" } else { "s" },
+                    bindings.len(),
+                    if bindings.len() == 1 { "was" } else { "were" }
+                ),
+                arm,
+            );
+        }
+
+        for (binding, field) in bindings.iter().zip(&variant.fields) {
+            if let Some(binding) = binding {
+                self.bindings.insert(*binding, field.clone());
+            }
+        }
+    }
+
+    /// Private variants keep the invariants of their type, only its own file sees them.
+    fn check_private(&mut self, id: ExpressionId, ty: &TypeSignature, variant: &EnumVariant) {
+        // Instances are declared where their generic type is.
+        let name = ty.name.split('<').next().unwrap_or_default();
+
+        let declared = self
+            .ast
+            .item_locations
+            .get(&format!("{}::{}", ty.owner, name))
+            .map(|location| location.file.as_str());
+
+        let used = self.ast.find_location(id);
+
+        if !variant.private || declared == used.as_ref().map(|location| location.file.as_str()) {
+            return;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "`{}` is a private variant of `{}`",
+                variant.name, ty.name
+            ))
+            .at(used)
+            .with_note(format!(
+                "only `{}` can build or match it, use the functions of `{}` instead",
+                ty.owner, name
+            )),
+        );
+    }
+
+    /// Trusted definitions keep invariants the compiler can't check, only trusted packages
+    /// call them. Instances are called from where their generic code is written.
+    fn check_trusted(&mut self, id: ExpressionId, name: &str, definition: &DefinitionSignature) {
+        let Some(location) = self.ast.find_location(id) else {
+            return;
+        };
+
+        let Some(module) = self.ast.modules.get(&location.file) else {
+            return;
+        };
+
+        if !definition.trusted || self.ast.trusted_modules().contains(module.as_str()) {
+            return;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "`{}` is trusted, it can only be called from trusted packages",
+                name
+            ))
+            .at(Some(location))
+            .with_note(untrusted_note(self.ast, module)),
+        );
+    }
+
+    /// Methods are as visible as they're declared, unless they implement a trait. Instances
+    /// are called from where their generic code is written, like trusted definitions.
+    fn check_visible(&mut self, id: ExpressionId, name: &str, definition: &DefinitionSignature) {
+        let DefinitionOwner::Type(ty) = &definition.owner else {
+            return;
+        };
+
+        let Some(location) = self.ast.find_location(id) else {
+            return;
+        };
+
+        let Some(module) = self.ast.modules.get(&location.file) else {
+            return;
+        };
+
+        // Instances are named after their generic, which is the item declared.
+        let generic = |name: &str| name.split('<').next().unwrap_or_default().to_string();
+        let path = format!("{}::{}::{}", ty.owner, generic(&ty.name), generic(name));
+
+        let implemented = self.ast.traits.iter().any(|(path, declared)| {
+            self.ast
+                .implements(ty, path.rsplit("::").next().unwrap_or_default())
+                && declared.contains(&generic(name))
+        });
+
+        if self.ast.public_items.contains(&path)
+            || implemented
+            || self.ast.is_within(module, &ty.owner)
+        {
+            return;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!("`{}` is private to `{}`", generic(name), ty.owner))
+                .at(Some(location)),
+        );
+    }
+
+    /// Binds the element of a `for` loop to the type of the container's elements.
+    fn check_for(&mut self, for_expression: &For) {
+        let element = match self.infer(for_expression.container) {
+            Type::Array(TypeExpression::Array { element, .. }) => Some(*element),
+            Type::Named(ty) => self.iterated(for_expression.container, &ty),
+            Type::Unknown => None,
+            found => {
+                self.error(
+                    format!("{} can't be iterated with `for`", found),
+                    for_expression.container,
+                );
+                None
+            }
+        };
+
+        if let Some(element) = element {
+            self.bindings.insert(for_expression.element, element);
+        }
+
+        self.infer(for_expression.body);
+    }
+
+    /// Type of the elements `Get(&self, index)` returns for the container type `ty`.
+    fn iterated(&mut self, container: ExpressionId, ty: &TypeSignature) -> Option<TypeExpression> {
+        let length = self
+            .ast
+            .find_method(ty, "Length")
+            .filter(|length| length.args.len() == 1);
+        let element = self
+            .ast
+            .find_method(ty, "Get")
+            .filter(|get| get.args.len() == 2 && get.return_variable_type.is_reference())
+            .and_then(|get| get.return_type.clone());
+
+        match (length, element) {
+            (Some(_), Some(element)) => Some(element),
+            _ => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` can't be iterated with `for`", ty.name))
+                        .at(self.ast.find_location(container))
+                        .with_note(
+                            "it needs `Length(self)` and `Get(self, index)` returning a reference"
+                                .to_string(),
+                        ),
+                );
+                None
+            }
+        }
+    }
+
+    fn infer_conversion(&mut self, id: ExpressionId, conversion: &Conversion) -> Type {
+        let target = &conversion.ty;
+        let source = self.infer(conversion.value);
+
+        if !self.ast.types.contains_key(target) {
+            return Type::Unknown;
+        }
+
+        if self.is_float(target) {
+            match &source {
+                Type::Unknown | Type::Literal(_) | Type::Decimal | Type::Size => {}
+                Type::Named(ty) if self.is_range(ty) || self.is_real(ty) => {}
+                found => self.error(
+                    format!("{} can't be converted to `{}`", found, target.name),
+                    id,
+                ),
+            }
+
+            return Type::Named(target.clone());
+        }
+
+        if !self.is_range(target) {
+            self.error(
+                format!(
+                    "`as` converts to range and floating-point types, `{}` isn't one",
+                    target.name
+                ),
+                id,
+            );
+
+            return Type::Unknown;
+        }
+
+        let source_bounds = match &source {
+            Type::Unknown => None,
+            Type::Literal(value) => Some((*value, *value)),
+            Type::Named(ty) if self.is_range(ty) => range_bounds(self.ast, ty),
+            Type::Size => None,
+            found => {
+                self.error(
+                    format!("{} can't be converted to `{}`", found, target.name),
+                    id,
+                );
+
+                None
+            }
+        };
+
+        if let Some((source_min, source_max)) = source_bounds
+            && let Some((min, max)) = range_bounds(self.ast, target)
+        {
+            self.check_conversion(
+                id,
+                conversion,
+                &source,
+                (source_min, source_max),
+                (min, max),
+            );
+        }
+
+        Type::Named(target.clone())
+    }
+
+    /// Warns about conversions whose outcome is known at compile time.
+    fn check_conversion(
+        &mut self,
+        id: ExpressionId,
+        conversion: &Conversion,
+        source: &Type,
+        (source_min, source_max): (i128, i128),
+        (min, max): (i128, i128),
+    ) {
+        let target = &conversion.ty.name;
+        let location = self.ast.find_location(id);
+
+        let warning = if min <= source_min && source_max <= max {
+            let note = match conversion.balanced {
+                true => "`balanced` never has to clamp it".to_string(),
+                false => format!("every value of {} fits in `{}`", source, target),
+            };
+
+            Diagnostic::warning(format!(
+                "converting {} to `{}` always succeeds",
+                source, target
+            ))
+            .with_note(note)
+        } else if source_max < min || max < source_min {
+            let bound = if source_max < min { min } else { max };
+
+            match conversion.balanced {
+                true => Diagnostic::warning(format!(
+                    "converting {} to `{}` always clamps it to {}",
+                    source, target, bound
+                )),
+                false => Diagnostic::warning(format!(
+                    "converting {} to `{}` always fails",
+                    source, target
+                ))
+                .with_note(format!("`{}` ranges from {} to {}", target, min, max)),
+            }
+        } else {
+            return;
+        };
+
+        self.diagnostics.push(warning.at(location));
+    }
+
+    /// `as *T` reinterprets a raw pointer as a pointer to another type.
+    fn infer_cast(&mut self, id: ExpressionId, cast: &Cast) -> Type {
+        match self.infer(cast.value) {
+            Type::Pointer(_) | Type::Unknown => {}
+            found => self.error(format!("`as *` converts raw pointers, found {}", found), id),
+        }
+
+        Type::of(&cast.ty)
+    }
+
+    /// Sizes are unsigned, so only range types without negative values can hold them.
+    fn check_size(&mut self, id: ExpressionId, ty: &TypeSignature) {
+        if range_bounds(self.ast, ty).is_some_and(|(min, _)| min < 0) {
+            self.error(format!("expected `{}`, found {}", ty.name, Type::Size), id);
+        }
+    }
+
+    fn is_range(&self, ty: &TypeSignature) -> bool {
+        matches!(
+            self.ast.types.get(ty).map(|rosario_type| &rosario_type.ty),
+            Some(TypeBody::Range(_))
+        )
+    }
+
+    /// Only number types support arithmetic, and real numbers have no `mod` or `**`.
+    fn check_arithmetic(&mut self, id: ExpressionId, ty: &TypeSignature, op: &BinOpType) {
+        let Some(rosario_type) = self.ast.types.get(ty) else {
+            return;
+        };
+
+        let message = match (&rosario_type.ty, op) {
+            (TypeBody::Range(_), _) => return,
+            (TypeBody::Float(_) | TypeBody::Fixed(_), BinOpType::Mod | BinOpType::ToThePowerOf)
+            // Products of fixed-point values are scaled, which compound assignments can't do.
+            | (TypeBody::Fixed(_), BinOpType::MulEquals | BinOpType::DivEquals) => {
+                format!("`{}` doesn't support `{}`", ty.name, op)
+            }
+            (TypeBody::Float(_) | TypeBody::Fixed(_), _) => return,
+            _ if op.is_comparison() => format!("`{}` can't be compared with `{}`", ty.name, op),
+            _ => format!("`{}` doesn't support arithmetic", ty.name),
+        };
+
+        self.error(message, id);
+    }
+
+    fn is_float(&self, ty: &TypeSignature) -> bool {
+        matches!(
+            self.ast.types.get(ty).map(|rosario_type| &rosario_type.ty),
+            Some(TypeBody::Float(_))
+        )
+    }
+
+    fn is_fixed(&self, ty: &TypeSignature) -> bool {
+        matches!(
+            self.ast.types.get(ty).map(|rosario_type| &rosario_type.ty),
+            Some(TypeBody::Fixed(_))
+        )
+    }
+
+    fn is_real(&self, ty: &TypeSignature) -> bool {
+        self.is_float(ty) || self.is_fixed(ty)
+    }
+
+    /// Products and quotients of fixed-point values are rescaled to the type's delta.
+    fn scale_operation(&mut self, id: ExpressionId, ty: &TypeSignature, op: &BinOpType) {
+        if !self.is_fixed(ty) {
+            return;
+        }
+
+        match op {
+            BinOpType::Mul => self.scalings.insert(id, Scaling::Product(ty.clone())),
+            BinOpType::Div => self.scalings.insert(id, Scaling::Quotient(ty.clone())),
+            _ => None,
+        };
+    }
+
+    /// The value of a constant number expression, as its sign and magnitude.
+    fn real_constant(&self, id: ExpressionId) -> Option<(bool, Exact)> {
+        if let Some(Expression::Number(number)) = self.ast.find_expression(id) {
+            return Some((false, number.value.clone()));
+        }
+
+        match self.ast.evaluate(id) {
+            Some(value) => Some((value < 0, Exact::from(value.unsigned_abs()))),
+            None => self.ast.evaluate_real(id),
+        }
+    }
+
+    /// Checks a constant used as a value of the real type `ty`: it must fit in it, and be a
+    /// multiple of the delta of fixed-point types, whose constants are stored scaled.
+    fn check_real_fits(&mut self, id: ExpressionId, ty: &TypeSignature) {
+        let Some((negative, value)) = self.real_constant(id) else {
+            if !self.is_fixed(ty) {
+                return;
+            }
+
+            // Sums of constants are scaled one by one.
+            match self.ast.find_expression(id) {
+                Some(Expression::BinaryOperation(bin_op))
+                    if matches!(bin_op.op, BinOpType::Add | BinOpType::Sub) =>
+                {
+                    self.check_real_fits(bin_op.left, ty);
+                    self.check_real_fits(bin_op.right, ty);
+                }
+                _ => self.error(
+                    format!("this constant can't be computed exactly as a `{}`", ty.name),
+                    id,
+                ),
+            }
+
+            return;
+        };
+
+        let sign = if negative { "-" } else { "" };
+
+        if let Some(digits) = float_digits(self.ast, ty) {
+            let text = format!("{}{}", sign, value);
+            let finite = match digits {
+                ..=6 => text.parse::<f32>().is_ok_and(f32::is_finite),
+                _ => text.parse::<f64>().is_ok_and(f64::is_finite),
+            };
+
+            if !finite {
+                self.error(format!("`{}` doesn't fit in `{}`", text, ty.name), id);
+            }
+
+            return;
+        }
+
+        let Some((delta, min, max)) = fixed_bounds(self.ast, ty) else {
+            return;
+        };
+
+        match multiple(&(negative, value.clone()), &delta) {
+            Some((count, true)) if min <= count && count <= max => {
+                self.scalings.insert(id, Scaling::Constant(count));
+            }
+            Some((_, false)) => self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "`{}{}` isn't a multiple of the delta of `{}`",
+                    sign, value, ty.name
+                ))
+                .at(self.ast.find_location(id))
+                .with_note(format!("`{}` counts in steps of {}", ty.name, delta)),
+            ),
+            _ => {
+                let bound = |count: i128| {
+                    let sign = if count < 0 { "-" } else { "" };
+                    format!("{}{}", sign, delta.times(count.unsigned_abs()))
+                };
+
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}{}` doesn't fit in `{}`", sign, value, ty.name))
+                        .at(self.ast.find_location(id))
+                        .with_note(format!(
+                            "`{}` ranges from {} to {}",
+                            ty.name,
+                            bound(min),
+                            bound(max)
+                        )),
+                );
+            }
+        }
+    }
+
+    fn infer_call(&mut self, id: ExpressionId, call: &Call) -> Type {
+        let Some(definition) = self.ast.find_definition(call) else {
+            for arg in &call.args {
+                self.infer(*arg);
+            }
+
+            return Type::Unknown;
+        };
+
+        self.check_arguments(id, &call.name, definition, &definition.args, &call.args)
+    }
+
+    /// C functions are checked against their `C_LANG function` declaration.
+    fn infer_foreign_call(&mut self, id: ExpressionId, call: &Call) -> Type {
+        let Some(declaration) = self.ast.foreign.get(&call.name) else {
+            self.diagnostics.push(
+                Diagnostic::error(format!("the C function `{}` isn't declared", call.name))
+                    .at(self.ast.find_location(id))
+                    .with_note(format!(
+                        "declare it with `C_LANG function {}(...) => Type;`",
+                        call.name
+                    )),
+            );
+
+            for arg in &call.args {
+                self.infer(*arg);
+            }
+
+            return Type::Unknown;
+        };
+
+        self.check_arguments(id, &call.name, declaration, &declaration.args, &call.args)
+    }
+
+    fn infer_method_call(&mut self, id: ExpressionId, call: &MethodCall) -> Type {
+        let receiver = self.infer(call.receiver);
+
+        if let Type::Named(ty) = &receiver
+            && call.name == "Clone"
+            && call.args.is_empty()
+            && self.ast.find_method(ty, "Clone").is_none()
+            && self.ast.is_copy(ty)
+        {
+            // Copying is cloning for `Copy` types, so generic code can clone any element.
+            return receiver;
+        }
+
+        if let Type::Array(ty) = &receiver
+            && call.name == "Length"
+            && call.args.is_empty()
+        {
+            // Every array is a `Container`, its length is known at compile time.
+            return match self.array_bounds(id, ty) {
+                Some((min, max)) => Type::Literal(max - min + 1),
+                None => Type::Unknown,
+            };
+        }
+
+        let method = match &receiver {
+            Type::Unknown => None,
+            Type::Named(ty) => {
+                let method = self.ast.find_method(ty, &call.name);
+
+                if method.is_none() {
+                    self.error(format!("`{}` has no method `{}`", ty.name, call.name), id);
+                }
+
+                method
+            }
+            found => {
+                self.error(format!("{} has no method `{}`", found, call.name), id);
+                None
+            }
+        };
+
+        let Some(method) = method else {
+            for arg in &call.args {
+                self.infer(*arg);
+            }
+
+            return Type::Unknown;
+        };
+
+        let parameters = match method.args.first() {
+            Some(first)
+                if self
+                    .ast
+                    .variables
+                    .get(method)
+                    .and_then(|variables| variables.get(first))
+                    .is_some_and(|variable| variable.name == "self") =>
+            {
+                &method.args[1..]
+            }
+            _ => {
+                self.error(
+                    format!(
+                        "`{}` doesn't take `self`, it can't be called on a value",
+                        call.name
+                    ),
+                    id,
+                );
+
+                &method.args[..]
+            }
+        };
+
+        self.check_arguments(id, &call.name, method, parameters, &call.args)
+    }
+
+    /// `Type::Variant(fields)` builds a variant, `Type::Name(args)` calls a definition of `Type`.
+    fn infer_associated(&mut self, id: ExpressionId, associated: &Associated) -> Type {
+        let ty = &associated.ty.ty;
+
+        if !self.ast.types.contains_key(ty) {
+            for arg in &associated.args {
+                self.infer(*arg);
+            }
+
+            return Type::Unknown;
+        }
+
+        if let Some((_, variant)) = self.ast.find_variant(ty, &associated.name) {
+            self.check_private(id, ty, variant);
+
+            if variant.fields.len() != associated.args.len() {
+                self.error(
+                    format!(
+                        "`{}` has {} field{} but {} {} given",
+                        associated.name,
+                        variant.fields.len(),
+                        if variant.fields.len() == 1 { "" } else { "s" },
+                        associated.args.len(),
+                        if associated.args.len() == 1 {
+                            "was"
+                        } else {
+                            "were"
+                        }
+                    ),
+                    id,
+                );
+            }
+
+            for (i, arg) in associated.args.iter().enumerate() {
+                match variant.fields.get(i) {
+                    Some(field) => self.expect_expression(*arg, field),
+                    None => {
+                        self.infer(*arg);
+                    }
+                }
+            }
+
+            return Type::Named(ty.clone());
+        }
+
+        match self.ast.find_method(ty, &associated.name) {
+            Some(definition) => self.check_arguments(
+                id,
+                &associated.name,
+                definition,
+                &definition.args,
+                &associated.args,
+            ),
+            None => {
+                self.error(
+                    format!(
+                        "`{}` has no variant or definition `{}`",
+                        ty.name, associated.name
+                    ),
+                    id,
+                );
+
+                for arg in &associated.args {
+                    self.infer(*arg);
+                }
+
+                Type::Unknown
+            }
+        }
+    }
+
+    fn check_arguments(
+        &mut self,
+        id: ExpressionId,
+        name: &str,
+        definition: &DefinitionSignature,
+        parameters: &[VariableId],
+        args: &[ExpressionId],
+    ) -> Type {
+        self.check_trusted(id, name, definition);
+        self.check_visible(id, name, definition);
+
+        if parameters.len() != args.len() {
+            self.error(
+                format!(
+                    "`{}` takes {} argument{} but {} {} given",
+                    name,
+                    parameters.len(),
+                    if parameters.len() == 1 { "" } else { "s" },
+                    args.len(),
+                    if args.len() == 1 { "was" } else { "were" },
+                ),
+                id,
+            );
+        }
+
+        for (i, arg) in args.iter().enumerate() {
+            let parameter = parameters
+                .get(i)
+                .and_then(|parameter| self.ast.variables.get(definition)?.get(parameter));
+
+            match parameter {
+                Some(parameter) => self.expect_expression(*arg, &parameter.ty),
+                None => {
+                    self.infer(*arg);
+                }
+            }
+        }
+
+        match &definition.return_type {
+            Some(ty) => Type::of(ty),
+            None => Type::Nothing,
+        }
+    }
+
+    fn check_assignment(&mut self, assignment: &Assignment) {
+        match self.infer(assignment.place) {
+            Type::Named(ty) => {
+                if let Some(op) = &assignment.op {
+                    self.check_arithmetic(assignment.place, &ty, op);
+                }
+
+                self.expect(assignment.value, &ty);
+            }
+            ty @ (Type::Array(_) | Type::Pointer(_)) => self.expect_type(assignment.value, &ty),
+            _ => {
+                self.infer(assignment.value);
+            }
+        }
+    }
+
+    /// `base[index]` takes an index of the array's range, so it never needs a bounds check.
+    fn infer_index(&mut self, id: ExpressionId, index: &Index) -> Type {
+        let array = match self.infer(index.base) {
+            Type::Array(ty) => ty,
+            Type::Pointer(ty) => return self.infer_pointer_index(id, index, *ty),
+            Type::Unknown => {
+                self.infer(index.index);
+                return Type::Unknown;
+            }
+            found => {
+                self.error(format!("{found} can't be indexed, it isn't an array"), id);
+                self.infer(index.index);
+                return Type::Unknown;
+            }
+        };
+
+        let TypeExpression::Array {
+            index: range,
+            element,
+        } = &array
+        else {
+            return Type::Unknown;
+        };
+
+        match self.array_bounds(id, &array) {
+            Some(_) => self.expect_expression(index.index, range),
+            None => {
+                self.infer(index.index);
+            }
+        }
+
+        Type::of(element)
+    }
+
+    /// `pointer[index]` reads the `index`th value after the pointer, without any bounds.
+    fn infer_pointer_index(&mut self, id: ExpressionId, index: &Index, element: Type) -> Type {
+        let module = match self.signature.map(|signature| &signature.owner) {
+            Some(DefinitionOwner::Path(path)) => Some(path.as_str()),
+            Some(DefinitionOwner::Type(ty)) => Some(ty.owner.as_str()),
+            _ => None,
+        };
+
+        if let Some(module) = module
+            && !self.ast.trusted_modules().contains(module)
+        {
+            self.error(
+                "raw pointers can only be indexed in trusted packages".to_string(),
+                id,
+            );
+        }
+
+        match self.infer(index.index) {
+            Type::Literal(_) | Type::Size | Type::Unknown => {}
+            Type::Named(ty) if self.is_range(&ty) => {}
+            found => self.error(
+                format!("pointers are indexed by range types, found {}", found),
+                index.index,
+            ),
+        }
+
+        element
+    }
+
+    /// Bounds of the range indexing the array type `ty`, reporting it when it isn't one.
+    fn array_bounds(&mut self, id: ExpressionId, ty: &TypeExpression) -> Option<(i128, i128)> {
+        let TypeExpression::Array { index, .. } = ty else {
+            return None;
+        };
+
+        if let Some(signature) = index.signature() {
+            if !self.ast.types.contains_key(signature) {
+                return None;
+            }
+
+            if let Some(bounds) = range_bounds(self.ast, signature) {
+                return Some(bounds);
+            }
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "arrays are indexed by range types, `{}` isn't one",
+                index
+            ))
+            .at(self.ast.find_location(id))
+            .with_note(format!("the length of `{}` comes from its range", ty)),
+        );
+
+        None
+    }
+
+    /// Checks each element of `[a, b, ...]` against the array type it's used as.
+    fn check_array_literal(
+        &mut self,
+        id: ExpressionId,
+        elements: &[ExpressionId],
+        ty: &TypeExpression,
+    ) {
+        let TypeExpression::Array { element, .. } = ty else {
+            return;
+        };
+
+        if let Some((min, max)) = self.array_bounds(id, ty) {
+            let length = max - min + 1;
+
+            if elements.len() as i128 != length {
+                self.error(
+                    format!(
+                        "`{}` holds {} element{} but the literal has {}",
+                        ty,
+                        length,
+                        if length == 1 { "" } else { "s" },
+                        elements.len()
+                    ),
+                    id,
+                );
+            }
+        }
+
+        for value in elements {
+            self.expect_expression(*value, element);
+        }
+
+        self.array_literals.insert(id, ty.clone());
+    }
+
+    fn check_return(&mut self, id: ExpressionId, value: Option<ExpressionId>) {
+        let Some(signature) = self.signature else {
+            return;
+        };
+
+        match (value, &signature.return_type) {
+            (None, None) => {}
+            (Some(value), Some(ty)) => self.expect_expression(value, ty),
+            (Some(value), None) => {
+                self.infer(value);
+                self.error(format!("`{}` doesn't return a value", signature.name), id);
+            }
+            (None, Some(ty)) => {
+                self.error(format!("`{}` must return a `{}`", signature.name, ty), id);
+            }
+        }
+    }
+
+    fn expect_expression(&mut self, id: ExpressionId, ty: &TypeExpression) {
+        self.expect_type(id, &Type::of(ty));
+    }
+
+    fn expect_type(&mut self, id: ExpressionId, ty: &Type) {
+        if let Some(Expression::Match(match_expression)) = self.ast.find_expression(id) {
+            return self.check_match(id, match_expression, Some(ty));
+        }
+
+        match ty {
+            Type::Named(ty) => self.expect(id, ty),
+            Type::Unknown => {
+                self.infer(id);
+            }
+            Type::Array(array)
+                if let Some(Expression::Array(elements)) = self.ast.find_expression(id) =>
+            {
+                self.check_array_literal(id, elements, array);
+            }
+            expected => match self.infer(id) {
+                Type::Unknown => {}
+                found if found == *expected => {}
+                found => self.error(format!("expected {}, found {}", expected, found), id),
+            },
+        }
+    }
+
+    /// Checks that the expression `id` can be used where a `ty` is expected.
+    fn expect(&mut self, id: ExpressionId, ty: &TypeSignature) {
+        if let Some(Expression::Match(match_expression)) = self.ast.find_expression(id) {
+            return self.check_match(id, match_expression, Some(&Type::Named(ty.clone())));
+        }
+
+        match self.infer(id) {
+            Type::Unknown => {}
+            Type::Named(found) if found == *ty => {}
+            Type::Literal(value) => self.check_fits(id, value, ty),
+            Type::Decimal if self.is_real(ty) => self.check_real_fits(id, ty),
+            Type::Size if self.is_range(ty) => self.check_size(id, ty),
+            found => self.error(format!("expected `{}`, found {}", ty.name, found), id),
+        }
+    }
+
+    fn check_fits(&mut self, id: ExpressionId, value: i128, ty: &TypeSignature) {
+        match self.ast.types.get(ty).map(|rosario_type| &rosario_type.ty) {
+            Some(TypeBody::Range(_)) => {}
+            Some(TypeBody::Float(_) | TypeBody::Fixed(_)) => return self.check_real_fits(id, ty),
+            Some(_) => {
+                self.error(
+                    format!("expected `{}`, found {}", ty.name, Type::Literal(value)),
+                    id,
+                );
+                return;
+            }
+            None => return,
+        }
+
+        let Some((min, max)) = range_bounds(self.ast, ty) else {
+            return;
+        };
+
+        if value < min || value > max {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` doesn't fit in `{}`", value, ty.name))
+                    .at(self.ast.find_location(id))
+                    .with_note(format!("`{}` ranges from {} to {}", ty.name, min, max)),
+            );
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{
+    diagnostic::{Diagnostic, Location},
+    lexer::{Lexer, SYMBOL_LIST, TokenType},
+    parser::Parser,
+    syntax::{NodeKind, SyntaxElement, SyntaxNode, SyntaxToken, SyntaxTree, TriviaKind},
+};
+
+const INDENT: &str = "    ";
+
+/// Formats the file at `path`. Files that don't parse aren't formatted, and neither are
+/// those whose tokens formatting would change, which would be a bug of the formatter.
+pub fn format_file(path: &str) -> Result<String, Vec<Diagnostic>> {
+    let lexer = Lexer::from_file(path, None);
+    let tree = SyntaxTree::parse(lexer.clone());
+
+    if tree.diagnostics.iter().any(Diagnostic::is_error) {
+        return Err(tree.diagnostics);
+    }
+
+    let mut parser = Parser::default();
+    parser.start(lexer);
+
+    if parser.diagnostics.iter().any(Diagnostic::is_error) {
+        return Err(parser.diagnostics);
+    }
+
+    let result = format(&tree.root);
+
+    let mut formatted = Lexer::from(result.clone().into_bytes(), path.to_string(), None);
+    formatted.start();
+
+    let changed = formatted.contents.iter().map(|token| &token.ty).ne(tree
+        .root
+        .tokens()
+        .into_iter()
+        .map(|token| &token.ty));
+
+    if changed {
+        let diagnostic =
+            Diagnostic::error("formatting would change the meaning of this file".to_string())
+                .at(Some(Location {
+                    file: formatted.file_path,
+                    line: 1,
+                    column: 1,
+                }))
+                .with_note("it was left as it is".to_string());
+
+        return Err(vec![diagnostic]);
+    }
+
+    Ok(result)
+}
+
+/// Formats a file, keeping its comments and where its lines break. Lines are indented
+/// by their blocks and brackets, tokens are spaced the same way everywhere and blank
+/// lines are single and never at the start or the end of a block.
+///
+/// Formatting a formatted file doesn't change it.
+pub fn format(root: &SyntaxNode) -> String {
+    let mut placed = vec![];
+    place(root, 0, &mut placed);
+
+    let tokens = placed.iter().map(|placed| placed.token).collect::<Vec<_>>();
+    let generics = generic_brackets(&tokens);
+
+    let mut result = String::new();
+    let mut spacing = Spacing::default();
+
+    for (i, placed) in placed.iter().enumerate() {
+        let token = placed.token;
+
+        let mut lines = token
+            .leading
+            .split(|trivia| trivia.kind == TriviaKind::Newline)
+            .collect::<Vec<_>>();
+
+        let breaks = lines.len() > 1;
+
+        // The first part is on the line of the previous token, the last one is before
+        // this token on its line.
+        lines.pop();
+        if i != 0 && !lines.is_empty() {
+            lines.remove(0);
+        }
+
+        if i == 0 || breaks || token.ty == TokenType::EndOfFile {
+            if i != 0 {
+                result.push('\n');
+            }
+
+            let at_start = i == 0 || placed.block_start;
+            let closes = placed.closes || token.ty == TokenType::EndOfFile;
+
+            // Comments before what closes a block are part of the block.
+            let depth = placed.depth + placed.closes as usize;
+
+            let mut blank = false;
+            let mut written = false;
+
+            for line in lines {
+                let comment = line.iter().find(|trivia| {
+                    matches!(trivia.kind, TriviaKind::Comment | TriviaKind::DocComment)
+                });
+
+                match comment {
+                    None => blank = true,
+                    Some(comment) => {
+                        if blank && (written || !at_start) {
+                            result.push('\n');
+                        }
+
+                        result.push_str(&INDENT.repeat(depth));
+                        result.push_str(comment.text.trim_end());
+                        result.push('\n');
+
+                        blank = false;
+                        written = true;
+                    }
+                }
+            }
+
+            if blank && !closes && (written || !at_start) {
+                result.push('\n');
+            }
+
+            if token.ty != TokenType::EndOfFile {
+                result.push_str(&INDENT.repeat(placed.depth));
+            }
+        } else if spacing.spaced(token, generics[i]) {
+            result.push(' ');
+        }
+
+        result.push_str(&token.text);
+        spacing.next(token, generics[i]);
+
+        for trivia in &token.trailing {
+            if matches!(trivia.kind, TriviaKind::Comment | TriviaKind::DocComment) {
+                result.push(' ');
+                result.push_str(trivia.text.trim_end());
+            }
+        }
+    }
+
+    let length = result.trim_end().len();
+    result.truncate(length);
+
+    if !result.is_empty() {
+        result.push('\n');
+    }
+
+    result
+}
+
+/// A token with the indentation of the line it starts.
+#[derive(Debug)]
+struct Placed<'a> {
+    token: &'a SyntaxToken,
+    depth: usize,
+    /// Whether it's the first token of a block.
+    block_start: bool,
+    /// Whether it closes a block or brackets, like `end` or `)`.
+    closes: bool,
+}
+
+/// Keywords that stay at the level of their declaration or statement when they start a
+/// line, like the `begin` after the header of a procedure.
+fn is_statement_keyword(ty: &TokenType) -> bool {
+    matches!(
+        ty,
+        TokenType::Begin
+            | TokenType::End
+            | TokenType::ElsIf
+            | TokenType::Else
+            | TokenType::Then
+            | TokenType::Loop
+            | TokenType::Match
+            | TokenType::Enum
+            | TokenType::Is
+    )
+}
+
+fn place<'a>(node: &'a SyntaxNode, depth: usize, result: &mut Vec<Placed<'a>>) {
+    let last = node.children.len().saturating_sub(1);
+
+    for (i, child) in node.children.iter().enumerate() {
+        match child {
+            SyntaxElement::Token(token) => {
+                let closes = match node.kind {
+                    NodeKind::Group => i == last && i != 0,
+                    _ => matches!(
+                        token.ty,
+                        TokenType::End | TokenType::ElsIf | TokenType::Else
+                    ),
+                };
+
+                let depth = match node.kind {
+                    NodeKind::File | NodeKind::Block => depth,
+                    NodeKind::Statement if i == 0 || is_statement_keyword(&token.ty) => depth,
+                    NodeKind::Group if i == 0 || closes => depth,
+                    NodeKind::Statement | NodeKind::Group => depth + 1,
+                };
+
+                result.push(Placed {
+                    token,
+                    depth,
+                    block_start: false,
+                    closes,
+                });
+            }
+            SyntaxElement::Node(child) => {
+                let start = result.len();
+
+                let depth = match (node.kind, child.kind) {
+                    (_, NodeKind::Block) | (NodeKind::Group, _) => depth + 1,
+                    _ => depth,
+                };
+
+                place(child, depth, result);
+
+                if child.kind == NodeKind::Block
+                    && let Some(first) = result.get_mut(start)
+                {
+                    first.block_start = true;
+                }
+            }
+        }
+    }
+}
+
+/// Which tokens are the `<` and `>` around generics, like in `Vector<T>`, rather than
+/// comparisons: a `<` after a name whose `>` comes before anything a type can't hold.
+fn generic_brackets(tokens: &[&SyntaxToken]) -> Vec<bool> {
+    let mut result = vec![false; tokens.len()];
+
+    for (i, token) in tokens.iter().enumerate() {
+        let named = i > 0 && matches!(tokens[i - 1].ty, TokenType::Identifier(_));
+
+        if token.ty != TokenType::LessThan || !named || result[i] {
+            continue;
+        }
+
+        let mut open = vec![i];
+
+        for (j, token) in tokens.iter().enumerate().skip(i + 1) {
+            match token.ty {
+                TokenType::LessThan => open.push(j),
+                TokenType::MoreThan => {
+                    result[j] = true;
+                    result[open.pop().unwrap()] = true;
+
+                    if open.is_empty() {
+                        break;
+                    }
+                }
+                TokenType::Identifier(_)
+                | TokenType::Number { .. }
+                | TokenType::Of
+                | TokenType::Character(',' | '&')
+                // The bounds of generic parameters, like `<R: Range + Copy>`.
+                | TokenType::Colon
+                | TokenType::Add
+                | TokenType::DoubleColon
+                | TokenType::Multiply
+                | TokenType::Mutable => {}
+                _ => break,
+            }
+        }
+
+        // Unclosed, so it was a comparison, and so were the brackets after it.
+        if !open.is_empty() {
+            for flag in &mut result[i..] {
+                *flag = false;
+            }
+        }
+    }
+
+    result
+}
+
+/// Decides the spaces between tokens on the same line.
+#[derive(Debug, Default)]
+struct Spacing<'a> {
+    previous: Option<&'a SyntaxToken>,
+    /// Whether the previous token is a prefix operator, like the `-` of `-1`.
+    prefix: bool,
+    /// Whether the previous token is the `<` or `>` of generics.
+    generic: bool,
+}
+
+impl<'a> Spacing<'a> {
+    /// Whether the previous token ends an operand, so an operator after it is binary.
+    fn after_operand(&self) -> bool {
+        let Some(previous) = self.previous else {
+            return false;
+        };
+
+        match previous.ty {
+            TokenType::Identifier(_)
+            | TokenType::Number { .. }
+            | TokenType::StaticString(_)
+            | TokenType::CharLiteral(_)
+            | TokenType::SelfVariable
+            | TokenType::Character(')' | ']' | '_' | '?') => true,
+            TokenType::MoreThan => self.generic,
+            _ => false,
+        }
+    }
+
+    /// Whether there's a space before `token`, `generic` when it's the `<` or `>` of
+    /// generics.
+    fn spaced(&self, token: &SyntaxToken, generic: bool) -> bool {
+        let Some(previous) = self.previous else {
+            return false;
+        };
+
+        // Like `- -1`, which would be a comment without its space.
+        let joined = previous.text.chars().last().into_iter();
+        let joined = joined.chain(token.text.chars().next()).collect::<String>();
+
+        if SYMBOL_LIST.contains_key(joined.as_str()) {
+            return true;
+        }
+
+        if generic || self.prefix || self.generic && previous.ty == TokenType::LessThan {
+            return false;
+        }
+
+        let called = matches!(
+            previous.ty,
+            TokenType::Identifier(_) | TokenType::Character(')' | ']')
+        ) || self.generic && previous.ty == TokenType::MoreThan;
+
+        match (&previous.ty, &token.ty) {
+            (_, TokenType::Character(',' | ')' | ']' | '?'))
+            | (_, TokenType::Semicolon | TokenType::Dot | TokenType::DoubleColon)
+            | (_, TokenType::Colon | TokenType::RangeDots)
+            | (TokenType::Character('(' | '[' | '&'), _)
+            | (TokenType::Dot | TokenType::DoubleColon | TokenType::RangeDots, _) => false,
+            (TokenType::Not, _) => false,
+            (TokenType::Clang, TokenType::Character('(')) => false,
+            // The symbol of `operator "?"(self)`.
+            (TokenType::StaticString(_), TokenType::Character('(')) => false,
+            (TokenType::SelfVariable, TokenType::Character('[')) => false,
+            (_, TokenType::Character('(' | '[')) => !called,
+            _ => true,
+        }
+    }
+
+    fn next(&mut self, token: &'a SyntaxToken, generic: bool) {
+        self.prefix = matches!(
+            token.ty,
+            TokenType::Sub | TokenType::Add | TokenType::Multiply
+        ) && !self.after_operand();
+
+        self.generic = generic;
+        self.previous = Some(token);
+    }
+}
@@ -1,15 +1,29 @@
 use std::{collections::HashMap, path::Path, sync::LazyLock};
 
+use crate::{
+    diagnostic::{Diagnostic, Location},
+    parser::number::{Exact, Natural},
+    unicode,
+};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TokenType {
     EndOfFile,
     Identifier(String),
     Character(char),
-    Number(String, Option<String>),
+    /// A number literal, `real` when it's written with a fraction or an
+    /// exponent, like `2.5` or `1e3`, and with the type named by its suffix,
+    /// like the `UInt8` of `255_UInt8`.
+    Number {
+        value: Exact,
+        real: bool,
+        suffix: Option<String>,
+    },
     Procedure,
     Function,
     Is,
     Loop,
+    While,
     For,
     Return,
     Semicolon,
@@ -20,6 +34,7 @@ pub enum TokenType {
     Else,
     Then,
     StaticString(String),
+    CharLiteral(char),
     Do,
     Mutable,
     Let,
@@ -49,6 +64,7 @@ pub enum TokenType {
     Dot,
     Modulo,
     Public,
+    Private,
     Use,
     Package,
     Colon,
@@ -60,12 +76,20 @@ pub enum TokenType {
     Operator,
     Trait,
     Clang,
+    Trusted,
+    As,
+    Balanced,
+    SizeOf,
 }
 
 #[derive(Debug, Clone)]
 pub struct Token {
+    /// Line, from 0, and column, from 1, counted in characters.
     pub begin_location: (usize, usize),
+    /// Length in characters.
     pub location_size: usize,
+    /// Column counted in UTF-16 code units, as editors do.
+    pub utf16_column: usize,
     pub ty: TokenType,
 }
 
@@ -103,6 +127,7 @@ pub static IDENTIFIER_LIST: LazyLock<HashMap<&str, TokenType>> = LazyLock::new(|
         ("function", TokenType::Function),
         ("is", TokenType::Is),
         ("loop", TokenType::Loop),
+        ("while", TokenType::While),
         ("for", TokenType::For),
         ("return", TokenType::Return),
         ("begin", TokenType::Begin),
@@ -120,6 +145,7 @@ pub static IDENTIFIER_LIST: LazyLock<HashMap<&str, TokenType>> = LazyLock::new(|
         ("range", TokenType::Range),
         ("mod", TokenType::Modulo),
         ("public", TokenType::Public),
+        ("private", TokenType::Private),
         ("use", TokenType::Use),
         ("package", TokenType::Package),
         ("enum", TokenType::Enum),
@@ -128,12 +154,16 @@ pub static IDENTIFIER_LIST: LazyLock<HashMap<&str, TokenType>> = LazyLock::new(|
         ("operator", TokenType::Operator),
         ("trait", TokenType::Trait),
         ("C_LANG", TokenType::Clang),
+        ("trusted", TokenType::Trusted),
+        ("as", TokenType::As),
+        ("balanced", TokenType::Balanced),
+        ("size_of", TokenType::SizeOf),
     ])
 });
 
 #[derive(Default, Debug, Clone)]
 pub struct Lexer {
-    input: Vec<u8>,
+    pub(crate) input: Vec<char>,
     pub contents: Vec<Token>,
     position: usize,
     last_line: usize,
@@ -142,6 +172,7 @@ pub struct Lexer {
     pub folder_path: String,
     pub file_name: String,
     pub main_rosario_path: String,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl Lexer {
@@ -192,13 +223,52 @@ impl Lexer {
             None => folder_name,
         };
 
-        Self {
-            input,
+        let mut lexer = Self {
             file_path,
             folder_path,
             file_name,
             main_rosario_path,
             ..Default::default()
+        };
+
+        lexer.decode(&input);
+
+        lexer
+    }
+
+    /// Decodes the UTF-8 `input`, reporting invalid sequences and replacing them with
+    /// U+FFFD.
+    fn decode(&mut self, input: &[u8]) {
+        let mut rest = input;
+
+        loop {
+            let error = match std::str::from_utf8(rest) {
+                Ok(text) => return self.input.extend(text.chars()),
+                Err(error) => error,
+            };
+
+            let (valid, invalid) = rest.split_at(error.valid_up_to());
+            self.input
+                .extend(std::str::from_utf8(valid).unwrap_or_default().chars());
+
+            let length = error.error_len().unwrap_or(invalid.len());
+            let bytes = invalid[..length]
+                .iter()
+                .map(|byte| format!("`{:#04X}`", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let line = self.input.iter().filter(|c| **c == '\n').count();
+            let column = self.input.iter().rev().take_while(|c| **c != '\n').count() + 1;
+
+            self.error(
+                "invalid UTF-8".to_string(),
+                (line, column),
+                Some(format!("found {}, source files must be UTF-8", bytes)),
+            );
+
+            self.input.push(char::REPLACEMENT_CHARACTER);
+            rest = &invalid[length..];
         }
     }
 
@@ -211,21 +281,22 @@ impl Lexer {
         Lexer::from(content, path.to_string(), main_rosario_path)
     }
 
-    pub fn advance(&mut self) -> u8 {
+    pub fn advance(&mut self) -> char {
         self.position += 1;
 
         let result = self.current_char();
         self.location.1 += 1;
 
-        if result == b'\n' {
-            self.last_line = self.location.1;
+        if result == '\n' {
+            // The column of the character before the line break, where `go_back` returns.
+            self.last_line = self.location.1 - 1;
             self.location = (self.location.0 + 1, 0);
         }
 
         result
     }
 
-    pub fn go_back(&mut self) -> u8 {
+    pub fn go_back(&mut self) -> char {
         self.position -= 1;
 
         let result = self.current_char();
@@ -239,19 +310,56 @@ impl Lexer {
         result
     }
 
-    pub fn current_char(&self) -> u8 {
-        if self.position - 1 >= self.input.len() {
-            return b'\0';
+    pub fn current_char(&self) -> char {
+        if self.position > self.input.len() {
+            return '\0';
         }
 
         self.input[self.position - 1]
     }
 
+    /// Whether `advance` went past the last character of the input.
+    fn at_end(&self) -> bool {
+        self.position > self.input.len()
+    }
+
+    /// The character after the current one, without advancing.
+    fn peek(&self) -> char {
+        self.input.get(self.position).copied().unwrap_or('\0')
+    }
+
+    fn location_of(&self, (line, column): (usize, usize)) -> Option<Location> {
+        Some(Location {
+            file: self.file_path.clone(),
+            line: line + 1,
+            column,
+        })
+    }
+
+    fn push(&mut self, begin_location: (usize, usize), location_size: usize, ty: TokenType) {
+        self.contents.push(Token {
+            begin_location,
+            location_size,
+            utf16_column: 0,
+            ty,
+        });
+    }
+
+    fn error(&mut self, message: String, location: (usize, usize), note: Option<String>) {
+        let mut diagnostic = Diagnostic::error(message).at(self.location_of(location));
+
+        if let Some(note) = note {
+            diagnostic = diagnostic.with_note(note);
+        }
+
+        self.diagnostics.push(diagnostic);
+    }
+
     pub fn get_token(&mut self) {
         let mut char = self.advance();
 
         loop {
-            if char != b' ' && char != b'\n' && char != b'\t' {
+            if !matches!(char, ' ' | '\n' | '\t' | '\r') {
                 break;
             }
 
@@ -262,101 +370,313 @@ impl Lexer {
             return self.get_number();
         }
 
-        if char == b'\"' {
+        if char == '\"' {
             return self.get_static_string();
         }
 
-        if char.is_ascii_alphabetic() {
-            return self.get_identifier();
+        if char == '\'' {
+            return self.get_char_literal();
         }
 
-        if char == b';' {
-            return self.contents.push(Token {
-                begin_location: self.location,
-                location_size: 1,
-                ty: TokenType::Semicolon,
-            });
+        if char == 'r' && matches!(self.peek(), '\"' | '#') {
+            return self.get_raw_string();
         }
 
-        if char == b'\0' {
-            return self.contents.push(Token {
-                begin_location: self.location,
-                location_size: 0,
-                ty: TokenType::EndOfFile,
-            });
+        if unicode::is_xid_start(char) {
+            return self.get_identifier();
         }
 
-        match SYMBOL_LIST.get(String::from_utf8(vec![char]).unwrap().as_str()) {
-            Some(token) => {
-                let second_char = self.advance();
-                match SYMBOL_LIST.get(String::from_utf8(vec![char, second_char]).unwrap().as_str())
-                {
-                    Some(second_token) => {
-                        if *second_token == TokenType::Comment {
-                            while char != b'\n' && char != b'\0' {
-                                char = self.advance();
-                            }
+        if char == ';' {
+            return self.push(self.location, 1, TokenType::Semicolon);
+        }
 
-                            return;
+        if char == '\0' {
+            return self.push(self.location, 0, TokenType::EndOfFile);
+        }
+
+        if let Some(token) = SYMBOL_LIST.get(char.to_string().as_str()) {
+            let location = self.location;
+            let second_char = self.advance();
+            match SYMBOL_LIST.get(format!("{}{}", char, second_char).as_str()) {
+                Some(second_token) => {
+                    if *second_token == TokenType::Comment {
+                        while char != '\n' && char != '\0' {
+                            char = self.advance();
                         }
 
-                        return self.contents.push(Token {
-                            begin_location: self.location,
-                            location_size: 2,
-                            ty: second_token.clone(),
-                        });
-                    }
-                    None => {
-                        self.go_back();
-
-                        return self.contents.push(Token {
-                            begin_location: self.location,
-                            location_size: 1,
-                            ty: token.clone(),
-                        });
+                        return;
                     }
+
+                    return self.push(location, 2, second_token.clone());
+                }
+                None => {
+                    self.go_back();
+
+                    return self.push(self.location, 1, token.clone());
                 }
             }
-            None => {}
         }
 
-        return self.contents.push(Token {
-            begin_location: self.location,
-            location_size: 1,
-            ty: TokenType::Character(char as char),
-        });
+        // Invalid UTF-8 was reported when decoding.
+        if char == char::REPLACEMENT_CHARACTER {
+            return;
+        }
+
+        if !char.is_ascii() {
+            return self.error(
+                format!("unexpected character `{}`", char),
+                self.location,
+                None,
+            );
+        }
+
+        self.push(self.location, 1, TokenType::Character(char));
     }
 
     pub fn get_static_string(&mut self) {
         let begin_location = self.location;
         let start_position = self.position;
 
-        let mut char = self.advance();
         let mut result = String::new();
-        while char != b'\"' {
-            result.push(char as char);
+        loop {
+            let char = self.advance();
+
+            if self.at_end() {
+                self.error(
+                    "unterminated string".to_string(),
+                    begin_location,
+                    Some("the file ends before its closing `\"`".to_string()),
+                );
+                break;
+            }
+
+            match char {
+                '\"' => break,
+                '\\' => {
+                    if let Some(escaped) = self.get_escape() {
+                        result.push(escaped);
+                    }
+                }
+                _ => result.push(char),
+            }
+        }
+
+        self.push(
+            begin_location,
+            self.position - start_position + 1,
+            TokenType::StaticString(result),
+        );
+    }
+
+    /// Reads a raw string, `r"..."` or `r#"..."#` with any number of `#`. It
+    /// may span lines and its contents are kept as they are, without escapes.
+    pub fn get_raw_string(&mut self) {
+        let begin_location = self.location;
+        let start_position = self.position;
+
+        let mut hashes = 0;
+        let mut char = self.advance();
+        while char == '#' {
+            hashes += 1;
             char = self.advance();
         }
 
-        self.contents.push(Token {
+        let mut result = String::new();
+        if char != '\"' {
+            self.error(
+                "expected `\"` to start the raw string".to_string(),
+                begin_location,
+                None,
+            );
+            self.go_back();
+        } else {
+            loop {
+                let char = self.advance();
+
+                if self.at_end() {
+                    self.error(
+                        "unterminated raw string".to_string(),
+                        begin_location,
+                        Some(format!(
+                            "it ends with `\"{}`, and the file ends before it",
+                            "#".repeat(hashes)
+                        )),
+                    );
+                    break;
+                }
+
+                let closes = self
+                    .input
+                    .get(self.position..self.position + hashes)
+                    .is_some_and(|rest| rest.iter().all(|c| *c == '#'));
+
+                if char == '\"' && closes {
+                    for _ in 0..hashes {
+                        self.advance();
+                    }
+                    break;
+                }
+
+                result.push(char);
+            }
+        }
+
+        self.push(
             begin_location,
-            location_size: self.position - start_position,
-            ty: TokenType::StaticString(result),
-        });
+            self.position - start_position + 1,
+            TokenType::StaticString(result),
+        );
+    }
+
+    /// Reads a character literal like `'a'`, `'é'` or `'\n'`.
+    pub fn get_char_literal(&mut self) {
+        let begin_location = self.location;
+        let start_position = self.position;
+
+        let char = self.advance();
+        let value = match char {
+            '\'' => {
+                self.error("empty character literal".to_string(), begin_location, None);
+                self.go_back();
+                None
+            }
+            '\\' => self.get_escape(),
+            '\n' | '\0' => {
+                self.go_back();
+                None
+            }
+            _ => Some(char),
+        };
+
+        if self.advance() != '\'' {
+            // Skips the rest of the literal, staying on its line.
+            let mut char = self.current_char();
+            while char != '\'' && char != '\n' && !self.at_end() {
+                char = self.advance();
+            }
+
+            if char == '\'' {
+                self.error(
+                    "character literals hold a single character".to_string(),
+                    begin_location,
+                    Some("use a string for more, like `\"...\"`".to_string()),
+                );
+            } else {
+                self.error(
+                    "unterminated character literal".to_string(),
+                    begin_location,
+                    Some("it needs a closing `'` on the same line".to_string()),
+                );
+                self.go_back();
+            }
+        }
+
+        self.push(
+            begin_location,
+            self.position - start_position + 1,
+            TokenType::CharLiteral(value.unwrap_or('\0')),
+        );
+    }
+
+    /// Decodes the escape sequence after a `\\`, reporting the invalid ones.
+    fn get_escape(&mut self) -> Option<char> {
+        let location = self.location;
+
+        let char = self.advance();
+        match char {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '\"' => Some('\"'),
+            '\'' => Some('\''),
+            'u' => self.get_unicode_escape(location),
+            _ if self.at_end() => {
+                self.go_back();
+                None
+            }
+            _ => {
+                self.error(
+                    format!("unknown escape `\\{}`", char),
+                    location,
+                    Some(
+                        "the escapes are `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\\"`, `\\'` and `\\u{...}`"
+                            .to_string(),
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    /// Decodes the `{...}` of a `\\u{...}` escape: one to six hexadecimal
+    /// digits naming a Unicode scalar value.
+    fn get_unicode_escape(&mut self, location: (usize, usize)) -> Option<char> {
+        if self.peek() != '{' {
+            self.error(
+                "`\\u` takes its code point in braces, like `\\u{1F600}`".to_string(),
+                location,
+                None,
+            );
+            return None;
+        }
+
+        self.advance();
+
+        let mut digits = String::new();
+        while self.peek().is_ascii_hexdigit() || self.peek() == '_' {
+            let char = self.advance();
+            if char != '_' {
+                digits.push(char);
+            }
+        }
+
+        if self.peek() != '}' {
+            self.error(
+                "unterminated `\\u{...}` escape".to_string(),
+                location,
+                Some("it takes hexadecimal digits and a closing `}`".to_string()),
+            );
+            return None;
+        }
+
+        self.advance();
+
+        if digits.is_empty() || digits.len() > 6 {
+            self.error(
+                "`\\u{...}` takes one to six hexadecimal digits".to_string(),
+                location,
+                None,
+            );
+            return None;
+        }
+
+        let value = u32::from_str_radix(&digits, 16).unwrap();
+        let result = char::from_u32(value);
+
+        if result.is_none() {
+            self.error(
+                format!("`\\u{{{}}}` isn't a Unicode scalar value", digits),
+                location,
+                Some("surrogates and values past `10FFFF` have no character".to_string()),
+            );
+        }
+
+        result
     }
 
+    /// Reads an identifier: a character with the XID_Start property followed by
+    /// XID_Continue ones, as in Unicode's UAX #31.
     pub fn get_identifier(&mut self) {
         let mut char = self.current_char();
-        while !char.is_ascii_alphanumeric() {
-            char = self.advance();
-        }
 
         let start_location = self.location;
         let start_position = self.position;
 
         let mut result_string = String::new();
-        while char.is_ascii_alphanumeric() || char == b'_' {
-            result_string.push(char as char);
+        while unicode::is_xid_continue(char) {
+            result_string.push(char);
             char = self.advance();
         }
 
@@ -365,57 +685,208 @@ impl Lexer {
             None => TokenType::Identifier(result_string),
         };
 
-        self.contents.push(Token {
-            begin_location: start_location,
-            location_size: self.position - start_position,
-            ty: token_ty,
-        });
+        self.push(start_location, self.position - start_position, token_ty);
 
-        self.position -= 1;
+        self.go_back();
     }
 
+    /// Reads a number literal: decimal, or hexadecimal, binary or octal after
+    /// `0x`, `0b` or `0o`, with `_` between digits. Decimals may have a fraction
+    /// and an exponent, and any of them a type suffix after a `_`.
     pub fn get_number(&mut self) {
-        let mut char = self.current_char();
-        let mut result = (vec![char], vec![]);
-
         let start_location = self.location;
         let start_position = self.position;
 
-        char = self.advance();
+        let (radix, name) = match (self.current_char(), self.peek()) {
+            ('0', 'x') => (16, "hexadecimal"),
+            ('0', 'b') => (2, "binary"),
+            ('0', 'o') => (8, "octal"),
+            _ => (10, "decimal"),
+        };
 
-        let mut decimal = false;
-        while char.is_ascii_digit() || char == b'_' {
-            if char == b'_' {
-                char = self.advance();
-                continue;
+        let mut digits = String::new();
+
+        if radix == 10 {
+            digits.push(self.current_char());
+        } else {
+            self.advance();
+        }
+
+        digits += &self.get_digits(radix, name);
+
+        if digits.is_empty() {
+            self.error(
+                format!("{} literals need at least one digit", name),
+                start_location,
+                None,
+            );
+        }
+
+        let mut fraction = String::new();
+        let mut exponent = 0i64;
+
+        if self.peek() == '.'
+            && self
+                .input
+                .get(self.position + 1)
+                .is_some_and(char::is_ascii_digit)
+        {
+            self.advance();
+            let location = self.location;
+
+            fraction = self.get_digits(10, "decimal");
+
+            if radix != 10 {
+                self.error(
+                    "only decimal literals have a fraction".to_string(),
+                    location,
+                    None,
+                );
             }
+        }
 
-            if char != b'.' {
-                match decimal {
-                    false => result.0.push(char),
-                    true => result.1.push(char),
-                };
-            } else {
-                decimal = true;
+        let signed = matches!(self.peek(), 'e' | 'E')
+            && match self.input.get(self.position + 1) {
+                Some('+' | '-') => self.input.get(self.position + 2),
+                next => next,
             }
+            .is_some_and(char::is_ascii_digit);
 
-            char = self.advance();
+        if radix == 10 && signed {
+            let location = self.location;
+            self.advance();
+
+            let negative = match self.peek() {
+                '-' => {
+                    self.advance();
+                    true
+                }
+                '+' => {
+                    self.advance();
+                    false
+                }
+                _ => false,
+            };
+
+            let value = self.get_digits(10, "decimal");
+
+            match value.parse::<i64>() {
+                Ok(value) if negative => exponent = -value,
+                Ok(value) => exponent = value,
+                Err(_) => self.error("this exponent is too large".to_string(), location, None),
+            }
         }
 
-        self.go_back();
+        if radix == 10 && !signed && matches!(self.peek(), 'e' | 'E') {
+            self.advance();
+            self.error(
+                "exponents need digits, like `1e3`".to_string(),
+                self.location,
+                None,
+            );
 
-        self.contents.push(Token {
-            begin_location: start_location,
-            location_size: self.position - start_position,
-            ty: TokenType::Number(
-                String::from_utf8(result.0).unwrap(),
-                if result.1.len() != 0 {
-                    Some(String::from_utf8(result.1).unwrap())
-                } else {
-                    None
-                },
-            ),
-        });
+            while self.peek().is_ascii_alphanumeric() {
+                self.advance();
+            }
+        }
+
+        let real = !fraction.is_empty() || (signed && radix == 10);
+
+        let mut suffix = None;
+
+        if self.peek() == '_' {
+            self.advance();
+
+            let mut name = String::new();
+            while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+                name.push(self.advance());
+            }
+
+            suffix = Some(name);
+        }
+
+        let scale = exponent
+            .checked_sub(fraction.len() as i64)
+            .unwrap_or(i64::MIN);
+        digits += &fraction;
+
+        let value = Exact::new(
+            Natural::from_digits(&digits, radix).unwrap_or_default(),
+            scale,
+        );
+
+        self.push(
+            start_location,
+            self.position - start_position + 1,
+            TokenType::Number {
+                value,
+                real,
+                suffix,
+            },
+        );
+    }
+
+    /// Reads the digits after the current character, skipping `_` separators
+    /// and stopping before a `_` that starts a type suffix. Decimal digits
+    /// also stop at an `e`, which may start an exponent. Only the first
+    /// invalid digit is reported.
+    fn get_digits(&mut self, radix: u32, name: &str) -> String {
+        let mut result = String::new();
+        let mut reported = false;
+
+        loop {
+            let char = self.peek();
+
+            if char == '_' {
+                // A suffix starts with a letter that isn't a digit, so `0xFF_UInt8` has
+                // one but `0x1_Bad` is the number `0x1BAD`.
+                let suffix = self
+                    .input
+                    .get(self.position + 1)
+                    .is_some_and(|c| c.is_ascii_alphabetic() && !c.is_digit(radix));
+
+                if suffix {
+                    break;
+                }
+
+                self.advance();
+                continue;
+            }
+
+            if !char.is_ascii_alphanumeric() || radix == 10 && matches!(char, 'e' | 'E') {
+                break;
+            }
+
+            self.advance();
+
+            if char.is_digit(radix) {
+                result.push(char);
+                continue;
+            }
+
+            if reported {
+                continue;
+            }
+
+            reported = true;
+
+            let note = match (char.is_ascii_alphabetic(), radix) {
+                (true, 16) => Some(
+                    "type suffixes follow a `_` and can't start with `A` to `F`, like `0xFF_UInt8`"
+                        .to_string(),
+                ),
+                (true, _) => Some("type suffixes follow a `_`, like `255_UInt8`".to_string()),
+                (false, _) => None,
+            };
+
+            self.error(
+                format!("`{}` isn't a digit of {} literals", char, name),
+                self.location,
+                note,
+            );
+        }
+
+        result
     }
 
     pub fn start(&mut self) {
@@ -423,12 +894,37 @@ impl Lexer {
             self.get_token();
         }
 
-        if self.contents.last().unwrap().ty != TokenType::EndOfFile {
-            self.contents.push(Token {
-                begin_location: self.location,
-                location_size: 0,
-                ty: TokenType::EndOfFile,
-            })
+        if self
+            .contents
+            .last()
+            .is_none_or(|token| token.ty != TokenType::EndOfFile)
+        {
+            self.push(self.location, 0, TokenType::EndOfFile)
+        }
+
+        self.set_utf16_columns();
+    }
+
+    /// Counts the column of every token in UTF-16 code units, which differs
+    /// from the one in characters after characters outside the BMP.
+    fn set_utf16_columns(&mut self) {
+        let mut line_starts = vec![0];
+        for (position, char) in self.input.iter().enumerate() {
+            if *char == '\n' {
+                line_starts.push(position + 1);
+            }
+        }
+
+        for token in &mut self.contents {
+            let (line, column) = token.begin_location;
+            let start = line_starts.get(line).copied().unwrap_or(self.input.len());
+            let end = (start + column.saturating_sub(1)).min(self.input.len());
+
+            token.utf16_column = self.input[start..end]
+                .iter()
+                .map(|char| char.len_utf16())
+                .sum::<usize>()
+                + 1;
         }
     }
 }
@@ -1,9 +1,38 @@
+pub mod analysis;
+pub mod diagnostic;
+pub mod formatter;
 pub mod lexer;
+pub mod loader;
 pub mod parser;
+pub mod syntax;
+pub mod unicode;
 
 #[cfg(test)]
 mod tests {
-    use crate::{lexer::Lexer, parser::Parser};
+    use std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    };
+
+    use crate::{
+        analysis::{
+            check_initialization, check_moves, check_mutability, check_ownership, check_trust,
+            monomorphize, resolve,
+            trust::trusted_code,
+            typecheck,
+            typecheck::{array_length, array_literals, fixed_bounds, range_bounds},
+        },
+        diagnostic::Diagnostic,
+        formatter::{format, format_file},
+        lexer::{Lexer, TokenType},
+        loader::{Loader, library_path},
+        parser::{
+            Parser,
+            ast::{Expression, TypeBody, TypeExpression, TypeSignature, UnsafeKind, VariableType},
+            number::{Exact, Natural},
+        },
+        syntax::{NodeKind, SyntaxElement, SyntaxNode, SyntaxTree, Trivia, TriviaKind},
+    };
 
     #[test]
     fn local_variables() {
@@ -13,4 +42,2078 @@ mod tests {
 
         dbg!(parser.ast);
     }
+
+    #[test]
+    fn generics() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/generics.ros", None));
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        let names = ast
+            .types
+            .keys()
+            .map(|signature| signature.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            [
+                "Integer",
+                "Option<tests::generics::Integer>",
+                "Option<tests::generics::Option<tests::generics::Integer>>"
+            ]
+        );
+
+        let instance = ast
+            .types
+            .iter()
+            .find(|(signature, _)| {
+                signature.name == "Option<tests::generics::Option<tests::generics::Integer>>"
+            })
+            .unwrap()
+            .1;
+
+        match &instance.ty {
+            TypeBody::Enum(variants) => {
+                assert_eq!(
+                    variants[1].fields[0].to_string(),
+                    "Option<tests::generics::Integer>"
+                )
+            }
+            _ => panic!("Expected an enum instance."),
+        }
+
+        let definitions = ast
+            .definitions
+            .keys()
+            .map(|signature| signature.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(definitions, ["Main", "Wrap<tests::generics::Integer>"]);
+
+        for variables in ast.variables.values() {
+            for variable in variables.values() {
+                let TypeExpression::Named(reference) = &variable.ty else {
+                    panic!("Expected a named type.");
+                };
+
+                assert!(reference.generics.is_empty());
+                assert!(reference.ty.name.starts_with("Option<"));
+            }
+        }
+    }
+
+    #[test]
+    fn recursive_generics() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/recursive_generics.ros", None));
+
+        let diagnostics = monomorphize(&parser.ast).unwrap_err();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0]
+                .message
+                .contains("unbounded recursive instantiation of `Nested`")
+        );
+    }
+
+    #[test]
+    fn distinct_instances() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/instances/main.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let ast = monomorphize(&ast).unwrap();
+
+        let mut names = ast
+            .types
+            .keys()
+            .map(|signature| signature.name.as_str())
+            .filter(|name| name.contains('<'))
+            .collect::<Vec<_>>();
+
+        names.sort();
+
+        assert_eq!(
+            names,
+            [
+                "Holder<instances::left::Shared>",
+                "Holder<instances::right::Shared>",
+                "Pair<instances::main::A, instances::main::B_C>",
+                "Pair<instances::main::A_B, instances::main::C>",
+            ]
+        );
+
+        let diagnostics = typecheck(&ast);
+
+        let messages = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "expected `Pair<instances::main::A_B, instances::main::C>`, \
+                 found `Pair<instances::main::A, instances::main::B_C>`",
+                "expected `Holder<instances::left::Shared>`, \
+                 found `Holder<instances::right::Shared>`",
+                "expected `Holder<instances::right::Shared>`, \
+                 found `Holder<instances::left::Shared>`",
+            ]
+        );
+    }
+
+    #[test]
+    fn packages() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/packages/main.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let paths = loader
+            .modules
+            .values()
+            .map(|module| module.rosario_path.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            paths,
+            [
+                "packages::main",
+                "packages::numbers",
+                "packages::shapes::shapes"
+            ]
+        );
+
+        let owners = loader
+            .parser
+            .ast
+            .types
+            .keys()
+            .map(|signature| format!("{}::{}", signature.owner, signature.name))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            owners,
+            [
+                "packages::numbers::Natural",
+                "packages::shapes::shapes::Side"
+            ]
+        );
+
+        let uses = &loader.parser.ast.uses["packages::main"];
+
+        assert_eq!(uses[0].path, ["numbers"]);
+        assert!(uses[0].glob);
+        assert_eq!(uses[1].path, ["shapes", "Side"]);
+    }
+
+    #[test]
+    fn package_cycle() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/cycle/a.ros");
+
+        assert_eq!(loader.diagnostics.len(), 1);
+        assert_eq!(
+            loader.diagnostics[0].message,
+            "import cycle between packages: cycle::a -> cycle::b -> cycle::a"
+        );
+    }
+
+    #[test]
+    fn resolution() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/resolution/main.ros");
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let types = ast
+            .variables
+            .values()
+            .flat_map(|variables| variables.values())
+            .filter_map(|variable| variable.ty.signature())
+            .map(|ty| format!("{}::{}", ty.owner, ty.name))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            types,
+            [
+                "resolution::left::Only_Left",
+                "resolution::right::Shared",
+                "resolution::main::Local",
+                "resolution::left::Counter",
+                "resolution::left::Counter"
+            ]
+        );
+
+        let mut loader = Loader::default();
+
+        loader.load("tests/resolution/errors.ros");
+
+        let mut ast = loader.parser.ast;
+        let diagnostics = resolve(&mut ast);
+
+        let messages = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "`Secret` is private to `resolution::right`",
+                "`Shared` is ambiguous",
+                "cannot find type `Only_Lef` in this scope"
+            ]
+        );
+
+        assert_eq!(diagnostics[1].notes.len(), 2);
+        assert_eq!(diagnostics[2].notes, ["did you mean `Only_Left`?"]);
+        assert_eq!(diagnostics[2].location.as_ref().unwrap().line, 11);
+
+        // Methods are only seen once their receiver's type is known.
+        let private = typecheck(&monomorphize(&ast).unwrap())
+            .into_iter()
+            .find(|diagnostic| diagnostic.message.contains("is private"))
+            .unwrap();
+
+        assert_eq!(private.message, "`Step` is private to `resolution::left`");
+        assert_eq!(private.location.unwrap().line, 13);
+    }
+
+    #[test]
+    fn aliases() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/aliases.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let ast = monomorphize(&ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+
+        // Aliases are gone, their uses name the types themselves.
+        let types = ast
+            .variables
+            .iter()
+            .filter(|(signature, _)| signature.name == "Main")
+            .flat_map(|(_, variables)| variables.values())
+            .filter_map(|variable| variable.ty.signature())
+            .map(|ty| ty.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(types, ["UInt8", "UInt8", "Small", "Int32", "NonZeroInt"]);
+
+        let small = TypeSignature {
+            owner: "tests::aliases".to_string(),
+            name: "Small".to_string(),
+        };
+
+        assert_eq!(range_bounds(&ast, &small), Some((1, 51)));
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/aliases_errors.ros", None));
+
+        let diagnostics = resolve(&mut parser.ast)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.location.unwrap().line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    "`Boxed` can only be another name for a type without generics".to_string(),
+                    6
+                ),
+                ("`Left` names a cycle of aliases".to_string(), 7),
+                ("`Right` names a cycle of aliases".to_string(), 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn operators() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/operators_errors.ros", None));
+
+        let diagnostics = std::mem::take(&mut parser.diagnostics)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.notes))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            [(
+                "`+` can't be defined by a type".to_string(),
+                vec!["the operators are `?`".to_string()]
+            )]
+        );
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        let diagnostics = typecheck(&ast)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.location.unwrap().line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(diagnostics, [("`Count` has no method `?`".to_string(), 10)]);
+    }
+
+    #[test]
+    fn scopes() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/scopes.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        let mut diagnostics = typecheck(&ast);
+        diagnostics.extend(check_initialization(&ast));
+        diagnostics.extend(check_moves(&ast));
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        // Every `a` declared in a block is gone after it, so `e` reads the first one.
+        let variables = ast
+            .variables
+            .iter()
+            .find(|(signature, _)| signature.name == "Main")
+            .unwrap()
+            .1
+            .iter()
+            .collect::<Vec<_>>();
+
+        let initializer = variables
+            .iter()
+            .find(|(_, variable)| variable.name == "e")
+            .and_then(|(_, variable)| variable.initializer)
+            .unwrap();
+
+        let Some(Expression::Variable(read)) = ast.find_expression(initializer) else {
+            panic!("Expected a variable.");
+        };
+
+        let first = variables
+            .iter()
+            .find(|(_, variable)| variable.name == "a")
+            .unwrap();
+
+        assert_eq!(read, first.0);
+    }
+
+    #[test]
+    fn type_checking() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/typecheck.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+
+        let bounds = |name: &str| {
+            let signature = TypeSignature {
+                owner: "tests::typecheck".to_string(),
+                name: name.to_string(),
+            };
+
+            range_bounds(&ast, &signature).unwrap()
+        };
+
+        assert_eq!(bounds("Int8"), (-128, 127));
+        assert_eq!(bounds("Precedence"), (3, 512));
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/typecheck_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+        let diagnostics = typecheck(&ast);
+
+        let messages = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "`Backwards` is an empty range",
+                "the upper bound of `Huge` overflows",
+                "`Guess` must return a `Natural` on every path",
+                "`300` doesn't fit in `Int8`",
+                "`Int8` can't be used with `Natural` in `+`",
+                "expected `Natural`, found `Int8`",
+                "`Identity` takes 1 argument but 2 were given",
+                "this constant expression divides by zero",
+                "`Main` doesn't return a value"
+            ]
+        );
+
+        assert_eq!(diagnostics[0].notes, ["`5` is more than `3`"]);
+        assert_eq!(diagnostics[1].location.as_ref().unwrap().line, 4);
+        assert_eq!(diagnostics[3].notes, ["`Int8` ranges from -128 to 127"]);
+        assert_eq!(diagnostics[3].location.as_ref().unwrap().line, 26);
+    }
+
+    #[test]
+    fn conversions() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/conversions.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+        let diagnostics = typecheck(&ast);
+
+        assert!(diagnostics.iter().all(|diagnostic| !diagnostic.is_error()));
+
+        let messages = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "converting `Negative` to `Percent` always fails",
+                "converting the integer `300` to `Percent` always clamps it to 100",
+                "converting `Int8` to `Int32` always succeeds"
+            ]
+        );
+    }
+
+    #[test]
+    fn mutability() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/mutability.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_mutability(&ast).is_empty());
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/mutability_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+        let diagnostics = check_mutability(&ast);
+
+        let mut messages = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>();
+
+        messages.sort();
+
+        assert_eq!(
+            messages,
+            [
+                "cannot assign to `later`, it isn't mutable",
+                "cannot assign to `maybe`, it isn't mutable",
+                "cannot assign to `repeated`, it isn't mutable",
+                "cannot assign to `self`, it isn't mutable",
+                "cannot assign to `total`, it isn't mutable",
+                "cannot assign to `value`, it isn't mutable",
+                "cannot call `Reset` on `total`, it isn't mutable",
+            ]
+        );
+
+        let later = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.message.contains("`later`"))
+            .unwrap();
+
+        assert_eq!(later.location.as_ref().unwrap().line, 28);
+        assert_eq!(
+            later.notes,
+            [
+                "`later` may already be assigned here, and it can only be assigned once",
+                "declare it with `let mutable later`"
+            ]
+        );
+    }
+
+    #[test]
+    fn assignments() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/assignments.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        let messages = |diagnostics: Vec<Diagnostic>| {
+            diagnostics
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            messages(typecheck(&ast)),
+            [
+                "`Counter` has no field `count`",
+                "`Counter` has no field `count`",
+                "`Counter` can't be dereferenced, it isn't a pointer",
+            ]
+        );
+
+        assert_eq!(
+            messages(check_mutability(&ast)),
+            [
+                "this expression can't be assigned to",
+                "this expression can't be assigned to",
+                "this expression can't be assigned to",
+                "this expression can't be assigned to",
+                "cannot assign to a field of `fixed`, it isn't mutable",
+            ]
+        );
+    }
+
+    #[test]
+    fn initialization() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/initialization.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_mutability(&ast).is_empty());
+        assert!(check_initialization(&ast).is_empty());
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/initialization_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+
+        let mut messages = check_initialization(&ast)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.is_error(), diagnostic.message))
+            .collect::<Vec<_>>();
+
+        messages.sort();
+
+        assert_eq!(
+            messages,
+            [
+                (false, "`unused` is never read".to_string()),
+                (true, "`late` may be read before it's assigned".to_string()),
+                (
+                    true,
+                    "`result` may be read before it's assigned".to_string()
+                ),
+                (true, "`side` may be read before it's assigned".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ownership() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/ownership.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_mutability(&ast).is_empty());
+        assert!(check_ownership(&ast).is_empty());
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/ownership_errors.ros", None));
+
+        let errors = parser
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.message.as_str(),
+                    diagnostic.location.as_ref().unwrap().line,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            errors,
+            [
+                ("the reference `count` can't be `mutable`", 56),
+                (
+                    "only `self` can be marked as a reference before its name",
+                    56
+                ),
+                ("the reference `aliased` can't be `mutable`", 69),
+            ]
+        );
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+
+        let mutability = check_mutability(&ast);
+
+        assert_eq!(mutability.len(), 1);
+        assert_eq!(
+            mutability[0].message,
+            "cannot take a `mutable &` reference to `fixed`, it isn't mutable"
+        );
+
+        let mut messages = check_ownership(&ast)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+
+        messages.sort();
+
+        assert_eq!(
+            messages,
+            [
+                "`found` doesn't live as long as `side`",
+                "`missing` is a reference, it needs a value when it's declared",
+                "a reference can't be used as a value",
+                "cannot call `Push` on `stack` while `top` points into it",
+                "cannot return a reference to `local`",
+                "expected a reference, found a value",
+                "this expression can't be referenced",
+            ]
+        );
+    }
+
+    #[test]
+    fn moves() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/moves.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_ownership(&ast).is_empty());
+        assert!(check_moves(&ast).is_empty());
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/moves_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+
+        let diagnostics = check_moves(&ast);
+
+        let twice = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.message == "`token` is used after it's moved")
+            .unwrap();
+
+        assert_eq!(
+            twice.location.as_ref().map(|location| location.line),
+            Some(28)
+        );
+        assert_eq!(twice.notes[0], "`token` is moved at 27:10");
+
+        // Fields of a borrowed value are bound by reference, they can't be moved out.
+        assert!(diagnostics.iter().any(|diagnostic| {
+            diagnostic.message == "cannot move out of `token`, it's a reference"
+                && diagnostic.location.as_ref().map(|location| location.line) == Some(66)
+        }));
+
+        // Binding a field that isn't `Copy` takes it out of the matched variable.
+        let unpacked = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.message == "`holder` may be used after it's moved")
+            .unwrap();
+
+        assert_eq!(
+            unpacked.location.as_ref().map(|location| location.line),
+            Some(81)
+        );
+        assert_eq!(unpacked.notes[0], "`holder` may have been moved at 76:11");
+
+        let mut messages = diagnostics
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+
+        messages.sort();
+
+        assert_eq!(
+            messages,
+            [
+                "`Handle` can't implement both `Copy` and `Drop`",
+                "`Shared` can't implement `Copy`, its fields need dropping",
+                "`holder` may be used after it's moved",
+                "`other` may be used after it's moved",
+                "`token` is used after it's moved",
+                "`token` may be used after it's moved",
+                "cannot move fields out of `Guard`, it implements `Drop`",
+                "cannot move out of `token`, it's a reference",
+                "cannot move out of `token`, it's a reference",
+            ]
+        );
+    }
+
+    #[test]
+    fn type_expressions() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/type_expressions.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let variables = parser
+            .ast
+            .variables
+            .values()
+            .flat_map(|variables| variables.values())
+            .map(|variable| (variable.name.as_str(), variable))
+            .collect::<BTreeMap<_, _>>();
+
+        let written = variables
+            .iter()
+            .map(|(name, variable)| format!("{}: {}", name, variable.ty))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            written,
+            [
+                "cursor: *Natural",
+                "first: *Natural",
+                "nested: array<Index of array<Index of *Natural>>",
+                "pair: Pair<Natural, Index>",
+                "second: *Natural",
+                "table: array<Index of Natural>",
+            ]
+        );
+
+        assert_eq!(variables["table"].variable_type, VariableType::Reference);
+        assert_eq!(
+            variables["cursor"].variable_type,
+            VariableType::MutableReference
+        );
+
+        assert_eq!(variables["first"].ty, variables["second"].ty);
+        assert_eq!(variables["cursor"].ty, variables["first"].ty);
+
+        let TypeExpression::Pointer(natural) = &variables["first"].ty else {
+            panic!("Expected a pointer.");
+        };
+
+        assert_ne!(
+            variables["first"].ty,
+            TypeExpression::Reference(natural.clone())
+        );
+
+        let TypeExpression::Array { index, element } = &variables["table"].ty else {
+            panic!("Expected an array.");
+        };
+
+        assert_eq!(element, natural);
+        assert_ne!(index, natural);
+    }
+
+    #[test]
+    fn trust() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/trust/main.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let diagnostics = check_trust(&ast)
+            .into_iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.unwrap();
+
+                format!(
+                    "{}:{}: {} ({})",
+                    location.line, location.column, diagnostic.message, diagnostic.notes[0]
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            [
+                "4:21: `C_LANG` can only be used in trusted packages \
+                 (`trust::main` is the main file, only packages can be trusted)",
+                "3:31: raw pointers can only be used in trusted packages \
+                 (`trust::plain` isn't trusted, it can be declared with `trusted package plain;`)",
+                "3:49: raw pointers can only be dereferenced in trusted packages \
+                 (`trust::plain` isn't trusted, it can be declared with `trusted package plain;`)",
+            ]
+        );
+
+        let trusted = trusted_code(&ast)
+            .into_iter()
+            .map(|(module, code)| {
+                let kinds = code.iter().map(|code| code.kind).collect::<Vec<_>>();
+                (module, kinds)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            trusted,
+            [(
+                "trust::memory",
+                vec![
+                    UnsafeKind::Pointer,
+                    UnsafeKind::Dereference,
+                    UnsafeKind::Pointer,
+                    UnsafeKind::Foreign,
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn foreign() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/foreign/main.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+        assert!(check_trust(&ast).is_empty());
+
+        assert_eq!(
+            ast.foreign_includes["foreign::memory"],
+            ["stdlib.h", "string.h"]
+        );
+
+        let declarations = ast.foreign.keys().collect::<Vec<_>>();
+
+        assert_eq!(declarations, ["free", "malloc", "strlen"]);
+
+        let ast = monomorphize(&ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_ownership(&ast).is_empty());
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/foreign_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        let mut messages = typecheck(&ast)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+
+        messages.sort();
+
+        assert_eq!(
+            messages,
+            [
+                "`malloc` takes 1 argument but 0 were given",
+                "expected `Byte`, found a pointer to `Byte`",
+                "expected a pointer to `Byte`, found `Size`",
+                "the C function `memcpy` isn't declared",
+            ]
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/arrays.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_mutability(&ast).is_empty());
+        assert!(check_ownership(&ast).is_empty());
+        assert!(check_moves(&ast).is_empty());
+
+        let literals = array_literals(&ast)
+            .values()
+            .map(|ty| (ty.to_string(), array_length(&ast, ty)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            literals,
+            [
+                ("array<Weekday of Count>".to_string(), Some(7)),
+                ("array<Digit of Digit>".to_string(), Some(10)),
+            ]
+        );
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/arrays_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+        let diagnostics = typecheck(&ast);
+
+        let messages = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "`array<Weekday of Count>` holds 7 elements but the literal has 3",
+                "`700` doesn't fit in `Count`",
+                "`0` doesn't fit in `Weekday`",
+                "`Count` can't be indexed, it isn't an array",
+                "arrays are indexed by range types, `Flag` isn't one",
+                "the type of an array literal must be known from where it's used",
+            ]
+        );
+
+        assert_eq!(diagnostics[2].notes, ["`Weekday` ranges from 1 to 7"]);
+    }
+
+    #[test]
+    fn loops() {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/loops.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_mutability(&ast).is_empty());
+        assert!(check_initialization(&ast).is_empty());
+        assert!(check_ownership(&ast).is_empty());
+        assert!(check_moves(&ast).is_empty());
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/loops_errors.ros", None));
+
+        let messages = |diagnostics: Vec<Diagnostic>| {
+            diagnostics
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            messages(resolve(&mut parser.ast)),
+            ["`Token` implements `Sized` without its `Length`"]
+        );
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert_eq!(
+            messages(typecheck(&ast)),
+            ["`Count` can't be iterated with `for`"]
+        );
+        assert_eq!(
+            messages(check_mutability(&ast)),
+            [
+                "cannot change `values` while it's iterated",
+                "cannot change `left` while it's iterated",
+            ]
+        );
+        assert_eq!(
+            messages(check_ownership(&ast)),
+            ["`Size` borrows its receiver, which is a value returned by a call"]
+        );
+        assert_eq!(
+            messages(check_moves(&ast)),
+            ["`token` is moved inside a loop"]
+        );
+    }
+
+    #[test]
+    fn library() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/groups.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+        assert!(check_trust(&ast).is_empty());
+
+        // The safe pointers the groups are built on share the traits of their kind.
+        for (name, implemented) in [
+            ("SingleAccess", "SingleAccessCore"),
+            ("ContainerAccess", "ContainerAccessCore"),
+        ] {
+            let access = TypeSignature {
+                owner: "core::access".to_string(),
+                name: name.to_string(),
+            };
+
+            assert!(ast.implements(&access, implemented));
+            assert!(ast.find_method(&access, "From_Pointer").is_some());
+        }
+
+        assert_eq!(
+            ast.traits["core::access::SingleAccessCore"],
+            ["New", "From", "From_Pointer", "Free", "Unwrap"]
+        );
+
+        let ast = monomorphize(&ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_mutability(&ast).is_empty());
+        assert!(check_initialization(&ast).is_empty());
+        assert!(check_ownership(&ast).is_empty());
+        assert!(check_moves(&ast).is_empty());
+
+        let vector = TypeSignature {
+            owner: "core::vector".to_string(),
+            name: "Vector<core::basic_types::Int32>".to_string(),
+        };
+
+        assert!(ast.implements(&vector, "Drop"));
+        assert!(ast.find_method(&vector, "Push").is_some());
+
+        // An element handed out by `Get` keeps the vector from growing while it's used.
+        let mut loader = Loader::default();
+
+        loader.load("tests/groups_errors.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let ast = monomorphize(&ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+
+        let diagnostics = check_ownership(&ast);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot call `Push` on `numbers` while `first` points into it"
+        );
+        assert_eq!(diagnostics[0].location.as_ref().unwrap().line, 13);
+        assert_eq!(
+            diagnostics[0].notes,
+            ["`first` is used afterwards at 17:23"]
+        );
+
+        // A map whose keys can't be hashed is reported where it's written, not in `core::map`.
+        let mut loader = Loader::default();
+
+        loader.load("tests/map_errors.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let diagnostics = monomorphize(&ast)
+            .unwrap_err()
+            .into_iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.unwrap();
+
+                assert!(location.file.ends_with("map_errors.ros"));
+                assert_eq!(diagnostic.notes, ["`Map` needs it of its parameter `K`"]);
+
+                (diagnostic.message, location.line)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            [
+                ("`Point` doesn't implement `Hash`".to_string(), 11),
+                ("`Point` doesn't implement `Equal`".to_string(), 11),
+                ("`Point` doesn't implement `Clone`".to_string(), 11),
+            ]
+        );
+
+        // The room of a group is only reached from trusted packages, so it's freed once.
+        let mut loader = Loader::default();
+
+        loader.load("tests/trusted_calls.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+        assert!(check_trust(&ast).is_empty());
+
+        let ast = monomorphize(&ast).unwrap();
+
+        let diagnostics = typecheck(&ast)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.location.unwrap().line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    "`Access` is trusted, it can only be called from trusted packages".to_string(),
+                    9
+                ),
+                ("`Access` is private to `core::vector`".to_string(), 9),
+                (
+                    "`Free` is trusted, it can only be called from trusted packages".to_string(),
+                    9
+                ),
+                (
+                    "`Vector` is a private variant of `Vector<core::basic_types::Int32>`"
+                        .to_string(),
+                    12
+                ),
+                (
+                    "`Free` is trusted, it can only be called from trusted packages".to_string(),
+                    12
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn generic_methods() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/generic_methods.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let ast = monomorphize(&ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_ownership(&ast).is_empty());
+        assert!(check_moves(&ast).is_empty());
+
+        let boxed = TypeSignature {
+            owner: "tests::generic_methods".to_string(),
+            name: "Box<core::basic_types::Int32>".to_string(),
+        };
+
+        // Each call gets an instance, named after the method and its own generics.
+        assert!(ast.find_method(&boxed, "Pair").is_none());
+        assert!(
+            ast.find_method(&boxed, "Pair<core::basic_types::UInt8>")
+                .is_some()
+        );
+        assert!(
+            ast.find_method(&boxed, "Pair<core::basic_types::Int64>")
+                .is_some()
+        );
+
+        let counter = TypeSignature {
+            owner: "tests::generic_methods".to_string(),
+            name: "Counter".to_string(),
+        };
+
+        assert!(
+            ast.find_method(&counter, "With<core::basic_types::Int8>")
+                .is_some()
+        );
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/generic_methods_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let diagnostics = monomorphize(&parser.ast)
+            .unwrap_err()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.location.unwrap().line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    "`Swap` expects 1 generic argument(s), found 0".to_string(),
+                    20
+                ),
+                (
+                    "`Swap` expects 1 generic argument(s), found 2".to_string(),
+                    21
+                ),
+                (
+                    "`Get` expects 0 generic argument(s), found 1".to_string(),
+                    22
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn strings() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/strings.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+        assert!(check_trust(&ast).is_empty());
+
+        let ast = monomorphize(&ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+        assert!(check_mutability(&ast).is_empty());
+        assert!(check_initialization(&ast).is_empty());
+        assert!(check_ownership(&ast).is_empty());
+        assert!(check_moves(&ast).is_empty());
+
+        assert_eq!(
+            ast.static_string().map(|ty| ty.owner.as_str()),
+            Some("core::strings")
+        );
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/strings_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+        let diagnostics = typecheck(&ast);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "string literals are `StaticString`s of the core library"
+        );
+        assert_eq!(
+            diagnostics[0]
+                .location
+                .as_ref()
+                .map(|location| location.column),
+            Some(5)
+        );
+
+        // Only the core library builds and takes apart its strings.
+        let mut loader = Loader::default();
+
+        loader.load("tests/private_variants.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let ast = monomorphize(&ast).unwrap();
+
+        let diagnostics = typecheck(&ast)
+            .into_iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.unwrap();
+                (diagnostic.message, location.line)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    "`StaticString` is a private variant of `StaticString`".to_string(),
+                    5
+                ),
+                ("`String` is a private variant of `String`".to_string(), 12),
+                ("`String` is a private variant of `String`".to_string(), 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn literals() {
+        let mut lexer = Lexer::from_file("tests/literals.ros", None);
+
+        lexer.start();
+
+        assert!(lexer.diagnostics.is_empty());
+
+        let literals = lexer
+            .contents
+            .into_iter()
+            .map(|token| token.ty)
+            .filter(|ty| *ty != TokenType::EndOfFile)
+            .collect::<Vec<_>>();
+
+        let string = |text: &str| TokenType::StaticString(text.to_string());
+
+        assert_eq!(
+            literals,
+            [
+                string("line\nbreak"),
+                string("tab\there"),
+                string("\"quoted\""),
+                string("back\\slash"),
+                string("H\u{1F600}"),
+                string("\0"),
+                TokenType::CharLiteral('a'),
+                TokenType::CharLiteral('é'),
+                TokenType::CharLiteral('\n'),
+                TokenType::CharLiteral('\''),
+                TokenType::CharLiteral('\u{7FFF}'),
+                string("raw \\n"),
+                string("a \"quoted\"\nraw"),
+            ]
+        );
+
+        let mut loader = Loader::default();
+
+        loader.load("tests/literals_errors.ros");
+
+        let diagnostics = loader
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.unwrap();
+                (diagnostic.message, location.line, location.column)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            [
+                ("unknown escape `\\q`".to_string(), 1, 10),
+                (
+                    "character literals hold a single character".to_string(),
+                    2,
+                    1
+                ),
+                ("empty character literal".to_string(), 2, 6),
+                (
+                    "`\\u{D800}` isn't a Unicode scalar value".to_string(),
+                    2,
+                    10
+                ),
+                (
+                    "`\\u{...}` takes one to six hexadecimal digits".to_string(),
+                    3,
+                    2
+                ),
+                (
+                    "`\\u` takes its code point in braces, like `\\u{1F600}`".to_string(),
+                    3,
+                    16
+                ),
+                ("unterminated character literal".to_string(), 4, 1),
+                ("unterminated raw string".to_string(), 5, 1),
+            ]
+        );
+
+        // Used to loop forever on the end of the file.
+        let mut lexer = Lexer::from(
+            b"\"unterminated".to_vec(),
+            "tests/literals.ros".to_string(),
+            None,
+        );
+
+        lexer.start();
+
+        assert_eq!(lexer.diagnostics.len(), 1);
+        assert_eq!(lexer.diagnostics[0].message, "unterminated string");
+    }
+
+    #[test]
+    fn numbers() {
+        let mut lexer = Lexer::from(
+            b"0.05 2.50 1.5e10 1e-40 1e100 0x1_0000_0000_0000_0000_0000_0000_0000_0000 12_UInt8 \
+              0x1_Bad"
+                .to_vec(),
+            "tests/numbers.ros".to_string(),
+            None,
+        );
+
+        lexer.start();
+
+        assert!(lexer.diagnostics.is_empty());
+
+        let numbers = lexer
+            .contents
+            .iter()
+            .filter_map(|token| match &token.ty {
+                TokenType::Number {
+                    value,
+                    real,
+                    suffix,
+                } => Some((value.to_string(), *real, suffix.as_deref())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            numbers,
+            [
+                ("0.05".to_string(), true, None),
+                ("2.5".to_string(), true, None),
+                ("15000000000".to_string(), true, None),
+                ("1e-40".to_string(), true, None),
+                ("1e100".to_string(), true, None),
+                (
+                    "340282366920938463463374607431768211456".to_string(),
+                    false,
+                    None
+                ),
+                ("12".to_string(), false, Some("UInt8")),
+                // `B` is a hexadecimal digit, so it can't start a suffix.
+                ("7085".to_string(), false, None),
+            ]
+        );
+
+        // Values order by what they're worth, not by their digits and exponents.
+        let hundred = Exact::new(Natural::from_digits("1", 10).unwrap(), 2);
+        let tenth = Exact::new(Natural::from_digits("1", 10).unwrap(), -1);
+
+        assert!(hundred > Exact::from(50));
+        assert!(Exact::from(99) < hundred);
+        assert!(tenth < Exact::from(1) && Exact::default() < tenth);
+        assert_eq!(Exact::from(100).cmp(&hundred), std::cmp::Ordering::Equal);
+
+        let mut lexer = Lexer::from(
+            b"0x 0b102 12abc 0x1.5 1e 0xF_Byte".to_vec(),
+            "tests/numbers.ros".to_string(),
+            None,
+        );
+
+        lexer.start();
+
+        let messages = lexer
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.as_ref().unwrap();
+                (diagnostic.message.as_str(), location.column)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                ("hexadecimal literals need at least one digit", 1),
+                ("`2` isn't a digit of binary literals", 8),
+                ("`a` isn't a digit of decimal literals", 12),
+                ("only decimal literals have a fraction", 19),
+                ("exponents need digits, like `1e3`", 23),
+                ("`y` isn't a digit of hexadecimal literals", 30),
+            ]
+        );
+
+        assert_eq!(
+            lexer.diagnostics[5].notes,
+            ["type suffixes follow a `_` and can't start with `A` to `F`, like `0xFF_UInt8`"]
+        );
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/numbers.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/numbers_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        let messages = typecheck(&ast)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "`256` doesn't fit in `Byte`",
+                "`2.5` isn't a whole number, so it can't be a `Byte`",
+                "`1e40` doesn't fit in `Byte`",
+                "number suffixes name number types, `Side` isn't one",
+            ]
+        );
+    }
+
+    #[test]
+    fn reals() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/reals.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let ast = monomorphize(&ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+
+        let money = ast.types.keys().find(|ty| ty.name == "Money").unwrap();
+        let (delta, min, max) = fixed_bounds(&ast, money).unwrap();
+
+        assert_eq!(
+            (delta.to_string(), min, max),
+            ("0.01".to_string(), -100_000_000, 100_000_000)
+        );
+
+        let tiny = ast.types.keys().find(|ty| ty.name == "Tiny").unwrap();
+
+        assert_eq!(
+            fixed_bounds(&ast, tiny).map(|(_, min, max)| (min, max)),
+            Some((0, 0))
+        );
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from_file("tests/reals_errors.ros", None));
+
+        assert!(resolve(&mut parser.ast).is_empty());
+
+        let ast = monomorphize(&parser.ast).unwrap();
+
+        let messages = typecheck(&ast)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "`Gap` is an empty range",
+                "`0.005` isn't a multiple of the delta of `Money`",
+                "`100.01` doesn't fit in `Money`",
+                "this constant can't be computed exactly as a `Money`",
+                "`1e40` doesn't fit in `Single`",
+                "`Money` doesn't support `*=`",
+                "`Money` doesn't support `mod`",
+                "expected `Count`, found a decimal number",
+                "`Money` can't be converted to `Count`",
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode() {
+        let mut loader = Loader::default();
+
+        loader.load("tests/unicode.ros");
+
+        assert!(loader.diagnostics.is_empty());
+
+        let mut ast = loader.parser.ast;
+
+        assert!(resolve(&mut ast).is_empty());
+
+        let ast = monomorphize(&ast).unwrap();
+
+        assert!(typecheck(&ast).is_empty());
+
+        // Columns count characters, and UTF-16 units for editors.
+        let mut lexer = Lexer::from(
+            "x = \"😀\" + größe;".as_bytes().to_vec(),
+            "tests/unicode.ros".to_string(),
+            None,
+        );
+
+        lexer.start();
+
+        assert!(lexer.diagnostics.is_empty());
+
+        let columns = lexer
+            .contents
+            .iter()
+            .map(|token| (token.ty.clone(), token.begin_location.1, token.utf16_column))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            columns,
+            [
+                (TokenType::Identifier("x".to_string()), 1, 1),
+                (TokenType::Equals, 3, 3),
+                (TokenType::StaticString("😀".to_string()), 5, 5),
+                (TokenType::Add, 9, 10),
+                (TokenType::Identifier("größe".to_string()), 11, 12),
+                (TokenType::Semicolon, 16, 17),
+                (TokenType::EndOfFile, 16, 17),
+            ]
+        );
+
+        let mut lexer = Lexer::from(
+            b"let a = 1;\n-- \xFF\xFE\nlet \xC3b = \xE2\x82;\nlet \xE2\x82\xAC = 2;".to_vec(),
+            "tests/unicode.ros".to_string(),
+            None,
+        );
+
+        lexer.start();
+
+        let diagnostics = lexer
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.as_ref().unwrap();
+                (diagnostic.message.as_str(), location.line, location.column)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            [
+                ("invalid UTF-8", 2, 4),
+                ("invalid UTF-8", 2, 5),
+                ("invalid UTF-8", 3, 5),
+                ("invalid UTF-8", 3, 10),
+                ("unexpected character `€`", 4, 5),
+            ]
+        );
+
+        assert_eq!(
+            lexer.diagnostics[0].notes,
+            ["found `0xFF`, source files must be UTF-8"]
+        );
+    }
+
+    /// Parses `source` as if it were the file at `path`, returning its syntax errors with
+    /// their lines.
+    fn parse_errors(path: &str, source: &str) -> Vec<(String, usize)> {
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from(
+            source.as_bytes().to_vec(),
+            path.to_string(),
+            None,
+        ));
+
+        parser
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.location.unwrap().line))
+            .collect()
+    }
+
+    #[test]
+    fn parser_errors() {
+        let source = "function Size(self) => Integer is 0;\n\
+                      \n\
+                      function Length() => Integer is\n\
+                      begin\n\
+                      \x20   return self;\n\
+                      end Length;\n";
+
+        assert_eq!(
+            parse_errors("tests/mutability_errors.ros", source),
+            [
+                ("`self` can only be a parameter of a method".to_string(), 1),
+                ("`self` is only defined in methods".to_string(), 5),
+            ]
+        );
+
+        // The missing `;` is assumed, so the statements after it are still parsed.
+        let source = "procedure Main() is\n\
+                      begin\n\
+                      \x20   let a: Integer = 1\n\
+                      \x20   if a > 0 then\n\
+                      \x20       a = 2\n\
+                      \x20   end if;\n\
+                      \x20   let b: Integer = a\n\
+                      end Main;\n";
+
+        let mut parser = Parser::default();
+
+        parser.start(Lexer::from(
+            source.as_bytes().to_vec(),
+            "tests/local_variables.ros".to_string(),
+            None,
+        ));
+
+        let errors = parser
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.as_ref().unwrap();
+                (diagnostic.message.as_str(), location.line, location.column)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            errors,
+            [
+                ("expected `;` after this statement, found `if`", 4, 5),
+                ("expected `;` after this statement, found `end`", 6, 5),
+                ("expected `;` after this statement, found `end`", 8, 1),
+            ]
+        );
+
+        let main = *parser.ast.definitions.values().next().unwrap();
+
+        let Some(Expression::Body(body)) = parser.ast.find_expression(main) else {
+            panic!("expected a body");
+        };
+
+        assert_eq!(body.contents.len(), 3);
+
+        // Other syntax errors stop the parse of their file.
+        let sources = [
+            "procedure Main() is\nbegin\n    let x: Integer = ;\nend Main;\n",
+            "procedure Main() is\nbegin\nend Foo;\n",
+            "procedure Main() is\nbegin\n    @\nend Main;\n",
+            "procedure Main is\nbegin\nend Main;\n",
+            "type T is range 0..1;\n\nimplement T\n    mutable procedure Reset(mutable self) is \
+             self = 0;\nend implement;\n",
+        ];
+
+        assert_eq!(
+            sources.map(|source| parse_errors("tests/local_variables.ros", source)),
+            [
+                [("expected an expression, found `;`".to_string(), 3)],
+                [("`end Foo` doesn't close `Main`".to_string(), 3)],
+                [("expected an expression, found `@`".to_string(), 3)],
+                [("expected `(`, found `is`".to_string(), 1)],
+                [("expected `operator`, found `procedure`".to_string(), 4)],
+            ]
+        );
+    }
+
+    fn ros_files(folder: &Path) -> Vec<PathBuf> {
+        let mut result = vec![];
+
+        for entry in std::fs::read_dir(folder).unwrap() {
+            let path = entry.unwrap().path();
+
+            if path.is_dir() {
+                result.extend(ros_files(&path));
+            } else if path.extension().is_some_and(|extension| extension == "ros") {
+                result.push(path);
+            }
+        }
+
+        result
+    }
+
+    #[test]
+    fn syntax() {
+        let mut paths = ros_files(Path::new("tests"));
+        paths.extend(ros_files(&library_path()));
+
+        for path in paths {
+            let source = std::fs::read_to_string(&path).unwrap();
+            let tree = SyntaxTree::parse(Lexer::from_file(path.to_str().unwrap(), None));
+
+            assert_eq!(tree.root.to_string(), source, "{}", path.display());
+
+            // Whitespace is trivia, never part of a token, and only what the lexer
+            // reported is skipped.
+            if tree.diagnostics.is_empty() {
+                for token in tree.root.tokens() {
+                    assert_eq!(token.text.trim(), token.text, "{}", path.display());
+                    assert!(
+                        (token.leading.iter().chain(&token.trailing))
+                            .all(|trivia| trivia.kind != TriviaKind::Skipped),
+                        "{}",
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        let source = "--- Doubles `x`.\r\n\
+                      function Double(x: Integer) => Integer is  x * 2; -- Twice.\n\
+                      \n\
+                      procedure Main() is\n\
+                      begin\n\
+                      \x20   if Double(1) != 2 then Abort(); end if;\n\
+                      end Main; \u{20AC}";
+
+        let lexer = Lexer::from(
+            source.as_bytes().to_vec(),
+            "tests/unicode.ros".to_string(),
+            None,
+        );
+        let tree = SyntaxTree::parse(lexer);
+
+        assert_eq!(tree.root.to_string(), source);
+        assert_eq!(tree.diagnostics.len(), 1);
+
+        let SyntaxElement::Node(double) = &tree.root.children[0] else {
+            panic!("expected a declaration");
+        };
+
+        assert_eq!(double.kind, NodeKind::Statement);
+        assert_eq!(double.documentation(), ["Doubles `x`."]);
+
+        let tokens = double.tokens();
+        let semicolon = tokens.last().unwrap();
+
+        assert_eq!(
+            tokens[0].leading,
+            [
+                Trivia {
+                    kind: TriviaKind::DocComment,
+                    text: "--- Doubles `x`.".to_string()
+                },
+                Trivia {
+                    kind: TriviaKind::Newline,
+                    text: "\r\n".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            semicolon.trailing,
+            [
+                Trivia {
+                    kind: TriviaKind::Whitespace,
+                    text: " ".to_string()
+                },
+                Trivia {
+                    kind: TriviaKind::Comment,
+                    text: "-- Twice.".to_string()
+                },
+            ]
+        );
+
+        let SyntaxElement::Node(main) = &tree.root.children[1] else {
+            panic!("expected a declaration");
+        };
+
+        assert!(main.documentation().is_empty());
+
+        // The `begin` block holds the `if`, whose `then` block holds the call.
+        let kinds = |node: &SyntaxNode| {
+            node.children
+                .iter()
+                .map(|child| match child {
+                    SyntaxElement::Node(node) => format!("{:?}", node.kind),
+                    SyntaxElement::Token(token) => token.text.clone(),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds(main),
+            [
+                "procedure",
+                "Main",
+                "Group",
+                "is",
+                "begin",
+                "Block",
+                "end",
+                "Main",
+                ";"
+            ]
+        );
+
+        let SyntaxElement::Node(body) = &main.children[5] else {
+            panic!("expected a block");
+        };
+        let SyntaxElement::Node(statement) = &body.children[0] else {
+            panic!("expected a statement");
+        };
+
+        assert_eq!(
+            kinds(statement),
+            [
+                "if", "Double", "Group", "!=", "2", "then", "Block", "end", "if", ";"
+            ]
+        );
+
+        let SyntaxElement::Token(end) = tree.root.children.last().unwrap() else {
+            panic!("expected the end of the file");
+        };
+
+        assert_eq!(end.ty, TokenType::EndOfFile);
+        assert_eq!(
+            end.leading,
+            [Trivia {
+                kind: TriviaKind::Skipped,
+                text: "\u{20AC}".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn formatter() {
+        let source = "\n\
+            use core::*;\n\
+            --- Doubles `x`.\n\
+            function Double( x : Integer )=>Integer is x*2 ;   -- Twice.\n\
+            type Small is range 0 .. 10;\n\
+            type Pair<T> is\n\
+            \x20 enum\n\
+            \x20     Pair(T,T)  ,\n\
+            \x20     end Pair ;\n\
+            \n\
+            \n\
+            procedure Main() is\n\
+            begin\n\
+            \n\
+            \x20 let values : Vector < Integer > =Vector<Integer>::New();\n\
+            \x20       let total: Integer = -Double(2)+3 ** 2;\n\
+            \x20 if total!=5 then\n\
+            Abort();\n\
+            \x20      -- Unreachable.\n\
+            \n\
+            \x20 elsif total<0 then\n\
+            \x20     Abort( ) ;\n\
+            \x20 else\n\
+            \x20   match total==5 is\n\
+            \x20    True=>begin end,\n\
+            \x20           _ => Abort(),\n\
+            \x20   end match;\n\
+            \x20 end if;\n\
+            \x20 let pointer : &Integer = &total;\n\
+            \x20 let negated: Integer = -  -total;\n\
+            \n\
+            \n\
+            \x20  -- Done.\n\
+            end Main ;\n\
+            -- End of file.\n\
+            \n";
+
+        let expected = "\
+            use core::*;\n\
+            --- Doubles `x`.\n\
+            function Double(x: Integer) => Integer is x * 2; -- Twice.\n\
+            type Small is range 0..10;\n\
+            type Pair<T> is\n\
+            enum\n\
+            \x20   Pair(T, T),\n\
+            end Pair;\n\
+            \n\
+            procedure Main() is\n\
+            begin\n\
+            \x20   let values: Vector<Integer> = Vector<Integer>::New();\n\
+            \x20   let total: Integer = -Double(2) + 3 ** 2;\n\
+            \x20   if total != 5 then\n\
+            \x20       Abort();\n\
+            \x20       -- Unreachable.\n\
+            \x20   elsif total < 0 then\n\
+            \x20       Abort();\n\
+            \x20   else\n\
+            \x20       match total == 5 is\n\
+            \x20           True => begin end,\n\
+            \x20           _ => Abort(),\n\
+            \x20       end match;\n\
+            \x20   end if;\n\
+            \x20   let pointer: &Integer = &total;\n\
+            \x20   let negated: Integer = - -total;\n\
+            \n\
+            \x20   -- Done.\n\
+            end Main;\n\
+            -- End of file.\n";
+
+        let format_source = |source: &str| {
+            let lexer = Lexer::from(
+                source.as_bytes().to_vec(),
+                "tests/unicode.ros".to_string(),
+                None,
+            );
+
+            format(&SyntaxTree::parse(lexer).root)
+        };
+
+        assert_eq!(format_source(source), expected);
+
+        // Formatting is idempotent, and core is formatted.
+        for path in ros_files(Path::new("tests")) {
+            let formatted = format_source(&std::fs::read_to_string(&path).unwrap());
+
+            assert_eq!(format_source(&formatted), formatted, "{}", path.display());
+        }
+
+        for path in ros_files(&library_path()) {
+            let path = path.to_str().unwrap();
+
+            assert_eq!(
+                format_file(path).unwrap(),
+                std::fs::read_to_string(path).unwrap(),
+                "{}",
+                path
+            );
+        }
+    }
 }
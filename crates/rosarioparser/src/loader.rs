@@ -0,0 +1,229 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    diagnostic::Diagnostic,
+    lexer::Lexer,
+    parser::{Parser, ast::Package},
+};
+
+/// A parsed file and the files it declared with `package`.
+#[derive(Debug, Default, Clone)]
+pub struct Module {
+    pub path: PathBuf,
+    pub rosario_path: String,
+    pub packages: Vec<PathBuf>,
+}
+
+/// Folder of the standard library, `ROSARIO_LIBRARY` or the `core/` folder of this
+/// repository.
+pub fn library_path() -> PathBuf {
+    let path = match std::env::var_os("ROSARIO_LIBRARY") {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../../core")),
+    };
+
+    path.canonicalize().unwrap_or(path)
+}
+
+/// Parses a file and, recursively, every package it declares into a single `Ast`.
+///
+/// `package x;` is either `x.ros` next to the declaring file, which shares its Rosario
+/// path, or the folder `x/` whose entry file is `x/x.ros`, which adds `x` to the path.
+/// Files using `core::...` without declaring a `core` package get the standard library.
+#[derive(Debug, Default, Clone)]
+pub struct Loader {
+    pub parser: Parser,
+    pub modules: BTreeMap<PathBuf, Module>,
+    pub diagnostics: Vec<Diagnostic>,
+    stack: Vec<(PathBuf, String)>,
+    /// Rosario path of the standard library, once it's loaded.
+    library: Option<String>,
+}
+
+impl Loader {
+    pub fn load(&mut self, path: &str) {
+        self.load_lexer(Lexer::from_file(path, None));
+    }
+
+    fn load_lexer(&mut self, lexer: Lexer) {
+        let path = PathBuf::from(&lexer.file_path);
+        let rosario_path = format!("{}::{}", lexer.main_rosario_path, lexer.file_name);
+
+        if let Some(index) = self.stack.iter().position(|(p, _)| *p == path) {
+            let cycle = self.stack[index..]
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .chain([rosario_path.as_str()])
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            self.diagnostics.push(Diagnostic::error(format!(
+                "import cycle between packages: {}",
+                cycle
+            )));
+
+            return;
+        }
+
+        if self.modules.contains_key(&path) {
+            return;
+        }
+
+        let folder = PathBuf::from(&lexer.folder_path);
+        let main_rosario_path = lexer.main_rosario_path.clone();
+
+        self.stack.push((path.clone(), rosario_path.clone()));
+        self.parser.start(lexer);
+        self.diagnostics.append(&mut self.parser.lexer.diagnostics);
+        self.diagnostics.append(&mut self.parser.diagnostics);
+
+        let packages = self
+            .parser
+            .ast
+            .packages
+            .get(&rosario_path)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut children = vec![];
+
+        for (i, package) in packages.iter().enumerate() {
+            let Some(lexer) = self.package_lexer(&folder, &main_rosario_path, package) else {
+                continue;
+            };
+
+            self.parser.ast.packages.get_mut(&rosario_path).unwrap()[i].module =
+                format!("{}::{}", lexer.main_rosario_path, lexer.file_name);
+
+            children.push(PathBuf::from(&lexer.file_path));
+
+            self.load_lexer(lexer);
+        }
+
+        self.add_library(&rosario_path);
+
+        self.stack.pop();
+
+        self.modules.insert(
+            path.clone(),
+            Module {
+                path,
+                rosario_path,
+                packages: children,
+            },
+        );
+    }
+
+    /// Declares the standard library as the `core` package of `module` when it uses it.
+    fn add_library(&mut self, module: &str) {
+        let ast = &self.parser.ast;
+
+        let Some(import) = ast
+            .uses
+            .get(module)
+            .into_iter()
+            .flatten()
+            .find(|import| import.path.first().is_some_and(|segment| segment == "core"))
+            .cloned()
+        else {
+            return;
+        };
+
+        let declared = ast
+            .packages
+            .get(module)
+            .is_some_and(|packages| packages.iter().any(|package| package.name == "core"));
+
+        if declared {
+            return;
+        }
+
+        if self.library.is_none() {
+            let entry = library_path().join("core.ros");
+
+            if !entry.is_file() {
+                self.diagnostics.push(
+                    Diagnostic::error("the standard library wasn't found".to_string())
+                        .at(Some(import.location.clone()))
+                        .with_note(format!(
+                            "expected `{}`, `ROSARIO_LIBRARY` can point to its folder",
+                            entry.display()
+                        )),
+                );
+
+                return;
+            }
+
+            let lexer = Lexer::from_file(entry.to_str().unwrap(), None);
+            self.library = Some(format!("{}::{}", lexer.main_rosario_path, lexer.file_name));
+
+            self.load_lexer(lexer);
+        }
+
+        let Some(library) = self.library.clone() else {
+            return;
+        };
+
+        self.parser
+            .ast
+            .packages
+            .entry(module.to_string())
+            .or_default()
+            .push(Package {
+                name: "core".to_string(),
+                module: library,
+                library: true,
+                location: import.location,
+                ..Default::default()
+            });
+    }
+
+    fn package_lexer(
+        &mut self,
+        folder: &Path,
+        main_rosario_path: &str,
+        package: &Package,
+    ) -> Option<Lexer> {
+        let file = folder.join(format!("{}.ros", package.name));
+        let directory = folder.join(&package.name);
+        let entry = directory.join(format!("{}.ros", package.name));
+
+        let error = match (file.is_file(), directory.is_dir()) {
+            (true, false) => {
+                let mut lexer = Lexer::from_file(file.to_str().unwrap(), None);
+                lexer.main_rosario_path = main_rosario_path.to_string();
+
+                return Some(lexer);
+            }
+            (false, true) if entry.is_file() => {
+                return Some(Lexer::from_file(
+                    entry.to_str().unwrap(),
+                    Some(main_rosario_path.to_string()),
+                ));
+            }
+            (true, true) => Diagnostic::error(format!("package `{}` is ambiguous", package.name))
+                .with_note(format!(
+                    "both `{}` and `{}` exist",
+                    file.display(),
+                    directory.display()
+                )),
+            (false, true) => {
+                Diagnostic::error(format!("package `{}` has no entry file", package.name))
+                    .with_note(format!("expected `{}`", entry.display()))
+            }
+            (false, false) => {
+                Diagnostic::error(format!("package `{}` wasn't found", package.name)).with_note(
+                    format!("expected `{}` or `{}`", file.display(), directory.display()),
+                )
+            }
+        };
+
+        self.diagnostics
+            .push(error.at(Some(package.location.clone())));
+
+        None
+    }
+}
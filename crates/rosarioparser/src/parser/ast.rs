@@ -1,26 +1,56 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use crate::{diagnostic::Location, parser::number::Exact};
 
 #[derive(Debug, Default, Clone)]
 pub struct Ast {
     pub definitions: BTreeMap<DefinitionSignature, ExpressionId>,
     pub types: BTreeMap<TypeSignature, RosarioType>,
-    pub uses: BTreeMap<String, Vec<TypeSignature>>,
+    pub packages: BTreeMap<String, Vec<Package>>,
+    pub uses: BTreeMap<String, Vec<Use>>,
     pub expressions: BTreeMap<ExpressionId, Expression>,
     pub variables: BTreeMap<DefinitionSignature, BTreeMap<VariableId, Variable>>,
+    pub locations: BTreeMap<ExpressionId, Location>,
+    /// Full Rosario paths (`owner::Name`) of the items declared `public`.
+    pub public_items: BTreeSet<String>,
+    pub item_locations: BTreeMap<String, Location>,
+    /// Traits implemented by each type with `implement Trait for Type`, by name.
+    pub implementations: BTreeMap<TypeSignature, BTreeSet<String>>,
+    /// Raw pointers and `C_LANG` found while parsing, only trusted packages may use them.
+    pub unsafe_code: Vec<UnsafeCode>,
+    /// Rosario path of each parsed file, by its path on disk.
+    pub modules: BTreeMap<String, String>,
+    /// Headers included with `use C_LANG("header");`, by the Rosario path of the file.
+    pub foreign_includes: BTreeMap<String, Vec<String>>,
+    /// C functions declared with `C_LANG function`, by their C name. C names are global, so
+    /// a declaration is seen from every module.
+    pub foreign: BTreeMap<String, DefinitionSignature>,
+    /// Definitions each trait declares, by the full Rosario path of the trait.
+    pub traits: BTreeMap<String, Vec<String>>,
     expression_id: ExpressionId,
     variable_id: VariableId,
 }
 
 impl Ast {
-    pub fn new_body(&mut self, contents: Vec<ExpressionId>) -> ExpressionId {
+    pub fn new_expression(&mut self, expression: Expression) -> ExpressionId {
         self.expression_id.0 += 1;
 
-        self.expressions
-            .insert(self.expression_id, Expression::Body(Body { contents }));
+        self.expressions.insert(self.expression_id, expression);
 
         self.expression_id
     }
 
+    pub fn new_body(&mut self, contents: Vec<ExpressionId>) -> ExpressionId {
+        self.new_expression(Expression::Body(Body { contents }))
+    }
+
+    pub fn new_call(&mut self, call: Call) -> ExpressionId {
+        self.new_expression(Expression::Call(call))
+    }
+
     pub fn new_definition(&mut self, signature: DefinitionSignature, body: ExpressionId) {
         self.definitions.insert(signature, body);
     }
@@ -29,13 +59,12 @@ impl Ast {
         self.types.insert(TypeSignature { owner, name }, ty);
     }
 
-    pub fn new_number(&mut self, natural: u128, decimal: Option<u128>) -> ExpressionId {
-        self.expression_id.0 += 1;
-
-        self.expressions
-            .insert(self.expression_id, Expression::Number(natural, decimal));
+    pub fn new_number(&mut self, number: Number) -> ExpressionId {
+        self.new_expression(Expression::Number(number))
+    }
 
-        self.expression_id
+    pub fn new_unary_operator(&mut self, op: UnOpType, operand: ExpressionId) -> ExpressionId {
+        self.new_expression(Expression::UnaryOperation(UnOp { op, operand }))
     }
 
     pub fn new_binary_operator(
@@ -44,20 +73,116 @@ impl Ast {
         left: ExpressionId,
         right: ExpressionId,
     ) -> ExpressionId {
-        self.expression_id.0 += 1;
-
-        self.expressions.insert(
-            self.expression_id,
-            Expression::BinaryOperation(BinOp { op, left, right }),
-        );
-
-        self.expression_id
+        self.new_expression(Expression::BinaryOperation(BinOp { op, left, right }))
     }
 
     pub fn find_expression(&self, id: ExpressionId) -> Option<&Expression> {
         self.expressions.get(&id)
     }
 
+    pub fn find_location(&self, id: ExpressionId) -> Option<Location> {
+        self.locations.get(&id).cloned()
+    }
+
+    pub fn find_variable_location(&self, id: VariableId) -> Option<Location> {
+        self.expressions
+            .iter()
+            .find(|(_, expression)| **expression == Expression::NewVariable(id))
+            .and_then(|(id, _)| self.find_location(*id))
+    }
+
+    pub fn find_variable_by_id(&self, id: VariableId) -> Option<&Variable> {
+        self.variables
+            .values()
+            .find_map(|variables| variables.get(&id))
+    }
+
+    /// Folds a constant integer expression, `None` if it isn't constant or overflows.
+    ///
+    /// `Type::Min()` and `Type::Max()` fold to the bounds of the range type `Type`.
+    pub fn evaluate(&self, id: ExpressionId) -> Option<i128> {
+        self.evaluate_within(id, &mut vec![])
+    }
+
+    /// Folds `id` inside the bounds of the range types of `within`, which can't name
+    /// themselves again.
+    fn evaluate_within(&self, id: ExpressionId, within: &mut Vec<TypeSignature>) -> Option<i128> {
+        match self.find_expression(id)? {
+            Expression::Number(Number {
+                value,
+                real: false,
+                suffix: None,
+            }) => value.integer(),
+            Expression::UnaryOperation(un_op) => {
+                let operand = self.evaluate_within(un_op.operand, within)?;
+
+                match un_op.op {
+                    UnOpType::Minus => operand.checked_neg(),
+                    UnOpType::Plus => Some(operand),
+                    UnOpType::Dereference | UnOpType::Reference => None,
+                }
+            }
+            Expression::BinaryOperation(bin_op) => {
+                let left = self.evaluate_within(bin_op.left, within)?;
+                let right = self.evaluate_within(bin_op.right, within)?;
+
+                match bin_op.op {
+                    BinOpType::Add => left.checked_add(right),
+                    BinOpType::Sub => left.checked_sub(right),
+                    BinOpType::Mul => left.checked_mul(right),
+                    BinOpType::Div => left.checked_div(right),
+                    BinOpType::ToThePowerOf => left.checked_pow(u32::try_from(right).ok()?),
+                    BinOpType::Mod => left.checked_rem(right),
+                    _ => None,
+                }
+            }
+            Expression::Associated(associated) if associated.args.is_empty() => {
+                let ty = &associated.ty.ty;
+                let TypeBody::Range(range) = &self.types.get(ty)?.ty else {
+                    return None;
+                };
+
+                let bound = match associated.name.as_str() {
+                    "Min" => range.min,
+                    "Max" => range.max,
+                    _ => return None,
+                };
+
+                if within.contains(ty) {
+                    return None;
+                }
+
+                within.push(ty.clone());
+                let value = self.evaluate_within(bound, within);
+                within.pop();
+
+                value
+            }
+            _ => None,
+        }
+    }
+
+    /// Folds a constant real number, a number literal or its negation, as its sign and value.
+    pub fn evaluate_real(&self, id: ExpressionId) -> Option<(bool, Exact)> {
+        match self.find_expression(id)? {
+            Expression::Number(Number {
+                value,
+                suffix: None,
+                ..
+            }) => Some((false, value.clone())),
+            Expression::UnaryOperation(un_op) => {
+                let (negative, value) = self.evaluate_real(un_op.operand)?;
+
+                match un_op.op {
+                    UnOpType::Minus => Some((!negative, value)),
+                    UnOpType::Plus => Some((negative, value)),
+                    UnOpType::Dereference | UnOpType::Reference => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
     pub fn find_mut_expression(&mut self, id: ExpressionId) -> Option<&mut Expression> {
         self.expressions.get_mut(&id)
     }
@@ -67,17 +192,232 @@ impl Ast {
         signature: DefinitionSignature,
         variable: Variable,
     ) -> ExpressionId {
-        self.expression_id.0 += 1;
         self.variable_id.0 += 1;
 
-        let def = self.variables.entry(signature).or_insert(BTreeMap::new());
+        let def = self.variables.entry(signature).or_default();
         def.insert(self.variable_id, variable);
 
-        self.expression_id
+        self.new_expression(Expression::NewVariable(self.variable_id))
+    }
+
+    /// Registers a variable bound by a pattern, declared at `location`.
+    pub fn new_binding(
+        &mut self,
+        signature: DefinitionSignature,
+        variable: Variable,
+        location: Location,
+    ) -> VariableId {
+        let id = self.new_variable(signature, variable);
+        self.locations.insert(id, location);
+
+        self.variable_id
+    }
+
+    /// Registers the arguments of `signature`, returning it with their ids in `args`.
+    pub fn new_arguments(
+        &mut self,
+        mut signature: DefinitionSignature,
+        arguments: Vec<(Variable, Location)>,
+    ) -> DefinitionSignature {
+        let first = self.variable_id.0 + 1;
+
+        signature.args = (first..first + arguments.len() as u64)
+            .map(VariableId)
+            .collect();
+
+        for (variable, location) in arguments {
+            let id = self.new_variable(signature.clone(), variable);
+            self.locations.insert(id, location);
+        }
+
+        signature
+    }
+
+    pub fn find_variable(&self, signature: &DefinitionSignature, name: &str) -> Option<VariableId> {
+        self.variables
+            .get(signature)?
+            .iter()
+            .rev()
+            .find(|(_, variable)| variable.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Definition called by `call`, the first one named like it when its owner isn't resolved.
+    pub fn find_definition(&self, call: &Call) -> Option<&DefinitionSignature> {
+        self.definitions.keys().find(|definition| {
+            definition.name == call.name
+                && (call.owner.is_empty()
+                    || definition.owner == DefinitionOwner::Path(call.owner.clone()))
+        })
+    }
+
+    /// Definition called by a `Call`, or the declaration of the C function of a `Foreign` call.
+    pub fn find_called(&self, expression: &Expression) -> Option<&DefinitionSignature> {
+        match expression {
+            Expression::Call(call) => self.find_definition(call),
+            Expression::Foreign(call) => self.foreign.get(&call.name),
+            Expression::Associated(associated) => {
+                self.find_method(&associated.ty.ty, &associated.name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Definition `name` implemented for the type `ty`.
+    pub fn find_method(&self, ty: &TypeSignature, name: &str) -> Option<&DefinitionSignature> {
+        self.definitions.keys().find(|signature| {
+            signature.name == name && signature.owner == DefinitionOwner::Type(ty.clone())
+        })
+    }
+
+    /// Variant `name` of the enum `ty`, with its position.
+    pub fn find_variant(&self, ty: &TypeSignature, name: &str) -> Option<(usize, &EnumVariant)> {
+        match &self.types.get(ty)?.ty {
+            TypeBody::Enum(variants) => variants
+                .iter()
+                .enumerate()
+                .find(|(_, variant)| variant.name == name),
+            _ => None,
+        }
+    }
+
+    pub fn implements(&self, ty: &TypeSignature, name: &str) -> bool {
+        self.implementations
+            .get(ty)
+            .is_some_and(|traits| traits.contains(name))
+    }
+
+    /// Whether values of `ty` are copied instead of moved.
+    ///
+    /// Ranges and real numbers are plain numbers, so they're copied unless they implement
+    /// `Drop`, any other type has to implement `Copy`.
+    pub fn is_copy(&self, ty: &TypeSignature) -> bool {
+        match self.types.get(ty).map(|ty| &ty.ty) {
+            Some(TypeBody::Range(_) | TypeBody::Float(_) | TypeBody::Fixed(_)) => {
+                !self.implements(ty, "Drop") && self.find_drop(ty).is_none()
+            }
+            _ => self.implements(ty, "Copy"),
+        }
+    }
+
+    /// The core library's `StaticString`, the type of string literals, if it's loaded.
+    pub fn static_string(&self) -> Option<&TypeSignature> {
+        self.types
+            .keys()
+            .find(|ty| ty.owner == "core::strings" && ty.name == "StaticString")
+    }
+
+    /// The `Drop` procedure of `ty`, when it implements `Drop`.
+    pub fn find_drop(&self, ty: &TypeSignature) -> Option<&DefinitionSignature> {
+        self.find_method(ty, "Drop")
+    }
+
+    /// Whether dropping a value of `ty` does anything: it implements `Drop`, or one of its
+    /// variants has a field that needs dropping, like `Option<String>`.
+    ///
+    /// Pointers and references don't own what they point to, and arrays aren't dropped.
+    pub fn needs_drop(&self, ty: &TypeSignature) -> bool {
+        if self.find_drop(ty).is_some() {
+            return true;
+        }
+
+        match self.types.get(ty).map(|ty| &ty.ty) {
+            Some(TypeBody::Enum(variants)) => variants
+                .iter()
+                .flat_map(|variant| &variant.fields)
+                .filter_map(TypeExpression::signature)
+                .any(|field| self.needs_drop(field)),
+            _ => false,
+        }
+    }
+
+    /// Expressions directly contained by `id`, including the initializer of a new variable.
+    pub fn children(&self, id: ExpressionId) -> Vec<ExpressionId> {
+        match self.find_expression(id) {
+            Some(Expression::UnaryOperation(un_op)) => vec![un_op.operand],
+            Some(Expression::BinaryOperation(bin_op)) => vec![bin_op.left, bin_op.right],
+            Some(Expression::NewVariable(variable)) => self
+                .find_variable_by_id(*variable)
+                .and_then(|variable| variable.initializer)
+                .into_iter()
+                .collect(),
+            Some(Expression::Call(call) | Expression::Foreign(call)) => call.args.clone(),
+            Some(Expression::Associated(associated)) => associated.args.clone(),
+            Some(Expression::MethodCall(call)) => [call.receiver]
+                .into_iter()
+                .chain(call.args.iter().copied())
+                .collect(),
+            Some(Expression::Field(field)) => vec![field.base],
+            Some(Expression::Index(index)) => vec![index.base, index.index],
+            Some(Expression::Array(elements)) => elements.clone(),
+            Some(Expression::Conversion(conversion)) => vec![conversion.value],
+            Some(Expression::Cast(cast)) => vec![cast.value],
+            Some(Expression::Assignment(assignment)) => vec![assignment.place, assignment.value],
+            Some(Expression::If(if_expression)) => if_expression
+                .branches
+                .iter()
+                .flat_map(|(condition, body)| [*condition, *body])
+                .chain(if_expression.otherwise)
+                .collect(),
+            Some(Expression::Match(match_expression)) => [match_expression.value]
+                .into_iter()
+                .chain(match_expression.arms.iter().map(|arm| arm.body))
+                .collect(),
+            Some(Expression::Loop(loop_expression)) => loop_expression
+                .condition
+                .into_iter()
+                .chain([loop_expression.body])
+                .collect(),
+            Some(Expression::For(for_expression)) => {
+                vec![for_expression.container, for_expression.body]
+            }
+            Some(Expression::Return(value)) => value.iter().copied().collect(),
+            Some(Expression::Body(body)) => body.contents.clone(),
+            Some(
+                Expression::None
+                | Expression::Number(..)
+                | Expression::String(_)
+                | Expression::Variable(_)
+                | Expression::SizeOf(_),
+            )
+            | None => vec![],
+        }
+    }
+
+    /// Rosario paths of the packages declared with `trusted package`.
+    pub fn trusted_modules(&self) -> BTreeSet<&str> {
+        self.packages
+            .values()
+            .flatten()
+            .filter(|package| package.trusted)
+            .map(|package| package.module.as_str())
+            .collect()
+    }
+
+    /// Whether `module` is `ancestor` or inside one of its packages, which see its private
+    /// items. The standard library is its own root.
+    pub fn is_within(&self, module: &str, ancestor: &str) -> bool {
+        let mut current = module;
+
+        while current != ancestor {
+            let parent = self.packages.iter().find_map(|(parent, packages)| {
+                packages
+                    .iter()
+                    .any(|package| !package.library && package.module == current)
+                    .then_some(parent)
+            });
+
+            match parent {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+
+        true
     }
 
     pub fn find_type_signature_owner(&self, name: String) -> String {
-        for (signature, _) in &self.types {
+        for signature in self.types.keys() {
             if signature.name == name {
                 return signature.owner.clone();
             }
@@ -87,6 +427,65 @@ impl Ast {
     }
 }
 
+/// `package name;`, declared by the file whose Rosario path keys `Ast::packages`.
+#[derive(Debug, Default, Clone)]
+pub struct Package {
+    pub name: String,
+    /// Rosario path of the package's file, filled in by the `Loader`.
+    pub module: String,
+    pub public: bool,
+    /// `trusted package name;`, which lets the package use raw pointers and `C_LANG`.
+    pub trusted: bool,
+    /// The standard library, which the `Loader` adds to every file using `core::...`. It
+    /// stays the root of its own packages instead of becoming one of the file's.
+    pub library: bool,
+    pub location: Location,
+}
+
+/// A use of raw pointers or `C_LANG`, which the compiler can't check is safe.
+#[derive(Debug, Clone)]
+pub struct UnsafeCode {
+    /// Rosario path of the file it's written in.
+    pub module: String,
+    pub kind: UnsafeKind,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsafeKind {
+    /// `*T`
+    Pointer,
+    /// `*value`
+    Dereference,
+    /// `C_LANG name(args)`
+    Foreign,
+    /// `C_LANG function name(args) => T;`
+    ForeignDeclaration,
+    /// `use C_LANG("header");`
+    ForeignInclude,
+}
+
+impl Display for UnsafeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnsafeKind::Pointer => write!(f, "raw pointer type"),
+            UnsafeKind::Dereference => write!(f, "raw pointer dereference"),
+            UnsafeKind::Foreign => write!(f, "`C_LANG` call"),
+            UnsafeKind::ForeignDeclaration => write!(f, "`C_LANG` declaration"),
+            UnsafeKind::ForeignInclude => write!(f, "`C_LANG` include"),
+        }
+    }
+}
+
+/// `use a::b::C;` or `use a::b::*;`, keyed by the file declaring it in `Ast::uses`.
+#[derive(Debug, Default, Clone)]
+pub struct Use {
+    pub path: Vec<String>,
+    pub glob: bool,
+    pub public: bool,
+    pub location: Location,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct RosarioType {
     pub generics: Vec<Generic>,
@@ -98,6 +497,123 @@ pub enum TypeBody {
     #[default]
     Unknown,
     Range(Range),
+    /// `digits N`, a floating-point type with at least `N` significant decimal digits.
+    Float(ExpressionId),
+    /// `delta D range A..B`, a fixed-point type whose values are multiples of `D`.
+    Fixed(Fixed),
+    Enum(Vec<EnumVariant>),
+    /// `type Integer is Int32;`, another name for a type. Resolving replaces its uses with
+    /// the type it names.
+    Alias(TypeReference),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Vec<TypeExpression>,
+    /// `private Name(...)` can only be built or matched in the file declaring its type.
+    pub private: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TypeReference {
+    pub ty: TypeSignature,
+    pub generics: Vec<Generic>,
+}
+
+impl Display for TypeReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ty.name)?;
+
+        if !self.generics.is_empty() {
+            let generics = self
+                .generics
+                .iter()
+                .map(|g| g.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            write!(f, "<{}>", generics)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A type as it's written, two types are the same when they're written the same once
+/// their names are resolved.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TypeExpression {
+    /// `Name`, or `Name<A, B>` when it's applied to generic arguments.
+    Named(TypeReference),
+    /// `*T`, a raw pointer.
+    Pointer(Box<TypeExpression>),
+    /// `&T`
+    Reference(Box<TypeExpression>),
+    /// `mutable &T`
+    MutableReference(Box<TypeExpression>),
+    /// `array<R of T>`, holding a `T` for each value of the range `R`.
+    Array {
+        index: Box<TypeExpression>,
+        element: Box<TypeExpression>,
+    },
+}
+
+impl Default for TypeExpression {
+    fn default() -> Self {
+        Self::Named(TypeReference::default())
+    }
+}
+
+impl From<TypeSignature> for TypeExpression {
+    fn from(ty: TypeSignature) -> Self {
+        Self::Named(TypeReference {
+            ty,
+            generics: vec![],
+        })
+    }
+}
+
+impl TypeExpression {
+    /// Signature of a named type, whatever its generic arguments are.
+    pub fn signature(&self) -> Option<&TypeSignature> {
+        match self {
+            Self::Named(reference) => Some(&reference.ty),
+            _ => None,
+        }
+    }
+
+    /// Pattern bindings are declared without a type, they take the one of the field they bind.
+    pub fn is_inferred(&self) -> bool {
+        self.signature().is_some_and(|ty| ty.name.is_empty())
+    }
+
+    /// Every named type in the expression, outermost first.
+    pub fn references_mut(&mut self) -> Vec<&mut TypeReference> {
+        match self {
+            Self::Named(reference) => vec![reference],
+            Self::Pointer(inner) | Self::Reference(inner) | Self::MutableReference(inner) => {
+                inner.references_mut()
+            }
+            Self::Array { index, element } => {
+                let mut result = index.references_mut();
+                result.extend(element.references_mut());
+                result
+            }
+        }
+    }
+}
+
+impl Display for TypeExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Named(reference) => write!(f, "{}", reference),
+            Self::Pointer(inner) => write!(f, "*{}", inner),
+            Self::Reference(inner) => write!(f, "&{}", inner),
+            Self::MutableReference(inner) => write!(f, "mutable &{}", inner),
+            Self::Array { index, element } => write!(f, "array<{} of {}>", index, element),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -106,6 +622,12 @@ pub struct Range {
     pub max: ExpressionId,
 }
 
+#[derive(Debug, Default, Clone)]
+pub struct Fixed {
+    pub delta: ExpressionId,
+    pub range: Range,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum DefinitionOwner {
     #[default]
@@ -118,8 +640,13 @@ pub enum DefinitionOwner {
 pub struct DefinitionSignature {
     pub owner: DefinitionOwner,
     pub name: String,
+    pub generics: Vec<Generic>,
     pub args: Vec<VariableId>,
-    pub return_type: Option<TypeSignature>,
+    pub return_type: Option<TypeExpression>,
+    /// `Reference` or `MutableReference` when it returns `&T` or `mutable &T`.
+    pub return_variable_type: VariableType,
+    /// `trusted function ...`, which only trusted packages can call.
+    pub trusted: bool,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -137,9 +664,9 @@ pub struct VariableId(pub u64);
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Variable {
     pub name: String,
-    pub ty: TypeSignature,
+    /// Type of the value, a `&` or `mutable &` in front of it is in `variable_type`.
+    pub ty: TypeExpression,
     pub variable_type: VariableType,
-    pub generics: Vec<Generic>,
     pub initializer: Option<ExpressionId>,
 }
 
@@ -147,17 +674,39 @@ pub struct Variable {
 pub enum Expression {
     #[default]
     None,
-    Number(u128, Option<u128>),
+    Number(Number),
+    /// `"text"`, a string literal, which is a `StaticString` of the core library.
+    String(String),
+    UnaryOperation(UnOp),
     BinaryOperation(BinOp),
     NewVariable(VariableId),
     Variable(VariableId),
+    Call(Call),
+    /// `C_LANG name(args)`, calling a C function.
+    Foreign(Call),
+    MethodCall(MethodCall),
+    Associated(Associated),
+    Field(Field),
+    Index(Index),
+    /// `[a, b, ...]`, an array holding each value in order.
+    Array(Vec<ExpressionId>),
+    Conversion(Conversion),
+    Cast(Cast),
+    /// `size_of T`, the size of a `T` in bytes.
+    SizeOf(TypeExpression),
+    Assignment(Assignment),
+    If(If),
+    Match(Match),
+    Loop(Loop),
+    For(For),
+    Return(Option<ExpressionId>),
     Body(Body),
 }
 
 impl Expression {
     pub fn get_binary_operator(&self) -> Option<&BinOp> {
         match self {
-            Expression::BinaryOperation(b) => Some(&b),
+            Expression::BinaryOperation(b) => Some(b),
             _ => None,
         }
     }
@@ -175,6 +724,151 @@ pub struct Body {
     pub contents: Vec<ExpressionId>,
 }
 
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Call {
+    /// Path of the module declaring the called definition, empty until resolved.
+    pub owner: String,
+    pub name: String,
+    pub generics: Vec<Generic>,
+    pub args: Vec<ExpressionId>,
+}
+
+/// `receiver.Name(args)`, calling a definition implemented for the receiver's type.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MethodCall {
+    pub receiver: ExpressionId,
+    pub name: String,
+    /// `receiver.Name<A, B>(args)`, the generics of the method itself.
+    pub generics: Vec<Generic>,
+    pub args: Vec<ExpressionId>,
+}
+
+/// `Type::Name(args)`, building the variant `Name` of the enum `Type` or calling the
+/// definition `Name` implemented for `Type`. Variants without fields leave out `(args)`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Associated {
+    pub ty: TypeReference,
+    pub name: String,
+    /// `Type::Name<A, B>(args)`, the generics of the definition, after the type's.
+    pub generics: Vec<Generic>,
+    pub args: Vec<ExpressionId>,
+}
+
+/// `base.name`, reading a field of the base's type.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Field {
+    pub base: ExpressionId,
+    pub name: String,
+}
+
+/// `base[index]`, an element of an array indexed by a value of its range.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Index {
+    pub base: ExpressionId,
+    pub index: ExpressionId,
+}
+
+/// `if condition then ... elsif condition then ... else ... end if`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct If {
+    /// Each condition with the `Body` run when it holds, in order.
+    pub branches: Vec<(ExpressionId, ExpressionId)>,
+    pub otherwise: Option<ExpressionId>,
+}
+
+/// `while condition loop ... end loop`, or `loop ... end loop` which only ends by returning.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Loop {
+    pub condition: Option<ExpressionId>,
+    pub body: ExpressionId,
+}
+
+/// `for element of container loop ... end loop`, binding a reference to each element.
+///
+/// Arrays are iterated in order, any other container through its `Length(&self)` and
+/// `Get(&self, index)` methods.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct For {
+    pub element: VariableId,
+    pub container: ExpressionId,
+    pub body: ExpressionId,
+}
+
+/// `match value is Pattern => arm, ... end match`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Match {
+    pub value: ExpressionId,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: ExpressionId,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pattern {
+    /// `_`, matching any value.
+    #[default]
+    Wildcard,
+    /// `Variant(a, _)`, binding the variant's fields to new variables, `None` for `_`.
+    Variant {
+        name: String,
+        bindings: Vec<Option<VariableId>>,
+    },
+}
+
+/// `place = value`, or `place += value` and friends when `op` is set.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Assignment {
+    pub place: ExpressionId,
+    pub op: Option<BinOpType>,
+    pub value: ExpressionId,
+}
+
+/// A number literal with its exact value, `real` when it's written with a
+/// fraction or an exponent and typed by its suffix, like `255_UInt8`, if any.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Number {
+    pub value: Exact,
+    pub real: bool,
+    pub suffix: Option<TypeSignature>,
+}
+
+/// `value as Type`, which panics when `value` doesn't fit in `Type`, or
+/// `value as balanced Type`, which clamps it to the closest bound instead.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Conversion {
+    pub value: ExpressionId,
+    pub ty: TypeSignature,
+    pub balanced: bool,
+}
+
+/// `value as *T`, reading a raw pointer as a pointer to another type.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cast {
+    pub value: ExpressionId,
+    pub ty: TypeExpression,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnOp {
+    pub op: UnOpType,
+    pub operand: ExpressionId,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnOpType {
+    #[default]
+    Minus,
+    Plus,
+    /// `*pointer`, the value a pointer points to.
+    Dereference,
+    /// `&place`, a reference to a place owned somewhere else.
+    Reference,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct BinOp {
     pub op: BinOpType,
@@ -190,13 +884,96 @@ pub enum BinOpType {
     Add,
     Div,
     Mul,
+    /// `mod`, the remainder of a division, taking the sign of the left operand like C's `%`.
+    Mod,
     ToThePowerOf,
+    IsEquals,
+    IsNotEquals,
+    LessThan,
+    MoreThan,
+    LessThanOrEquals,
+    MoreThanOrEquals,
     AddEquals,
     SubEquals,
     MulEquals,
     DivEquals,
 }
 
+impl BinOpType {
+    /// Operator applied by a compound assignment, `+` for `+=`.
+    pub fn compound_operator(&self) -> Option<BinOpType> {
+        match self {
+            BinOpType::AddEquals => Some(BinOpType::Add),
+            BinOpType::SubEquals => Some(BinOpType::Sub),
+            BinOpType::MulEquals => Some(BinOpType::Mul),
+            BinOpType::DivEquals => Some(BinOpType::Div),
+            _ => None,
+        }
+    }
+
+    /// Binding power of the operator, operators with a higher one are applied first.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            _ if self.is_comparison() => 1,
+            BinOpType::Add | BinOpType::Sub => 2,
+            BinOpType::Mul | BinOpType::Div | BinOpType::Mod => 3,
+            BinOpType::ToThePowerOf => 4,
+            _ => 0,
+        }
+    }
+
+    /// Whether the operator compares its operands, giving a condition instead of a number.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinOpType::IsEquals
+                | BinOpType::IsNotEquals
+                | BinOpType::LessThan
+                | BinOpType::MoreThan
+                | BinOpType::LessThanOrEquals
+                | BinOpType::MoreThanOrEquals
+        )
+    }
+
+    /// `*` and `/`, which scale a number instead of adding one of the same kind.
+    pub fn is_scaling(&self) -> bool {
+        matches!(
+            self,
+            BinOpType::Mul | BinOpType::Div | BinOpType::MulEquals | BinOpType::DivEquals
+        )
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        *self == BinOpType::ToThePowerOf
+    }
+}
+
+impl Display for BinOpType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            BinOpType::Unknown => "?",
+            BinOpType::Sub => "-",
+            BinOpType::Add => "+",
+            BinOpType::Div => "/",
+            BinOpType::Mul => "*",
+            BinOpType::Mod => "mod",
+            BinOpType::ToThePowerOf => "**",
+            BinOpType::IsEquals => "==",
+            BinOpType::IsNotEquals => "!=",
+            BinOpType::LessThan => "<",
+            BinOpType::MoreThan => ">",
+            BinOpType::LessThanOrEquals => "<=",
+            BinOpType::MoreThanOrEquals => ">=",
+            BinOpType::AddEquals => "+=",
+            BinOpType::SubEquals => "-=",
+            BinOpType::MulEquals => "*=",
+            BinOpType::DivEquals => "/=",
+        };
+
+        write!(f, "{}", symbol)
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TypeSignature {
     pub owner: String,
@@ -205,10 +982,33 @@ pub struct TypeSignature {
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Generic {
+    /// Path of the module declaring the type, empty for parameters and until resolved.
+    pub owner: String,
     pub name: String,
+    pub generics: Vec<Generic>,
+    pub bounds: Vec<String>,
     pub end: GenericEnd,
 }
 
+impl Display for Generic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if !self.generics.is_empty() {
+            let generics = self
+                .generics
+                .iter()
+                .map(|g| g.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            write!(f, "<{}>", generics)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum GenericEnd {
     #[default]
@@ -226,3 +1026,12 @@ pub enum VariableType {
     MutableValue,
     MutableReference,
 }
+
+impl VariableType {
+    pub fn is_reference(&self) -> bool {
+        matches!(
+            self,
+            VariableType::Reference | VariableType::MutableReference
+        )
+    }
+}
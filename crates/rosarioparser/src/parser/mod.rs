@@ -1,12 +1,25 @@
+use std::panic::{self, AssertUnwindSafe};
+
 use crate::{
-    lexer::{Lexer, Token, TokenType},
+    diagnostic::{Diagnostic, Location},
+    lexer::{IDENTIFIER_LIST, Lexer, SYMBOL_LIST, Token, TokenType},
     parser::ast::{
-        Ast, BinOpType, DefinitionOwner, DefinitionSignature, ExpressionId, Generic, GenericEnd,
-        Range, RosarioType, TypeBody, TypeSignature, Variable, VariableType,
+        Assignment, Associated, Ast, BinOpType, Call, Cast, Conversion, DefinitionOwner,
+        DefinitionSignature, EnumVariant, Expression, ExpressionId, Field, Fixed, For, Generic,
+        GenericEnd, If, Index, Loop, Match, MatchArm, MethodCall, Number, Package, Pattern, Range,
+        RosarioType, TypeBody, TypeExpression, TypeReference, TypeSignature, UnOpType, UnsafeCode,
+        UnsafeKind, Use, Variable, VariableId, VariableType,
     },
+    parser::number::Exact,
 };
 
 pub mod ast;
+pub mod number;
+
+/// Operators a type can define, like `operator "?"(self) => T is self.Unwrap();`. Their
+/// definitions are named after their symbol, and `mutable operator`s, giving a `mutable &`
+/// to change the result through, after `mutable ` and their symbol.
+pub const OPERATORS: [&str; 1] = ["?"];
 
 #[derive(Debug, Default, Clone)]
 pub struct Parser {
@@ -15,382 +28,1773 @@ pub struct Parser {
 
     pub ast: Ast,
     pub current_def_sig: Option<DefinitionSignature>,
+    /// Type of the `implement` block being parsed, with the generic parameters it names.
+    pub current_implementation: Option<TypeReference>,
+    /// Variables in scope in the definition being parsed, the innermost last.
+    locals: Vec<(String, VariableId)>,
+    /// Where the variables of each open block start in `locals`.
+    scopes: Vec<usize>,
+    /// Syntax errors, which stop the parse of their file, and the errors the parser
+    /// recovered from, reported by the loader.
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl Parser {
     pub fn start(&mut self, lex: Lexer) {
         self.lexer = lex;
+        self.current_item = 0;
 
         self.lexer.start();
+
+        // The loader reports the lexer's diagnostics; a file that doesn't
+        // lex isn't parsed.
+        if self.lexer.diagnostics.iter().any(Diagnostic::is_error) {
+            return;
+        }
+
+        self.ast
+            .modules
+            .insert(self.lexer.file_path.clone(), self.file_path_to_rosario());
+
+        // A syntax error unwinds back here with its diagnostic, see `fail`.
+        let parsed = panic::catch_unwind(AssertUnwindSafe(|| self.parse_items()));
+
+        if let Err(payload) = parsed {
+            match payload.downcast::<Diagnostic>() {
+                Ok(diagnostic) => self.diagnostics.push(*diagnostic),
+                Err(payload) => panic::resume_unwind(payload),
+            }
+
+            self.current_def_sig = None;
+            self.current_implementation = None;
+            self.locals.clear();
+            self.scopes.clear();
+        }
+    }
+
+    fn parse_items(&mut self) {
         self.advance();
 
         while self.current_token().ty != TokenType::EndOfFile {
+            let public = self.current_token().ty == TokenType::Public;
+
+            if public {
+                self.advance();
+            }
+
             match self.current_token().ty {
-                TokenType::Procedure => self.parse_procedure(),
-                TokenType::Type => self.parse_type(),
-                _ => todo!("{:?}", self.current_token().ty),
+                TokenType::Procedure | TokenType::Function => self.parse_procedure(public, false),
+                TokenType::Type => self.parse_type(public),
+                TokenType::Package => self.parse_package(public, false),
+                TokenType::Trusted => match self.advance().ty {
+                    TokenType::Package => self.parse_package(public, true),
+                    TokenType::Procedure | TokenType::Function => {
+                        self.parse_procedure(public, true)
+                    }
+                    _ => self.expected("`package`, `procedure` or `function`"),
+                },
+                TokenType::Use => self.parse_use(public),
+                TokenType::Trait => self.parse_trait(public),
+                TokenType::Implement => self.parse_implement(),
+                TokenType::Clang => self.parse_foreign_declaration(),
+                _ => self.expected("a definition, a type, a trait, `use` or `package`"),
             }
 
             self.advance();
         }
     }
 
-    pub fn parse_type(&mut self) {
+    pub fn parse_package(&mut self, public: bool, trusted: bool) {
+        let location = self.current_location();
+
         self.advance();
 
         let name = self.get_identifier().clone();
 
         self.advance();
 
-        if self.current_token().ty != TokenType::Is {
-            Self::expected_token_found_error("is", &format!("{:?}", self.current_token().ty));
+        if self.current_token().ty != TokenType::Semicolon {
+            self.expected("`;`");
         }
 
-        self.advance();
+        self.ast
+            .packages
+            .entry(self.file_path_to_rosario())
+            .or_default()
+            .push(Package {
+                name,
+                module: String::new(),
+                public,
+                trusted,
+                library: false,
+                location,
+            });
+    }
 
-        let ty = match self.current_token().ty {
-            TokenType::Range => RosarioType {
-                generics: vec![],
-                ty: TypeBody::Range(self.parse_range()),
-            },
-            _ => todo!("{:?}", self.current_token().ty),
+    /// `use C_LANG("header");`, including a C header in the generated code.
+    pub fn parse_foreign_include(&mut self) {
+        self.new_unsafe_code(UnsafeKind::ForeignInclude);
+
+        if self.advance().ty != TokenType::Character('(') {
+            self.expected("`(`");
+        }
+
+        let TokenType::StaticString(header) = self.advance().ty.clone() else {
+            self.expected("a header name");
         };
 
-        self.advance();
+        if self.advance().ty != TokenType::Character(')') {
+            self.expected("`)`");
+        }
 
-        if self.current_token().ty != TokenType::Semicolon {
-            Self::expected_token_found_error(";", &format!("{:?}", self.current_token().ty));
+        if self.advance().ty != TokenType::Semicolon {
+            self.expected("`;`");
         }
 
-        self.ast.new_type(name, self.file_path_to_rosario(), ty);
+        self.ast
+            .foreign_includes
+            .entry(self.file_path_to_rosario())
+            .or_default()
+            .push(header);
     }
 
-    pub fn parse_range(&mut self) -> Range {
-        self.advance();
+    /// `C_LANG function name(args) => T;`, declaring a C function with Rosario types so its
+    /// calls can be checked.
+    pub fn parse_foreign_declaration(&mut self) {
+        self.new_unsafe_code(UnsafeKind::ForeignDeclaration);
 
-        let min = self.parse_expression(None, None);
+        if !matches!(
+            self.advance().ty,
+            TokenType::Procedure | TokenType::Function
+        ) {
+            self.expected("`procedure` or `function`");
+        }
 
         self.advance();
 
-        if self.current_token().ty != TokenType::RangeDots {
-            Self::expected_token_found_error("..", &format!("{:?}", self.current_token().ty));
-        }
+        let signature = self.parse_definition_signature(false);
 
-        self.advance();
+        if self.advance().ty != TokenType::Semicolon {
+            self.expected("`;`");
+        }
 
-        let max = self.parse_expression(None, None);
+        self.ast.foreign.insert(signature.name.clone(), signature);
+    }
 
-        Range { min, max }
+    /// Records a use of raw pointers or `C_LANG` at the current token.
+    pub fn new_unsafe_code(&mut self, kind: UnsafeKind) {
+        self.ast.unsafe_code.push(UnsafeCode {
+            module: self.file_path_to_rosario(),
+            kind,
+            location: self.current_location(),
+        });
     }
 
-    pub fn parse_definition_signature(&mut self) -> DefinitionSignature {
-        let name = self.get_identifier().clone();
+    pub fn parse_use(&mut self, public: bool) {
+        let location = self.current_location();
 
         self.advance();
 
-        if self.current_token().ty != TokenType::Character('(') {
-            todo!("Expected '(' Error.");
+        if self.current_token().ty == TokenType::Clang {
+            return self.parse_foreign_include();
         }
 
-        self.advance();
+        let mut path = vec![];
+        let mut glob = false;
+
+        loop {
+            path.push(self.get_identifier().clone());
 
-        while self.current_token().ty != TokenType::Character(')') {
-            // TODO: Arguments.
             self.advance();
-        }
 
-        self.advance();
+            if self.current_token().ty != TokenType::DoubleColon {
+                break;
+            }
 
-        let return_type = if self.current_token().ty == TokenType::Arrow {
             self.advance();
 
-            Some(self.parse_type_signature())
-        } else {
-            self.go_back();
-
-            None
-        };
+            if self.current_token().ty == TokenType::Multiply {
+                glob = true;
+                self.advance();
+                break;
+            }
+        }
 
-        DefinitionSignature {
-            owner: DefinitionOwner::Path(self.file_path_to_rosario()),
-            name,
-            args: vec![],
-            return_type,
+        if self.current_token().ty != TokenType::Semicolon {
+            self.expected("`;`");
         }
-    }
 
-    pub fn file_path_to_rosario(&self) -> String {
-        format!("{}::{}", self.lexer.main_rosario_path, self.lexer.file_name)
+        self.ast
+            .uses
+            .entry(self.file_path_to_rosario())
+            .or_default()
+            .push(Use {
+                path,
+                glob,
+                public,
+                location,
+            });
     }
 
-    pub fn parse_type_signature(&mut self) -> TypeSignature {
-        let name = self.get_identifier().clone();
+    /// Records the visibility and location of an item declared in the current file.
+    pub fn new_item(&mut self, name: &str, public: bool, location: Location) {
+        let path = format!("{}::{}", self.file_path_to_rosario(), name);
 
-        TypeSignature {
-            owner: self.ast.find_type_signature_owner(name.clone()),
-            name,
+        if public {
+            self.ast.public_items.insert(path.clone());
         }
-    }
 
-    pub fn expected_token_found_error(expected: &str, found: &str) -> ! {
-        panic!(
-            "Expected Token `{}`. Found `{}` instead...",
-            expected, found
-        )
+        self.ast.item_locations.insert(path, location);
     }
 
-    pub fn parse_procedure(&mut self) {
+    pub fn parse_type(&mut self, public: bool) {
         self.advance();
 
-        let signature = self.parse_definition_signature();
+        let location = self.current_location();
+        let name = self.get_identifier().clone();
 
-        self.current_def_sig = Some(signature.clone());
+        self.new_item(&name, public, location);
+
+        self.advance();
+
+        let generics = self.parse_generics();
 
         self.advance();
 
         if self.current_token().ty != TokenType::Is {
-            Self::expected_token_found_error("is", &format!("{:?}", self.current_token().ty));
+            self.expected("`is`");
         }
 
         self.advance();
 
-        let body = self.parse_expression(Some(signature.name.clone()), None);
+        let ty = match self.current_token().ty {
+            TokenType::Range => RosarioType {
+                generics,
+                ty: TypeBody::Range(self.parse_range()),
+            },
+            TokenType::Enum => RosarioType {
+                generics,
+                ty: TypeBody::Enum(self.parse_enum(name.clone())),
+            },
+            // `digits` and `delta` only mean something here, so they stay usable as names.
+            TokenType::Identifier(ref keyword) if keyword == "digits" => {
+                self.advance();
+
+                RosarioType {
+                    generics,
+                    ty: TypeBody::Float(self.parse_expression(None)),
+                }
+            }
+            TokenType::Identifier(ref keyword) if keyword == "delta" => {
+                self.advance();
+
+                let delta = self.parse_expression(None);
+
+                self.advance();
+
+                if self.current_token().ty != TokenType::Range {
+                    self.expected("`range`");
+                }
+
+                RosarioType {
+                    generics,
+                    ty: TypeBody::Fixed(Fixed {
+                        delta,
+                        range: self.parse_range(),
+                    }),
+                }
+            }
+            TokenType::Identifier(_) => RosarioType {
+                generics,
+                ty: TypeBody::Alias(self.parse_type_reference()),
+            },
+            _ => self.expected("`range`, `enum`, `digits`, `delta` or a type"),
+        };
 
         self.advance();
 
         if self.current_token().ty != TokenType::Semicolon {
-            Self::expected_token_found_error(";", &format!("{:?}", self.current_token().ty));
+            self.expected("`;`");
         }
 
-        self.current_def_sig = None;
-
-        self.ast.new_definition(signature, body);
+        self.ast.new_type(name, self.file_path_to_rosario(), ty);
     }
 
-    pub fn parse_let(&mut self) -> ExpressionId {
+    /// `trait Name<T> is signatures end Name;`, or `trait Name;` when it declares nothing.
+    ///
+    /// Only the names of the declared definitions are kept, implementations are checked
+    /// against them.
+    pub fn parse_trait(&mut self, public: bool) {
         self.advance();
 
+        let location = self.current_location();
         let name = self.get_identifier().clone();
 
+        self.new_item(&name, public, location);
+
+        self.advance();
+        self.parse_generics();
         self.advance();
 
-        if self.current_token().ty != TokenType::Colon {
-            Self::expected_token_found_error(":", &format!("{:?}", self.current_token().ty));
+        let mut definitions = vec![];
+
+        if self.current_token().ty == TokenType::Is {
+            self.advance();
+
+            while self.current_token().ty != TokenType::End {
+                if !matches!(
+                    self.current_token().ty,
+                    TokenType::Procedure | TokenType::Function
+                ) {
+                    self.expected("`procedure` or `function`");
+                }
+
+                self.advance();
+                definitions.push(self.get_identifier().clone());
+
+                while !matches!(
+                    self.advance().ty,
+                    TokenType::Semicolon | TokenType::EndOfFile
+                ) {}
+
+                self.advance();
+            }
+
+            self.parse_ending(Some(name.clone()));
+            self.advance();
+        }
+
+        if self.current_token().ty != TokenType::Semicolon {
+            self.expected("`;`");
         }
 
+        self.ast.traits.insert(
+            format!("{}::{}", self.file_path_to_rosario(), name),
+            definitions,
+        );
+    }
+
+    pub fn parse_range(&mut self) -> Range {
         self.advance();
 
-        let ty = self.parse_type_signature();
+        let min = self.parse_expression(None);
 
         self.advance();
 
-        let generics = self.parse_generics();
+        if self.current_token().ty != TokenType::RangeDots {
+            self.expected("`..`");
+        }
 
         self.advance();
 
-        let initializer = if self.current_token().ty == TokenType::Equals {
-            self.advance();
-            Some(self.parse_expression(Some(name.clone()), None))
-        } else {
-            None
-        };
+        let max = self.parse_expression(None);
 
-        self.ast.new_variable(
-            self.current_def_sig.clone().unwrap(),
-            Variable {
-                name,
-                ty,
-                variable_type: VariableType::Value,
-                generics,
-                initializer,
-            },
-        )
+        Range { min, max }
     }
 
-    pub fn parse_generics(&mut self) -> Vec<Generic> {
-        if self.current_token().ty != TokenType::MoreThan {
-            self.go_back();
-            return vec![];
-        }
+    pub fn parse_enum(&mut self, name: String) -> Vec<EnumVariant> {
+        let mut variants = vec![];
 
         self.advance();
 
-        let mut result = vec![];
+        while self.current_token().ty != TokenType::End {
+            let private = self.current_token().ty == TokenType::Private;
 
-        while self.current_token().ty != TokenType::MoreThan {
-            let generic_name = self.get_identifier().clone();
+            if private {
+                self.advance();
+            }
+
+            let variant_name = self.get_identifier().clone();
 
             self.advance();
 
-            let generic_end = match self.current_token().ty {
-                TokenType::Character(',') => GenericEnd::Comma,
-                TokenType::Of => GenericEnd::Of,
-                TokenType::MoreThan => GenericEnd::Nothing,
-                _ => Self::expected_token_found_error(
-                    ",` or `of",
-                    &format!("{:?}", self.current_token().ty),
-                ),
-            };
+            let mut fields = vec![];
 
-            result.push(Generic {
-                name: generic_name,
-                end: generic_end,
-            });
+            if self.current_token().ty == TokenType::Character('(') {
+                self.advance();
+
+                while self.current_token().ty != TokenType::Character(')') {
+                    fields.push(self.parse_type_expression());
+
+                    self.advance();
+
+                    match self.current_token().ty {
+                        TokenType::Character(',') => {
+                            self.advance();
+                        }
+                        TokenType::Character(')') => {}
+                        _ => self.expected("`,` or `)`"),
+                    }
+                }
 
-            if self.current_token().ty != TokenType::MoreThan {
                 self.advance();
             }
-        }
 
-        result
-    }
+            variants.push(EnumVariant {
+                name: variant_name,
+                fields,
+                private,
+            });
 
-    pub fn bin_op_token_to_type(&self) -> BinOpType {
-        match self.current_token().ty {
-            TokenType::Add => BinOpType::Add,
-            TokenType::Sub => BinOpType::Sub,
-            TokenType::Multiply => BinOpType::Mul,
-            TokenType::Divide => BinOpType::Div,
-            TokenType::ToThePowerOf => BinOpType::ToThePowerOf,
-            _ => todo!("{:?}", self.current_token().ty),
+            match self.current_token().ty {
+                TokenType::Character(',') => {
+                    self.advance();
+                }
+                TokenType::End => {}
+                _ => self.expected("`,` or `end`"),
+            }
         }
+
+        self.parse_ending(Some(name));
+
+        variants
     }
 
-    pub fn parse_binary_operator(&mut self, left: ExpressionId) -> ExpressionId {
-        let current_bin_op = self.bin_op_token_to_type();
+    pub fn parse_type_reference(&mut self) -> TypeReference {
+        let ty = self.parse_type_signature();
 
         self.advance();
 
-        let right = self.parse_expression(None, None);
+        let generics = self.parse_generics();
 
-        let right_bin_op = match self.ast.find_expression(right) {
-            Some(r) => r.get_binary_operator(),
-            None => None,
-        };
+        TypeReference { ty, generics }
+    }
 
-        let right_op_left_value = match right_bin_op {
-            Some(bin_op) => {
-                if bin_op.op > current_bin_op {
-                    ExpressionId(0)
-                } else {
-                    bin_op.left
+    /// Parses a type: `Name<A, B>`, `*T`, `&T`, `mutable &T` or `array<R of T>`.
+    pub fn parse_type_expression(&mut self) -> TypeExpression {
+        match self.current_token().ty {
+            TokenType::Multiply => {
+                self.new_unsafe_code(UnsafeKind::Pointer);
+                self.advance();
+                TypeExpression::Pointer(Box::new(self.parse_type_expression()))
+            }
+            TokenType::Character('&') => {
+                self.advance();
+                TypeExpression::Reference(Box::new(self.parse_type_expression()))
+            }
+            TokenType::Mutable => {
+                if self.advance().ty != TokenType::Character('&') {
+                    self.expected("`&`");
                 }
+
+                self.advance();
+                TypeExpression::MutableReference(Box::new(self.parse_type_expression()))
             }
-            None => ExpressionId(0),
-        };
+            TokenType::Identifier(ref name) if name == "array" => {
+                if self.advance().ty != TokenType::LessThan {
+                    self.expected("`<`");
+                }
 
-        let new_right_op_left_value = if right_op_left_value != ExpressionId(0) {
-            self.ast
-                .new_binary_operator(current_bin_op.clone(), left, right_op_left_value)
-        } else {
-            ExpressionId(0)
-        };
+                self.advance();
 
-        let mut_right_bin_op = match self.ast.find_mut_expression(right) {
-            Some(r) => r.get_mut_binary_operator(),
-            None => None,
-        };
+                let index = self.parse_type_expression();
 
-        match mut_right_bin_op {
-            Some(bin_op) => {
-                if bin_op.op > current_bin_op {
-                    return self.ast.new_binary_operator(current_bin_op, left, right);
-                } else {
-                    bin_op.left = new_right_op_left_value;
-                    return right;
+                if self.advance().ty != TokenType::Of {
+                    self.expected("`of`");
+                }
+
+                self.advance();
+
+                let element = self.parse_type_expression();
+
+                if self.advance().ty != TokenType::MoreThan {
+                    self.expected("`>`");
+                }
+
+                TypeExpression::Array {
+                    index: Box::new(index),
+                    element: Box::new(element),
                 }
             }
-            None => {
-                return self.ast.new_binary_operator(current_bin_op, left, right);
-            }
+            _ => TypeExpression::Named(self.parse_type_reference()),
         }
     }
 
-    pub fn parse_expression(
-        &mut self,
-        end_name: Option<String>,
-        left: Option<ExpressionId>,
-    ) -> ExpressionId {
-        let expr = match &self.current_token().ty {
-            TokenType::Begin => self.parse_body(end_name.clone()),
-            TokenType::Let => self.parse_let(),
-            TokenType::Number(natural, decimal) => self.ast.new_number(
-                natural.parse::<u128>().unwrap(),
-                match decimal {
-                    Some(d) => Some(d.parse::<u128>().unwrap()),
-                    None => None,
-                },
-            ),
-            TokenType::Add
-            | TokenType::Sub
-            | TokenType::Multiply
-            | TokenType::Divide
-            | TokenType::ToThePowerOf => self.parse_binary_operator(left.unwrap()),
-            _ => todo!("{:?}", self.current_token().ty),
-        };
+    /// Parses the type of a binding, splitting a `&` or `mutable &` in front of it off into
+    /// how the value is bound.
+    pub fn parse_binding_type(&mut self) -> (TypeExpression, Option<VariableType>) {
+        match self.parse_type_expression() {
+            TypeExpression::Reference(ty) => (*ty, Some(VariableType::Reference)),
+            TypeExpression::MutableReference(ty) => (*ty, Some(VariableType::MutableReference)),
+            ty => (ty, None),
+        }
+    }
+
+    pub fn parse_definition_signature(&mut self, trusted: bool) -> DefinitionSignature {
+        let name = self.parse_definition_name();
 
         self.advance();
 
-        if self.is_token_delimiter() {
-            self.go_back();
-            return expr;
-        }
+        let generics = self.parse_generics();
 
-        self.parse_expression(end_name, Some(expr))
-    }
+        self.advance();
 
-    pub fn is_token_delimiter(&self) -> bool {
-        match self.current_token().ty {
-            TokenType::Semicolon | TokenType::RangeDots => true,
-            _ => false,
+        if self.current_token().ty != TokenType::Character('(') {
+            self.expected("`(`");
         }
-    }
-
-    pub fn parse_body(&mut self, end_name: Option<String>) -> ExpressionId {
-        let mut contents = vec![];
 
         self.advance();
 
-        while self.current_token().ty != TokenType::End
-            && self.current_token().ty != TokenType::EndOfFile
-        {
-            contents.push(self.parse_expression(None, None));
+        let mut arguments = vec![];
 
-            self.advance();
+        while self.current_token().ty != TokenType::Character(')') {
+            let location = self.current_location();
+            let reference = self.parse_reference_marker();
+            let mutable = reference.is_none() && self.current_token().ty == TokenType::Mutable;
 
-            if !matches!(self.current_token().ty, TokenType::Semicolon) {
-                todo!("Semicolon Error.");
+            if mutable {
+                self.advance();
             }
 
-            self.advance();
-        }
+            let variable = if self.current_token().ty == TokenType::SelfVariable {
+                let implementation = match self.current_implementation.clone() {
+                    Some(implementation) => implementation,
+                    None => {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                "`self` can only be a parameter of a method".to_string(),
+                            )
+                            .at(Some(self.current_location()))
+                            .with_note("methods are declared in an `implement` block".to_string()),
+                        );
+
+                        TypeReference::default()
+                    }
+                };
+
+                // `mutable self` is a shorthand for `mutable &self`.
+                let variable_type = match (reference, mutable) {
+                    (Some(reference), _) => reference,
+                    (None, true) => VariableType::MutableReference,
+                    (None, false) => VariableType::Value,
+                };
+
+                Variable {
+                    name: "self".to_string(),
+                    ty: TypeExpression::Named(implementation),
+                    variable_type,
+                    initializer: None,
+                }
+            } else {
+                if reference.is_some() {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "only `self` can be marked as a reference before its name".to_string(),
+                        )
+                        .at(Some(location.clone()))
+                        .with_note("write the reference in the type, as in `x: &T`".to_string()),
+                    );
+                }
 
-        self.parse_ending(end_name);
+                let name = self.get_identifier().clone();
 
-        self.ast.new_body(contents)
-    }
+                self.advance();
 
-    pub fn parse_ending(&mut self, end_name: Option<String>) {
-        if !matches!(self.current_token().ty, TokenType::End) {
-            todo!("'end' Token Error.");
-        }
+                if self.current_token().ty != TokenType::Colon {
+                    self.expected("`:`");
+                }
 
-        match end_name {
-            Some(name) => {
                 self.advance();
 
-                if *self.get_identifier() != name {
-                    todo!("End Name Error.")
+                let (ty, reference) = self.parse_binding_type();
+
+                if mutable && reference.is_some() {
+                    self.diagnostics
+                        .push(Self::mutable_reference_binding(location.clone(), &name));
                 }
-            }
-            None => {}
-        }
-    }
 
-    pub fn get_identifier(&self) -> &String {
+                let variable_type = match (reference, mutable) {
+                    (Some(reference), _) => reference,
+                    (None, true) => VariableType::MutableValue,
+                    (None, false) => VariableType::Value,
+                };
+
+                Variable {
+                    name,
+                    ty,
+                    variable_type,
+                    initializer: None,
+                }
+            };
+
+            self.advance();
+
+            match self.current_token().ty {
+                TokenType::Character(',') => {
+                    self.advance();
+                }
+                TokenType::Character(')') => {}
+                _ => self.expected("`,` or `)`"),
+            }
+
+            arguments.push((variable, location));
+        }
+
+        self.advance();
+
+        let mut return_variable_type = VariableType::Value;
+
+        let return_type = if self.current_token().ty == TokenType::Arrow {
+            self.advance();
+
+            let (ty, reference) = self.parse_binding_type();
+
+            if let Some(reference) = reference {
+                return_variable_type = reference;
+            }
+
+            Some(ty)
+        } else {
+            self.go_back();
+
+            None
+        };
+
+        // Definitions of a generic type take its parameters before their own.
+        let (owner, generics) = match &self.current_implementation {
+            Some(reference) => (
+                DefinitionOwner::Type(reference.ty.clone()),
+                reference.generics.iter().cloned().chain(generics).collect(),
+            ),
+            None => (DefinitionOwner::Path(self.file_path_to_rosario()), generics),
+        };
+
+        let signature = DefinitionSignature {
+            owner,
+            name,
+            generics,
+            args: vec![],
+            return_type,
+            return_variable_type,
+            trusted,
+        };
+
+        self.ast.new_arguments(signature, arguments)
+    }
+
+    /// Parses `&` or `mutable &` before a type or `self`, leaving the parser after it.
+    /// The name of a definition, or the symbol of an operator after `operator`.
+    fn parse_definition_name(&mut self) -> String {
+        let operator = self.go_back().ty == TokenType::Operator;
+        let mutable = operator
+            && self.current_item > 1
+            && self.lexer.contents[self.current_item - 2].ty == TokenType::Mutable;
+
+        match self.advance().ty.clone() {
+            TokenType::StaticString(symbol) if operator => {
+                if !OPERATORS.contains(&symbol.as_str()) {
+                    let operators = OPERATORS.map(|operator| format!("`{}`", operator));
+
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("`{}` can't be defined by a type", symbol))
+                            .at(Some(self.current_location()))
+                            .with_note(format!("the operators are {}", operators.join(", "))),
+                    );
+                }
+
+                match mutable {
+                    true => format!("mutable {}", symbol),
+                    false => symbol,
+                }
+            }
+            _ => self.get_identifier().clone(),
+        }
+    }
+
+    pub fn parse_reference_marker(&mut self) -> Option<VariableType> {
+        match self.current_token().ty {
+            TokenType::Character('&') => {
+                self.advance();
+                Some(VariableType::Reference)
+            }
+            TokenType::Mutable => {
+                if self.advance().ty != TokenType::Character('&') {
+                    self.go_back();
+                    return None;
+                }
+
+                self.advance();
+                Some(VariableType::MutableReference)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn file_path_to_rosario(&self) -> String {
+        format!("{}::{}", self.lexer.main_rosario_path, self.lexer.file_name)
+    }
+
+    pub fn parse_type_signature(&mut self) -> TypeSignature {
+        let name = self.get_identifier().clone();
+
+        TypeSignature {
+            owner: self.ast.find_type_signature_owner(name.clone()),
+            name,
+        }
+    }
+
+    /// Stops parsing the file at a syntax error, reported as `expected {what}, found ...`.
+    pub fn expected(&self, what: &str) -> ! {
+        self.fail(
+            Diagnostic::error(format!(
+                "expected {}, found {}",
+                what,
+                describe(&self.current_token().ty)
+            ))
+            .at(Some(self.current_location())),
+        )
+    }
+
+    /// Stops parsing the file, `start` reports `diagnostic`. It unwinds without running the
+    /// panic hook, since it isn't a bug of the compiler.
+    fn fail(&self, diagnostic: Diagnostic) -> ! {
+        panic::resume_unwind(Box::new(diagnostic))
+    }
+
+    /// `mutable x: &T`, where `mutable` would make the reference itself mutable.
+    fn mutable_reference_binding(location: Location, name: &str) -> Diagnostic {
+        Diagnostic::error(format!("the reference `{}` can't be `mutable`", name))
+            .at(Some(location))
+            .with_note(format!(
+                "to change what it points to, write `{}: mutable &T`",
+                name
+            ))
+    }
+
+    pub fn parse_procedure(&mut self, public: bool, trusted: bool) {
+        self.advance();
+
+        let location = self.current_location();
+        let signature = self.parse_definition_signature(trusted);
+
+        let item = match &self.current_implementation {
+            Some(reference) => format!("{}::{}", reference.ty.name, signature.name),
+            None => signature.name.clone(),
+        };
+
+        self.new_item(&item, public, location);
+
+        self.current_def_sig = Some(signature.clone());
+
+        self.locals = signature
+            .args
+            .iter()
+            .map(|id| (self.ast.variables[&signature][id].name.clone(), *id))
+            .collect();
+
+        self.advance();
+
+        if self.current_token().ty != TokenType::Is {
+            self.expected("`is`");
+        }
+
+        self.advance();
+
+        let body = self.parse_expression(Some(signature.name.clone()));
+
+        self.advance();
+
+        if self.current_token().ty != TokenType::Semicolon {
+            self.expected("`;`");
+        }
+
+        self.current_def_sig = None;
+        self.locals.clear();
+
+        self.ast.new_definition(signature, body);
+    }
+
+    /// `implement [Trait for] Type<T> ... end implement;`, whose definitions belong to `Type`.
+    pub fn parse_implement(&mut self) {
+        self.advance();
+
+        let mut implementation = self.parse_type_reference();
+        let mut implemented = None;
+
+        self.advance();
+
+        if self.current_token().ty == TokenType::For {
+            self.advance();
+
+            implemented = Some(implementation.ty.name);
+            implementation = self.parse_type_reference();
+
+            self.advance();
+        }
+
+        // The type is looked up from this file, the resolver finds where it's declared.
+        let ty = TypeSignature {
+            owner: self.file_path_to_rosario(),
+            name: implementation.ty.name,
+        };
+
+        if let Some(implemented) = implemented {
+            self.ast
+                .implementations
+                .entry(ty.clone())
+                .or_default()
+                .insert(implemented);
+        }
+
+        self.current_implementation = Some(TypeReference {
+            ty,
+            generics: implementation.generics,
+        });
+
+        while self.current_token().ty != TokenType::End {
+            let public = self.current_token().ty == TokenType::Public;
+
+            if public {
+                self.advance();
+            }
+
+            let trusted = self.current_token().ty == TokenType::Trusted;
+
+            if trusted {
+                self.advance();
+            }
+
+            // `mutable operator "?"`, whose name `parse_definition_name` reads back.
+            if self.current_token().ty == TokenType::Mutable
+                && self.advance().ty != TokenType::Operator
+            {
+                self.expected("`operator`");
+            }
+
+            match self.current_token().ty {
+                TokenType::Procedure | TokenType::Function | TokenType::Operator => {
+                    self.parse_procedure(public, trusted)
+                }
+                _ => self.expected("`procedure`, `function` or `operator`"),
+            }
+
+            self.advance();
+        }
+
+        self.advance();
+
+        if self.current_token().ty != TokenType::Implement {
+            self.expected("`implement`");
+        }
+
+        self.advance();
+
+        if self.current_token().ty != TokenType::Semicolon {
+            self.expected("`;`");
+        }
+
+        self.current_implementation = None;
+    }
+
+    pub fn parse_let(&mut self) -> ExpressionId {
+        self.advance();
+
+        let location = self.current_location();
+
+        let mut variable_type = match self.current_token().ty {
+            TokenType::Mutable => {
+                self.advance();
+                VariableType::MutableValue
+            }
+            _ => VariableType::Value,
+        };
+
+        let name = self.get_identifier().clone();
+
+        self.advance();
+
+        if self.current_token().ty != TokenType::Colon {
+            self.expected("`:`");
+        }
+
+        self.advance();
+
+        let (ty, reference) = self.parse_binding_type();
+
+        if let Some(reference) = reference {
+            if variable_type == VariableType::MutableValue {
+                self.diagnostics
+                    .push(Self::mutable_reference_binding(location, &name));
+            }
+
+            variable_type = reference;
+        }
+
+        self.advance();
+
+        let initializer = if self.current_token().ty == TokenType::Equals {
+            self.advance();
+            Some(self.parse_expression(Some(name.clone())))
+        } else {
+            self.go_back();
+            None
+        };
+
+        let expression = self.ast.new_variable(
+            self.current_def_sig.clone().unwrap(),
+            Variable {
+                name: name.clone(),
+                ty,
+                variable_type,
+                initializer,
+            },
+        );
+
+        if let Some(Expression::NewVariable(id)) = self.ast.find_expression(expression) {
+            self.locals.push((name, *id));
+        }
+
+        expression
+    }
+
+    pub fn parse_generics(&mut self) -> Vec<Generic> {
+        if self.current_token().ty != TokenType::LessThan {
+            self.go_back();
+            return vec![];
+        }
+
+        self.advance();
+
+        let mut result = vec![];
+
+        while self.current_token().ty != TokenType::MoreThan {
+            let generic_name = self.get_identifier().clone();
+
+            self.advance();
+
+            let generics = self.parse_generics();
+
+            self.advance();
+
+            let mut bounds = vec![];
+
+            if self.current_token().ty == TokenType::Colon {
+                self.advance();
+
+                loop {
+                    bounds.push(self.get_identifier().clone());
+
+                    self.advance();
+
+                    if self.current_token().ty != TokenType::Add {
+                        break;
+                    }
+
+                    self.advance();
+                }
+            }
+
+            let generic_end = match self.current_token().ty {
+                TokenType::Character(',') => GenericEnd::Comma,
+                TokenType::Of => GenericEnd::Of,
+                TokenType::MoreThan => GenericEnd::Nothing,
+                _ => self.expected("`,` or `of`"),
+            };
+
+            result.push(Generic {
+                owner: String::new(),
+                name: generic_name,
+                generics,
+                bounds,
+                end: generic_end,
+            });
+
+            if self.current_token().ty != TokenType::MoreThan {
+                self.advance();
+            }
+        }
+
+        result
+    }
+
+    pub fn bin_op_token_to_type(&self) -> Option<BinOpType> {
+        match self.current_token().ty {
+            TokenType::Add => Some(BinOpType::Add),
+            TokenType::Sub => Some(BinOpType::Sub),
+            TokenType::Multiply => Some(BinOpType::Mul),
+            TokenType::Divide => Some(BinOpType::Div),
+            TokenType::Modulo => Some(BinOpType::Mod),
+            TokenType::ToThePowerOf => Some(BinOpType::ToThePowerOf),
+            TokenType::IsEquals => Some(BinOpType::IsEquals),
+            TokenType::IsNotEquals => Some(BinOpType::IsNotEquals),
+            TokenType::LessThan => Some(BinOpType::LessThan),
+            TokenType::MoreThan => Some(BinOpType::MoreThan),
+            TokenType::LessThanOrEquals => Some(BinOpType::LessThanOrEquals),
+            TokenType::MoreThanOrEquals => Some(BinOpType::MoreThanOrEquals),
+            _ => None,
+        }
+    }
+
+    /// Binary operator following the current token, without consuming it.
+    pub fn peek_binary_operator(&mut self) -> Option<BinOpType> {
+        self.advance();
+        let op = self.bin_op_token_to_type();
+        self.go_back();
+
+        op
+    }
+
+    /// Parses the operators following `left` whose precedence is at least `min_precedence`.
+    pub fn parse_binary_operator(
+        &mut self,
+        left: ExpressionId,
+        min_precedence: u8,
+    ) -> ExpressionId {
+        let mut left = left;
+
+        while let Some(op) = self.peek_binary_operator()
+            && op.precedence() >= min_precedence
+        {
+            let location = self.ast.find_location(left);
+
+            self.advance();
+            self.advance();
+
+            let mut right = self.parse_operand(None);
+
+            while let Some(next) = self.peek_binary_operator()
+                && (next.precedence() > op.precedence()
+                    || (next.is_right_associative() && next.precedence() == op.precedence()))
+            {
+                let precedence = match next.precedence() > op.precedence() {
+                    true => op.precedence() + 1,
+                    false => op.precedence(),
+                };
+
+                right = self.parse_binary_operator(right, precedence);
+            }
+
+            left = self.ast.new_binary_operator(op, left, right);
+
+            if let Some(location) = location {
+                self.ast.locations.insert(left, location);
+            }
+        }
+
+        left
+    }
+
+    /// Parses a whole expression, leaving the parser on its last token.
+    pub fn parse_expression(&mut self, end_name: Option<String>) -> ExpressionId {
+        let operand = self.parse_operand(end_name);
+        let place = self.parse_binary_operator(operand, 0);
+
+        let op = match self.advance().ty {
+            TokenType::Equals => None,
+            TokenType::AddEquals => Some(BinOpType::AddEquals),
+            TokenType::SubEquals => Some(BinOpType::SubEquals),
+            TokenType::MultiplyEquals => Some(BinOpType::MulEquals),
+            TokenType::DivideEquals => Some(BinOpType::DivEquals),
+            _ => {
+                self.go_back();
+                return place;
+            }
+        };
+
+        self.advance();
+
+        let value = self.parse_expression(None);
+        let assignment =
+            self.ast
+                .new_expression(Expression::Assignment(Assignment { place, op, value }));
+
+        if let Some(location) = self.ast.find_location(place) {
+            self.ast.locations.insert(assignment, location);
+        }
+
+        assignment
+    }
+
+    /// Parses an expression without any binary operator after it.
+    pub fn parse_operand(&mut self, end_name: Option<String>) -> ExpressionId {
+        let location = self.current_location();
+        let operand = self.parse_prefix(end_name);
+
+        self.parse_suffixes(operand, location, true)
+    }
+
+    /// Parses the indexing, fields, method calls and `?` following `operand`, and its conversions
+    /// when `conversions` is set.
+    fn parse_suffixes(
+        &mut self,
+        mut operand: ExpressionId,
+        location: Location,
+        conversions: bool,
+    ) -> ExpressionId {
+        loop {
+            let expression = match self.advance().ty.clone() {
+                TokenType::As if conversions && self.advance().ty == TokenType::Multiply => {
+                    Expression::Cast(Cast {
+                        value: operand,
+                        ty: self.parse_type_expression(),
+                    })
+                }
+                TokenType::As if conversions => {
+                    let balanced = self.current_token().ty == TokenType::Balanced;
+
+                    if balanced {
+                        self.advance();
+                    }
+
+                    Expression::Conversion(Conversion {
+                        value: operand,
+                        ty: self.parse_type_signature(),
+                        balanced,
+                    })
+                }
+                TokenType::Character('[') => {
+                    self.advance();
+
+                    let index = self.parse_expression(None);
+
+                    if self.advance().ty != TokenType::Character(']') {
+                        self.expected("`]`");
+                    }
+
+                    Expression::Index(Index {
+                        base: operand,
+                        index,
+                    })
+                }
+                // `value?` calls the `operator "?"` of the value's type.
+                TokenType::Character('?') => Expression::MethodCall(MethodCall {
+                    receiver: operand,
+                    name: "?".to_string(),
+                    generics: vec![],
+                    args: vec![],
+                }),
+                TokenType::Dot => {
+                    self.advance();
+
+                    let name = self.get_identifier().clone();
+
+                    self.advance();
+
+                    let generics = self.parse_generics();
+
+                    match self.advance().ty {
+                        TokenType::Character('(') => Expression::MethodCall(MethodCall {
+                            receiver: operand,
+                            name,
+                            generics,
+                            args: self.parse_arguments(),
+                        }),
+                        _ if !generics.is_empty() => self.expected("`(`"),
+                        _ => {
+                            self.go_back();
+
+                            Expression::Field(Field {
+                                base: operand,
+                                name,
+                            })
+                        }
+                    }
+                }
+                _ => break,
+            };
+
+            operand = self.ast.new_expression(expression);
+            self.ast.locations.insert(operand, location.clone());
+        }
+
+        self.go_back();
+
+        operand
+    }
+
+    /// Parses an operand without the conversions following it.
+    pub fn parse_prefix(&mut self, end_name: Option<String>) -> ExpressionId {
+        let location = self.current_location();
+
+        let expr = match &self.current_token().ty {
+            TokenType::Begin => self.parse_body(end_name),
+            TokenType::Let => self.parse_let(),
+            TokenType::If => self.parse_if(),
+            TokenType::Match => self.parse_match(end_name),
+            TokenType::While | TokenType::Loop => self.parse_loop(),
+            TokenType::For => self.parse_for(),
+            TokenType::SizeOf => {
+                self.advance();
+
+                let ty = self.parse_type_expression();
+
+                self.ast.new_expression(Expression::SizeOf(ty))
+            }
+            TokenType::Return => self.parse_return(),
+            TokenType::Number {
+                value,
+                real,
+                suffix,
+            } => {
+                let number = Number {
+                    value: value.clone(),
+                    real: *real,
+                    suffix: suffix.clone().map(|name| TypeSignature {
+                        owner: self.ast.find_type_signature_owner(name.clone()),
+                        name,
+                    }),
+                };
+
+                self.ast.new_number(number)
+            }
+            TokenType::StaticString(text) => {
+                self.ast.new_expression(Expression::String(text.clone()))
+            }
+            TokenType::CharLiteral(char) => self.ast.new_number(Number {
+                value: Exact::from(*char as u128),
+                ..Default::default()
+            }),
+            TokenType::Identifier(_) => self.parse_identifier_expression(),
+            TokenType::Clang => self.parse_foreign_call(),
+            TokenType::Character('[') => self.parse_array(),
+            TokenType::SelfVariable => {
+                let variable = self
+                    .current_def_sig
+                    .as_ref()
+                    .and_then(|signature| self.ast.find_variable(signature, "self"));
+
+                match variable {
+                    Some(variable) => self.ast.new_expression(Expression::Variable(variable)),
+                    None => {
+                        self.diagnostics.push(
+                            Diagnostic::error("`self` is only defined in methods".to_string())
+                                .at(Some(location.clone()))
+                                .with_note(
+                                    "a method takes `self` as its first parameter".to_string(),
+                                ),
+                        );
+
+                        self.ast.new_expression(Expression::None)
+                    }
+                }
+            }
+            TokenType::Sub | TokenType::Add | TokenType::Multiply | TokenType::Character('&') => {
+                let op = match self.current_token().ty {
+                    TokenType::Sub => UnOpType::Minus,
+                    TokenType::Add => UnOpType::Plus,
+                    TokenType::Multiply => {
+                        self.new_unsafe_code(UnsafeKind::Dereference);
+                        UnOpType::Dereference
+                    }
+                    _ => UnOpType::Reference,
+                };
+
+                self.advance();
+
+                // `&a[i]` takes a reference to the element, not to `a`.
+                let location = self.current_location();
+                let operand = self.parse_prefix(None);
+                let operand = self.parse_suffixes(operand, location, false);
+
+                self.ast.new_unary_operator(op, operand)
+            }
+            TokenType::Character('(') => {
+                self.advance();
+
+                let expr = self.parse_expression(None);
+
+                self.advance();
+
+                if self.current_token().ty != TokenType::Character(')') {
+                    self.expected("`)`");
+                }
+
+                expr
+            }
+            _ => self.expected("an expression"),
+        };
+
+        self.ast.locations.entry(expr).or_insert(location);
+
+        expr
+    }
+
+    /// Parses `if ... end if`, leaving the parser on the closing `if`.
+    pub fn parse_if(&mut self) -> ExpressionId {
+        let mut branches = vec![];
+        let mut otherwise = None;
+
+        loop {
+            match self.current_token().ty {
+                TokenType::If | TokenType::ElsIf => {
+                    self.advance();
+
+                    let condition = self.parse_expression(None);
+
+                    self.advance();
+
+                    if self.current_token().ty != TokenType::Then {
+                        self.expected("`then`");
+                    }
+
+                    branches.push((condition, self.parse_statements()));
+                }
+                TokenType::Else => otherwise = Some(self.parse_statements()),
+                _ => break,
+            }
+        }
+
+        self.parse_ending(None);
+        self.advance();
+
+        if self.current_token().ty != TokenType::If {
+            self.expected("`if`");
+        }
+
+        self.ast.new_expression(Expression::If(If {
+            branches,
+            otherwise,
+        }))
+    }
+
+    /// Parses `match ... end match`, or `end Name` when it's the body of `Name`.
+    pub fn parse_match(&mut self, end_name: Option<String>) -> ExpressionId {
+        self.advance();
+
+        let value = self.parse_expression(None);
+
+        self.advance();
+
+        if self.current_token().ty != TokenType::Is {
+            self.expected("`is`");
+        }
+
+        self.advance();
+
+        let mut arms = vec![];
+
+        while self.current_token().ty != TokenType::End {
+            // The bindings of an arm are only seen by its body.
+            self.enter_scope();
+
+            let pattern = self.parse_pattern();
+
+            self.advance();
+
+            if self.current_token().ty != TokenType::Arrow {
+                self.expected("`=>`");
+            }
+
+            self.advance();
+
+            let body = self.parse_expression(None);
+
+            self.exit_scope();
+
+            arms.push(MatchArm { pattern, body });
+
+            self.advance();
+
+            match self.current_token().ty {
+                TokenType::Character(',') => {
+                    self.advance();
+                }
+                TokenType::End => {}
+                _ => self.expected("`,` or `end`"),
+            }
+        }
+
+        match end_name {
+            Some(_) => self.parse_ending(end_name),
+            None => {
+                self.advance();
+
+                if self.current_token().ty != TokenType::Match {
+                    self.expected("`match`");
+                }
+            }
+        }
+
+        self.ast
+            .new_expression(Expression::Match(Match { value, arms }))
+    }
+
+    /// Parses `while condition loop ... end loop` or `loop ... end loop`, leaving the parser
+    /// on the closing `loop`.
+    pub fn parse_loop(&mut self) -> ExpressionId {
+        let condition = match self.current_token().ty {
+            TokenType::While => {
+                self.advance();
+
+                let condition = self.parse_expression(None);
+
+                if self.advance().ty != TokenType::Loop {
+                    self.expected("`loop`");
+                }
+
+                Some(condition)
+            }
+            _ => None,
+        };
+
+        let body = self.parse_loop_body();
+
+        self.ast
+            .new_expression(Expression::Loop(Loop { condition, body }))
+    }
+
+    /// Parses `for element of container loop ... end loop`, leaving the parser on the
+    /// closing `loop`.
+    pub fn parse_for(&mut self) -> ExpressionId {
+        self.advance();
+
+        let location = self.current_location();
+        let name = self.get_identifier().clone();
+
+        if self.advance().ty != TokenType::Of {
+            self.expected("`of`");
+        }
+
+        self.advance();
+
+        let container = self.parse_expression(None);
+
+        if self.advance().ty != TokenType::Loop {
+            self.expected("`loop`");
+        }
+
+        // Declared after the container, which can't see it.
+        let element = self.ast.new_binding(
+            self.current_def_sig.clone().unwrap(),
+            Variable {
+                name: name.clone(),
+                variable_type: VariableType::Reference,
+                ..Default::default()
+            },
+            location,
+        );
+
+        self.enter_scope();
+        self.locals.push((name, element));
+
+        let body = self.parse_loop_body();
+
+        self.exit_scope();
+
+        self.ast.new_expression(Expression::For(For {
+            element,
+            container,
+            body,
+        }))
+    }
+
+    /// Parses the statements of a loop and its `end loop`, leaving the parser on `loop`.
+    pub fn parse_loop_body(&mut self) -> ExpressionId {
+        let body = self.parse_statements();
+
+        self.parse_ending(None);
+
+        if self.advance().ty != TokenType::Loop {
+            self.expected("`loop`");
+        }
+
+        body
+    }
+
+    /// Parses `_` or `Variant(a, _)`, leaving the parser on its last token.
+    pub fn parse_pattern(&mut self) -> Pattern {
+        if self.current_token().ty == TokenType::Character('_') {
+            return Pattern::Wildcard;
+        }
+
+        let name = self.get_identifier().clone();
+
+        if self.advance().ty != TokenType::Character('(') {
+            self.go_back();
+
+            return Pattern::Variant {
+                name,
+                bindings: vec![],
+            };
+        }
+
+        self.advance();
+
+        let mut bindings = vec![];
+
+        while self.current_token().ty != TokenType::Character(')') {
+            let binding = match self.current_token().ty {
+                TokenType::Character('_') => None,
+                _ => {
+                    let name = self.get_identifier().clone();
+                    let variable = Variable {
+                        name: name.clone(),
+                        ..Default::default()
+                    };
+
+                    let id = self.ast.new_binding(
+                        self.current_def_sig.clone().unwrap(),
+                        variable,
+                        self.current_location(),
+                    );
+
+                    self.locals.push((name, id));
+
+                    Some(id)
+                }
+            };
+
+            bindings.push(binding);
+
+            self.advance();
+
+            match self.current_token().ty {
+                TokenType::Character(',') => {
+                    self.advance();
+                }
+                TokenType::Character(')') => {}
+                _ => self.expected("`,` or `)`"),
+            }
+        }
+
+        Pattern::Variant { name, bindings }
+    }
+
+    /// Parses `;` terminated statements up to `end`, `elsif` or `else`, leaving the parser
+    /// on that token.
+    pub fn parse_statements(&mut self) -> ExpressionId {
+        let mut contents = vec![];
+
+        self.enter_scope();
+        self.advance();
+
+        while !matches!(
+            self.current_token().ty,
+            TokenType::End | TokenType::ElsIf | TokenType::Else | TokenType::EndOfFile
+        ) {
+            contents.push(self.parse_expression(None));
+
+            self.advance();
+            self.expect_semicolon();
+            self.advance();
+        }
+
+        self.exit_scope();
+
+        self.ast.new_body(contents)
+    }
+
+    pub fn parse_return(&mut self) -> ExpressionId {
+        self.advance();
+
+        if self.current_token().ty == TokenType::Semicolon {
+            self.go_back();
+            return self.ast.new_expression(Expression::Return(None));
+        }
+
+        let value = self.parse_expression(None);
+
+        self.ast.new_expression(Expression::Return(Some(value)))
+    }
+
+    pub fn parse_identifier_expression(&mut self) -> ExpressionId {
+        let name = self.get_identifier().clone();
+
+        if let Some((_, variable)) = self.locals.iter().rev().find(|(local, _)| *local == name) {
+            let variable = *variable;
+
+            return self.ast.new_expression(Expression::Variable(variable));
+        }
+
+        self.advance();
+
+        let generics = self.parse_generics();
+
+        self.advance();
+
+        if self.current_token().ty == TokenType::DoubleColon {
+            let ty = TypeReference {
+                ty: TypeSignature {
+                    owner: self.ast.find_type_signature_owner(name.clone()),
+                    name,
+                },
+                generics,
+            };
+
+            return self.parse_associated(ty);
+        }
+
+        let args = self.parse_arguments();
+
+        self.ast.new_call(Call {
+            owner: String::new(),
+            name,
+            generics,
+            args,
+        })
+    }
+
+    /// Parses `::Name`, `::Name(args)` or `::Name<A, B>(args)` following the type `ty`,
+    /// leaving the parser on its last token.
+    pub fn parse_associated(&mut self, ty: TypeReference) -> ExpressionId {
+        self.advance();
+
+        let name = self.get_identifier().clone();
+
+        self.advance();
+
+        let generics = self.parse_generics();
+
+        let args = match self.advance().ty {
+            TokenType::Character('(') => self.parse_arguments(),
+            _ => {
+                self.go_back();
+                vec![]
+            }
+        };
+
+        self.ast.new_expression(Expression::Associated(Associated {
+            ty,
+            name,
+            generics,
+            args,
+        }))
+    }
+
+    /// Parses `[a, b, ...]`, leaving the parser on the closing bracket.
+    pub fn parse_array(&mut self) -> ExpressionId {
+        self.advance();
+
+        let mut elements = vec![];
+
+        while self.current_token().ty != TokenType::Character(']') {
+            elements.push(self.parse_expression(None));
+
+            self.advance();
+
+            match self.current_token().ty {
+                TokenType::Character(',') => {
+                    self.advance();
+                }
+                TokenType::Character(']') => {}
+                _ => self.expected("`,` or `]`"),
+            }
+        }
+
+        self.ast.new_expression(Expression::Array(elements))
+    }
+
+    /// Parses `C_LANG name(args)`, leaving the parser on the closing parenthesis.
+    pub fn parse_foreign_call(&mut self) -> ExpressionId {
+        self.new_unsafe_code(UnsafeKind::Foreign);
+        self.advance();
+
+        let name = self.get_identifier().clone();
+
+        self.advance();
+
+        let args = self.parse_arguments();
+
+        self.ast.new_expression(Expression::Foreign(Call {
+            owner: String::new(),
+            name,
+            generics: vec![],
+            args,
+        }))
+    }
+
+    /// Parses `(a, b, ...)`, leaving the parser on the closing parenthesis.
+    pub fn parse_arguments(&mut self) -> Vec<ExpressionId> {
+        if self.current_token().ty != TokenType::Character('(') {
+            self.expected("`(`");
+        }
+
+        self.advance();
+
+        let mut args = vec![];
+
+        while self.current_token().ty != TokenType::Character(')') {
+            args.push(self.parse_expression(None));
+
+            self.advance();
+
+            match self.current_token().ty {
+                TokenType::Character(',') => {
+                    self.advance();
+                }
+                TokenType::Character(')') => {}
+                _ => self.expected("`,` or `)`"),
+            }
+        }
+
+        args
+    }
+
+    pub fn parse_body(&mut self, end_name: Option<String>) -> ExpressionId {
+        let mut contents = vec![];
+
+        self.enter_scope();
+        self.advance();
+
+        while self.current_token().ty != TokenType::End
+            && self.current_token().ty != TokenType::EndOfFile
+        {
+            contents.push(self.parse_expression(None));
+
+            self.advance();
+            self.expect_semicolon();
+            self.advance();
+        }
+
+        self.parse_ending(end_name);
+        self.exit_scope();
+
+        self.ast.new_body(contents)
+    }
+
+    /// Reports a statement that isn't followed by `;`, and goes on as if it were, leaving
+    /// the parser on the statement's last token.
+    fn expect_semicolon(&mut self) {
+        if self.current_token().ty == TokenType::Semicolon {
+            return;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!(
+                "expected `;` after this statement, found {}",
+                describe(&self.current_token().ty)
+            ))
+            .at(Some(self.current_location())),
+        );
+
+        self.go_back();
+    }
+
+    /// Opens a block, whose variables go out of scope at `exit_scope`.
+    fn enter_scope(&mut self) {
+        self.scopes.push(self.locals.len());
+    }
+
+    fn exit_scope(&mut self) {
+        if let Some(start) = self.scopes.pop() {
+            self.locals.truncate(start);
+        }
+    }
+
+    pub fn parse_ending(&mut self, end_name: Option<String>) {
+        if !matches!(self.current_token().ty, TokenType::End) {
+            self.expected("`end`");
+        }
+
+        if let Some(name) = end_name {
+            self.advance();
+
+            let found = self.get_identifier().clone();
+
+            if found != name {
+                self.fail(
+                    Diagnostic::error(format!("`end {}` doesn't close `{}`", found, name))
+                        .at(Some(self.current_location()))
+                        .with_note(format!("`{}` ends with `end {};`", name, name)),
+                );
+            }
+        }
+    }
+
+    pub fn get_identifier(&self) -> &String {
         match &self.current_token().ty {
-            TokenType::Identifier(id) => &id,
-            _ => todo!("Identifier Error."),
+            TokenType::Identifier(id) => id,
+            _ => self.expected("a name"),
         }
     }
 
@@ -404,6 +1808,16 @@ impl Parser {
         self.current_token()
     }
 
+    pub fn current_location(&self) -> Location {
+        let (line, column) = self.current_token().begin_location;
+
+        Location {
+            file: self.lexer.file_path.clone(),
+            line: line + 1,
+            column,
+        }
+    }
+
     pub fn current_token(&self) -> &Token {
         if self.current_item == 0 {
             panic!("Parser hasn't started yet.");
@@ -412,3 +1826,21 @@ impl Parser {
         &self.lexer.contents[self.current_item - 1]
     }
 }
+
+/// How a token is named in syntax errors, like `` `;` `` or `the end of the file`.
+fn describe(ty: &TokenType) -> String {
+    match ty {
+        TokenType::EndOfFile => "the end of the file".to_string(),
+        TokenType::Identifier(name) => format!("`{}`", name),
+        TokenType::Character(char) => format!("`{}`", char),
+        TokenType::Number { .. } => "a number".to_string(),
+        TokenType::StaticString(_) => "a string".to_string(),
+        TokenType::CharLiteral(_) => "a character".to_string(),
+        TokenType::Semicolon => "`;`".to_string(),
+        _ => SYMBOL_LIST
+            .iter()
+            .chain(IDENTIFIER_LIST.iter())
+            .find(|(_, token)| *token == ty)
+            .map_or_else(|| format!("{:?}", ty), |(text, _)| format!("`{}`", text)),
+    }
+}
@@ -0,0 +1,285 @@
+use std::{cmp::Ordering, fmt::Display};
+
+/// An arbitrary-precision natural number, in base 2^32 digits from the least
+/// significant one, without leading zero digits.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Natural(Vec<u32>);
+
+impl Natural {
+    /// Reads `digits` in `radix`, `None` if one of them isn't a digit of it.
+    pub fn from_digits(digits: &str, radix: u32) -> Option<Self> {
+        let mut result = Natural::default();
+
+        for char in digits.chars() {
+            result.multiply_add(radix, char.to_digit(radix)?);
+        }
+
+        Some(result)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `self * factor + addend`, in place.
+    pub fn multiply_add(&mut self, factor: u32, addend: u32) {
+        let mut carry = addend as u64;
+
+        for digit in &mut self.0 {
+            let value = *digit as u64 * factor as u64 + carry;
+            *digit = value as u32;
+            carry = value >> 32;
+        }
+
+        if carry != 0 {
+            self.0.push(carry as u32);
+        }
+
+        self.normalize();
+    }
+
+    pub fn multiply(&self, other: &Natural) -> Self {
+        let mut result = vec![0u32; self.0.len() + other.0.len()];
+
+        for (i, left) in self.0.iter().enumerate() {
+            let mut carry = 0u64;
+
+            for (j, right) in other.0.iter().enumerate() {
+                let value = *left as u64 * *right as u64 + result[i + j] as u64 + carry;
+                result[i + j] = value as u32;
+                carry = value >> 32;
+            }
+
+            result[i + other.0.len()] = carry as u32;
+        }
+
+        let mut result = Natural(result);
+        result.normalize();
+
+        result
+    }
+
+    /// `(self / divisor, self % divisor)`.
+    pub fn divide(&self, divisor: u32) -> (Self, u32) {
+        let mut quotient = vec![0; self.0.len()];
+        let mut remainder = 0u64;
+
+        for (i, digit) in self.0.iter().enumerate().rev() {
+            let value = (remainder << 32) | *digit as u64;
+            quotient[i] = (value / divisor as u64) as u32;
+            remainder = value % divisor as u64;
+        }
+
+        let mut quotient = Natural(quotient);
+        quotient.normalize();
+
+        (quotient, remainder as u32)
+    }
+
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.0.len() > 4 {
+            return None;
+        }
+
+        Some(
+            self.0
+                .iter()
+                .rev()
+                .fold(0, |result, digit| (result << 32) | *digit as u128),
+        )
+    }
+
+    fn normalize(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+}
+
+impl Ord for Natural {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+impl PartialOrd for Natural {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Natural {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        let mut chunks = vec![];
+        let mut rest = self.clone();
+
+        while !rest.is_zero() {
+            let (quotient, chunk) = rest.divide(1_000_000_000);
+            chunks.push(chunk);
+            rest = quotient;
+        }
+
+        write!(f, "{}", chunks.pop().unwrap())?;
+
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The exact value of a number literal, `mantissa * 10^exponent`, kept with
+/// no trailing zeros in the mantissa so equal values compare equal.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Exact {
+    pub mantissa: Natural,
+    pub exponent: i64,
+}
+
+impl Exact {
+    pub fn new(mut mantissa: Natural, mut exponent: i64) -> Self {
+        if mantissa.is_zero() {
+            return Self::default();
+        }
+
+        loop {
+            let (quotient, remainder) = mantissa.divide(10);
+
+            if remainder != 0 {
+                break;
+            }
+
+            mantissa = quotient;
+            exponent += 1;
+        }
+
+        Self { mantissa, exponent }
+    }
+
+    pub fn integer(&self) -> Option<i128> {
+        if self.exponent < 0 {
+            return None;
+        }
+
+        let mut result = i128::try_from(self.mantissa.to_u128()?).ok()?;
+
+        for _ in 0..self.exponent {
+            result = result.checked_mul(10)?;
+        }
+
+        Some(result)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.exponent >= 0
+    }
+
+    /// `self / other`, rounded toward zero, and whether it's exact. `None` when
+    /// `other` is zero or the quotient doesn't fit in an `i128`.
+    pub fn divide(&self, other: &Exact) -> Option<(i128, bool)> {
+        let mut numerator = i128::try_from(self.mantissa.to_u128()?).ok()?;
+        let mut denominator = i128::try_from(other.mantissa.to_u128()?).ok()?;
+
+        if denominator == 0 {
+            return None;
+        }
+
+        let shift = self.exponent.checked_sub(other.exponent)?;
+        let power = 10i128.checked_pow(u32::try_from(shift.unsigned_abs()).ok()?);
+
+        match (shift >= 0, power) {
+            (true, Some(power)) => numerator = numerator.checked_mul(power)?,
+            (true, None) => return None,
+            (false, Some(power)) => denominator = denominator.checked_mul(power)?,
+            // The denominator is too large, so the quotient is a fraction.
+            (false, None) => return Some((0, numerator == 0)),
+        }
+
+        Some((numerator / denominator, numerator % denominator == 0))
+    }
+
+    /// `count` times `self`.
+    pub fn times(&self, count: u128) -> Self {
+        let count = Exact::from(count);
+
+        Exact::new(
+            self.mantissa.multiply(&count.mantissa),
+            self.exponent + count.exponent,
+        )
+    }
+}
+
+/// Orders by value, so `1e2` comes after `50`.
+impl Ord for Exact {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.mantissa.is_zero(), other.mantissa.is_zero()) {
+            (true, true) => return Ordering::Equal,
+            (true, false) => return Ordering::Less,
+            (false, true) => return Ordering::Greater,
+            (false, false) => {}
+        }
+
+        // The position of the leading digit decides, unless it's the same for both.
+        let magnitude = |value: &Exact| value.mantissa.to_string().len() as i64 + value.exponent;
+
+        magnitude(self).cmp(&magnitude(other)).then_with(|| {
+            let (mut left, mut right) = (self.mantissa.clone(), other.mantissa.clone());
+
+            for _ in other.exponent..self.exponent {
+                left.multiply_add(10, 0);
+            }
+
+            for _ in self.exponent..other.exponent {
+                right.multiply_add(10, 0);
+            }
+
+            left.cmp(&right)
+        })
+    }
+}
+
+impl PartialOrd for Exact {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<u128> for Exact {
+    fn from(value: u128) -> Self {
+        let digits = [96, 64, 32, 0].map(|shift| (value >> shift) as u32);
+        let mut mantissa = Natural(digits.into_iter().rev().collect());
+        mantissa.normalize();
+
+        Self::new(mantissa, 0)
+    }
+}
+
+/// Writes the value in decimal, switching to `e` notation for large exponents
+/// so `1e1000000` doesn't print a million zeros. Both forms are valid C.
+impl Display for Exact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.mantissa.to_string();
+
+        match self.exponent {
+            0 => write!(f, "{}", digits),
+            1..=30 => write!(f, "{}{}", digits, "0".repeat(self.exponent as usize)),
+            -30..=-1 => {
+                let scale = self.exponent.unsigned_abs() as usize;
+
+                match digits.len().checked_sub(scale) {
+                    Some(0) | None => write!(f, "0.{:0>scale$}", digits),
+                    Some(point) => write!(f, "{}.{}", &digits[..point], &digits[point..]),
+                }
+            }
+            exponent => write!(f, "{}e{}", digits, exponent),
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use std::fmt::Display;
+
+use crate::{
+    diagnostic::Diagnostic,
+    lexer::{Lexer, TokenType},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// Spaces and tabs.
+    Whitespace,
+    /// `\n` or `\r\n`.
+    Newline,
+    /// `-- ...`, without its line break.
+    Comment,
+    /// `--- ...`, documenting the declaration after it.
+    DocComment,
+    /// Text the lexer reported and skipped, like a stray `€`.
+    Skipped,
+}
+
+/// Text between tokens that doesn't change the meaning of the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+/// A token with its text as written and the trivia around it. The trailing trivia
+/// runs to the end of the token's line, the rest of what's before the next token
+/// leads that one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    pub ty: TokenType,
+    pub text: String,
+    /// Line, from 0, and column, from 1, like the lexer's.
+    pub begin_location: (usize, usize),
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// The whole file, its declarations and the end of the file.
+    File,
+    /// A declaration or statement up to its `;`, or an enum variant or match arm up to
+    /// its `,`.
+    Statement,
+    /// What's between `begin`, `then`, `else`, `loop`, `enum`, the `is` of a `match` or
+    /// `trait` or the header of an `implement`, and the `end` closing it.
+    Block,
+    /// `(...)` or `[...]`.
+    Group,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+/// The concrete syntax tree of a file, keeping every character of it so it prints back
+/// as it was read. Tools that rewrite code, like the formatter, build on it.
+#[derive(Debug, Clone)]
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl SyntaxTree {
+    /// Lexes and groups the file of `lexer`. It prints back byte for byte when the file
+    /// is valid UTF-8, and whatever its diagnostics.
+    pub fn parse(mut lexer: Lexer) -> Self {
+        lexer.start();
+
+        let input = std::mem::take(&mut lexer.input);
+
+        let mut line_starts = vec![0];
+        for (position, char) in input.iter().enumerate() {
+            if *char == '\n' {
+                line_starts.push(position + 1);
+            }
+        }
+
+        let mut builder = Builder::default();
+        let mut end = 0;
+        let mut leading = vec![];
+
+        for token in lexer.contents {
+            let (line, column) = token.begin_location;
+            let start = match token.ty {
+                TokenType::EndOfFile => input.len(),
+                _ => line_starts[line] + column - 1,
+            };
+            let stop = (start + token.location_size).min(input.len());
+
+            leading.extend(split_trivia(&input[end..start]));
+
+            // Trivia up to the end of the line trails the token.
+            end = next_start(&input, stop);
+
+            let mut trailing = split_trivia(&input[stop..end]);
+            let line_end = trailing
+                .iter()
+                .position(|trivia| trivia.kind == TriviaKind::Newline)
+                .unwrap_or(trailing.len());
+
+            let next_leading = trailing.split_off(line_end);
+
+            builder.push(SyntaxToken {
+                ty: token.ty,
+                text: input[start..stop].iter().collect(),
+                begin_location: token.begin_location,
+                leading: std::mem::replace(&mut leading, next_leading),
+                trailing,
+            });
+        }
+
+        Self {
+            root: builder.finish(),
+            diagnostics: lexer.diagnostics,
+        }
+    }
+}
+
+/// Where the token after `position` starts, skipping the trivia.
+fn next_start(input: &[char], mut position: usize) -> usize {
+    while position < input.len() {
+        let length = trivia_length(&input[position..]).1;
+
+        if length == 0 {
+            break;
+        }
+
+        position += length;
+    }
+
+    position
+}
+
+/// The kind and length of the trivia at the start of `text`, of length zero if there's
+/// a token instead.
+fn trivia_length(text: &[char]) -> (TriviaKind, usize) {
+    let line_length = text
+        .iter()
+        .position(|char| *char == '\n')
+        .unwrap_or(text.len());
+    let line_length = match text[..line_length].last() {
+        Some('\r') => line_length - 1,
+        _ => line_length,
+    };
+
+    match text {
+        ['\r', '\n', ..] => (TriviaKind::Newline, 2),
+        ['\n', ..] => (TriviaKind::Newline, 1),
+        [' ' | '\t' | '\r', ..] => {
+            let length = text
+                .iter()
+                .enumerate()
+                .take_while(|(i, char)| {
+                    matches!(char, ' ' | '\t') || **char == '\r' && text.get(i + 1) != Some(&'\n')
+                })
+                .count();
+
+            (TriviaKind::Whitespace, length.max(1))
+        }
+        ['-', '-', '-', next, ..] if *next != '-' => (TriviaKind::DocComment, line_length),
+        ['-', '-', '-'] => (TriviaKind::DocComment, 3),
+        ['-', '-', ..] => (TriviaKind::Comment, line_length),
+        _ => (TriviaKind::Skipped, 0),
+    }
+}
+
+/// Splits the text between two tokens into trivia.
+fn split_trivia(mut text: &[char]) -> Vec<Trivia> {
+    let mut result: Vec<Trivia> = vec![];
+
+    while !text.is_empty() {
+        let (kind, length) = match trivia_length(text) {
+            (_, 0) => (TriviaKind::Skipped, 1),
+            trivia => trivia,
+        };
+
+        let (trivia, rest) = text.split_at(length);
+
+        match result.last_mut() {
+            Some(last) if kind == TriviaKind::Skipped && last.kind == kind => {
+                last.text.extend(trivia)
+            }
+            _ => result.push(Trivia {
+                kind,
+                text: trivia.iter().collect(),
+            }),
+        }
+
+        text = rest;
+    }
+
+    result
+}
+
+/// Nests the tokens of a file into nodes, following the keywords that open and close
+/// blocks like the parser does, without checking the rest of the grammar.
+#[derive(Debug, Default)]
+struct Builder {
+    /// The open nodes, with the token that opened each block.
+    stack: Vec<(NodeKind, Vec<SyntaxElement>, Option<TokenType>)>,
+    /// The depth of a `match` or `trait` whose `is` opens a block.
+    block_is: Option<usize>,
+    /// The depth of an `implement` whose header is being read.
+    implement: Option<usize>,
+    previous: Option<TokenType>,
+}
+
+impl Builder {
+    fn push(&mut self, token: SyntaxToken) {
+        if self.stack.is_empty() {
+            self.open(NodeKind::File, None);
+        }
+
+        let ty = token.ty.clone();
+        let after_end = self.previous == Some(TokenType::End);
+        self.previous = Some(ty.clone());
+
+        // The header of an `implement` ends with its first definition or its `end`.
+        if self.implement == Some(self.stack.len())
+            && matches!(
+                ty,
+                TokenType::Public | TokenType::Procedure | TokenType::Function | TokenType::End
+            )
+        {
+            self.implement = None;
+            self.open(NodeKind::Block, Some(TokenType::Implement));
+        }
+
+        match ty {
+            TokenType::Character(')' | ']') if self.is_open(NodeKind::Group) => {
+                self.close_to(NodeKind::Group);
+                self.append(token);
+                self.close();
+            }
+            TokenType::End | TokenType::ElsIf | TokenType::Else
+                if self.is_open(NodeKind::Block) =>
+            {
+                self.close_to(NodeKind::Block);
+                self.close();
+                self.append(token);
+
+                if ty == TokenType::Else {
+                    self.open(NodeKind::Block, Some(ty));
+                }
+            }
+            TokenType::EndOfFile => {
+                self.close_to(NodeKind::File);
+                self.append(token);
+            }
+            _ => {
+                if matches!(self.top(), NodeKind::File | NodeKind::Block) {
+                    self.open(NodeKind::Statement, None);
+                }
+
+                if matches!(ty, TokenType::Character('(' | '[')) {
+                    self.open(NodeKind::Group, None);
+                }
+
+                let depth = self.stack.len();
+                self.append(token);
+
+                match ty {
+                    TokenType::Begin | TokenType::Then | TokenType::Enum => {
+                        self.open(NodeKind::Block, Some(ty))
+                    }
+                    TokenType::Loop if !after_end => self.open(NodeKind::Block, Some(ty)),
+                    TokenType::Match | TokenType::Trait if !after_end => {
+                        self.block_is = Some(depth)
+                    }
+                    TokenType::Is if self.block_is == Some(depth) => {
+                        self.block_is = None;
+                        self.open(NodeKind::Block, Some(ty));
+                    }
+                    TokenType::Implement if !after_end => self.implement = Some(depth),
+                    TokenType::Semicolon if self.top() == NodeKind::Statement => {
+                        self.block_is = None;
+                        self.close();
+                    }
+                    // Variants and arms end with a `,`.
+                    TokenType::Character(',')
+                        if self.top() == NodeKind::Statement
+                            && matches!(
+                                self.stack.iter().rev().nth(1),
+                                Some((_, _, Some(TokenType::Enum | TokenType::Is)))
+                            ) =>
+                    {
+                        self.close()
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> SyntaxNode {
+        if self.stack.is_empty() {
+            self.open(NodeKind::File, None);
+        }
+
+        self.close_to(NodeKind::File);
+
+        let (kind, children, _) = self.stack.pop().unwrap();
+
+        SyntaxNode { kind, children }
+    }
+
+    fn top(&self) -> NodeKind {
+        self.stack.last().unwrap().0
+    }
+
+    /// Whether a `kind` node is open, inside the innermost group.
+    fn is_open(&self, kind: NodeKind) -> bool {
+        self.stack
+            .iter()
+            .rev()
+            .take_while(|(open, _, _)| *open == kind || *open != NodeKind::Group)
+            .any(|(open, _, _)| *open == kind)
+    }
+
+    fn open(&mut self, kind: NodeKind, opener: Option<TokenType>) {
+        self.stack.push((kind, vec![], opener));
+    }
+
+    fn append(&mut self, token: SyntaxToken) {
+        self.stack
+            .last_mut()
+            .unwrap()
+            .1
+            .push(SyntaxElement::Token(token));
+    }
+
+    /// Closes the innermost node.
+    fn close(&mut self) {
+        let (kind, children, _) = self.stack.pop().unwrap();
+
+        self.stack
+            .last_mut()
+            .unwrap()
+            .1
+            .push(SyntaxElement::Node(SyntaxNode { kind, children }));
+    }
+
+    /// Closes the nodes inside the innermost `kind` one.
+    fn close_to(&mut self, kind: NodeKind) {
+        while self.top() != kind {
+            self.close();
+        }
+    }
+}
+
+impl SyntaxNode {
+    /// The tokens of the node, in order.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut result = vec![];
+
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => result.extend(node.tokens()),
+                SyntaxElement::Token(token) => result.push(token),
+            }
+        }
+
+        result
+    }
+
+    pub fn first_token(&self) -> Option<&SyntaxToken> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.first_token(),
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    /// The doc comments right before the node, without their `---` and the space after
+    /// it, one line each.
+    pub fn documentation(&self) -> Vec<String> {
+        let Some(token) = self.first_token() else {
+            return vec![];
+        };
+
+        let mut result = vec![];
+        let mut blank = false;
+
+        for trivia in &token.leading {
+            match trivia.kind {
+                TriviaKind::DocComment => {
+                    let text = &trivia.text[3..];
+                    result.push(text.strip_prefix(' ').unwrap_or(text).to_string());
+                    blank = false;
+                }
+                // A blank line or a comment separates them from the node.
+                TriviaKind::Newline if blank => result.clear(),
+                TriviaKind::Newline => blank = true,
+                TriviaKind::Comment | TriviaKind::Skipped => result.clear(),
+                TriviaKind::Whitespace => {}
+            }
+        }
+
+        result
+    }
+}
+
+impl Display for Trivia {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl Display for SyntaxToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for trivia in &self.leading {
+            write!(f, "{}", trivia)?;
+        }
+
+        write!(f, "{}", self.text)?;
+
+        for trivia in &self.trailing {
+            write!(f, "{}", trivia)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token)?;
+        }
+
+        Ok(())
+    }
+}